@@ -10,10 +10,13 @@
 //! 
 //! # I/O Sources
 //! 
-//! This module contains 2 different types representing different I/O sources implementing the [`IOHandler`] trait and 1 
-//! retroactive implementation of an existing type. They are:
+//! This module contains 3 different types representing different I/O sources implementing the [`IOHandler`] trait, 1
+//! adapter for any existing [`Read`](std::io::Read) + [`Seek`](std::io::Seek) stream, and 1 retroactive implementation
+//! of an existing type. They are:
 //! - [`FileMem`]
+//! - [`FileMemReadOnly`]
 //! - [`FileNull`]
+//! - [`StreamIO`]
 //! - [`std::fs::File`]
 //! 
 //! # Endianness Helper Functions
@@ -33,13 +36,18 @@ pub mod little_endian;
 pub mod big_endian;
 mod file_null;
 mod file_mem;
+mod file_mem_read_only;
 mod file_file;
+mod stream_io;
 
 use std::io::SeekFrom;
 
 pub use io_handler::IOHandler;
+pub(crate) use io_handler::{f64_to_s15f16, f64_to_u8f8, s15f16_to_f64, u8f8_to_f64};
 pub use file_null::FileNull;
 pub use file_mem::FileMem;
+pub use file_mem_read_only::FileMemReadOnly;
+pub use stream_io::StreamIO;
 
 #[cfg(feature = "use_little_endian")]
 pub use little_endian::adjust_endianness_16;
@@ -12,7 +12,7 @@ impl IOHandler for File {
         Ok(())
     }
 
-    fn close(self) -> Result<()> {
+    fn close(self: Box<Self>) -> Result<()> {
         self.sync_data()?;
         drop(self);
         Ok(())
@@ -99,7 +99,7 @@ mod test {
         let mut file = File::create(filename.clone())?;
 
         file.write_all(&[42u8, 69u8, 123u8, 7u8, 255u8])?;
-        file.close()?;
+        Box::new(file).close()?;
 
         let mut handler = File::open(filename)?;
         let mut buf = [0u8];
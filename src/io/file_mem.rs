@@ -83,7 +83,7 @@ where
         Ok(())
     }
 
-    fn close(self) -> Result<()> {
+    fn close(self: Box<Self>) -> Result<()> {
         Ok(())
     }
 
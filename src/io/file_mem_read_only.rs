@@ -0,0 +1,208 @@
+use std::{
+    convert::AsRef,
+    fmt::Debug,
+    io::{Error, ErrorKind, Result, SeekFrom},
+};
+
+use super::IOHandler;
+
+/// A `FileMemReadOnly` object provides read-only I/O over borrowed or shared in-memory bytes.
+///
+/// Unlike [`FileMem`](super::FileMem), a `FileMemReadOnly` object does not require its buffer to be writable, so it can
+/// wrap anything implementing <code>[AsRef]<\[u8]></code> without copying, such as:
+/// - <code>[&\[u8\]][bytes]</code> (including memory-mapped or embedded data)
+/// - <code>[Arc]<\[u8\]></code>
+/// - <code>[Rc]<\[u8\]></code>
+/// - <code>[Vec]\<u8></code>
+///
+/// Any attempt to [`write`](IOHandler::write) into a `FileMemReadOnly` object returns an [`ErrorKind::PermissionDenied`]
+/// error and leaves the position unchanged.
+///
+/// [bytes]: std::slice "slice"
+/// [Arc]: std::sync::Arc
+/// [Rc]: std::rc::Rc
+///
+/// # Examples
+/// ```
+/// use lcms2::io::{FileMemReadOnly, IOHandler};
+/// use std::io::SeekFrom;
+///
+/// let buf = [42u8, 69, 123, 7, 255];
+///
+/// let mut file = FileMemReadOnly::new(buf.as_slice());
+///
+/// file.seek(SeekFrom::Start(3)).unwrap();
+/// assert_eq!(file.read_u8().unwrap(), 7);
+///
+/// assert!(file.write_u8(0).is_err());
+/// ```
+#[derive(Debug)]
+pub struct FileMemReadOnly<T>
+where
+    T: AsRef<[u8]>,
+{
+    pub(crate) data: T,
+    pub(crate) pointer: usize,
+}
+
+impl<T> FileMemReadOnly<T>
+where
+    T: AsRef<[u8]>,
+{
+    /// Creates a new `FileMemReadOnly` object over the provided in-memory buffer.
+    ///
+    /// The initial position is `0`.
+    ///
+    /// # Examples
+    /// ```
+    /// use lcms2::io::FileMemReadOnly;
+    /// use std::sync::Arc;
+    ///
+    /// let shared: Arc<[u8]> = Arc::from(vec![0u8; 128]);
+    /// let file = FileMemReadOnly::new(shared.clone());
+    /// ```
+    pub fn new(data: T) -> FileMemReadOnly<T> {
+        FileMemReadOnly { data, pointer: 0 }
+    }
+
+    /// Consumes this `FileMemReadOnly` object, returning the underlying buffer.
+    pub fn into_inner(self) -> T {
+        self.data
+    }
+}
+
+impl<T> IOHandler for FileMemReadOnly<T>
+where
+    T: AsRef<[u8]> + Debug,
+{
+    fn read(&mut self, buf: &mut [u8]) -> Result<()> {
+        let data = self.data.as_ref();
+        let len = buf.len();
+
+        if self.pointer > data.len() || data.len() - self.pointer < len {
+            return Err(Error::new(
+                ErrorKind::UnexpectedEof,
+                "read from memory error: got fewer bytes than requested",
+            ));
+        }
+
+        buf.copy_from_slice(&data[self.pointer..self.pointer + len]);
+        self.pointer += len;
+
+        Ok(())
+    }
+
+    fn seek(&mut self, pos: SeekFrom) -> Result<()> {
+        let (base, offset) = match pos {
+            SeekFrom::Start(value) => (0u64, value as i64),
+            SeekFrom::End(value) => (self.data.as_ref().len() as u64, value),
+            SeekFrom::Current(value) => (self.pointer as u64, value),
+        };
+
+        match base.checked_add_signed(offset) {
+            Some(value) => {
+                self.pointer = value as usize;
+                Ok(())
+            }
+            None => Err(Error::new(
+                ErrorKind::InvalidInput,
+                "invalid seek to a negative or overflowing position",
+            )),
+        }
+    }
+
+    fn close(self: Box<Self>) -> Result<()> {
+        Ok(())
+    }
+
+    fn tell(&mut self) -> Result<usize> {
+        Ok(self.pointer)
+    }
+
+    fn write(&mut self, _buf: &[u8]) -> Result<()> {
+        Err(Error::new(
+            ErrorKind::PermissionDenied,
+            "write to memory error: the memory block was opened as read-only",
+        ))
+    }
+
+    fn reported_size(&mut self) -> Result<usize> {
+        Ok(self.data.as_ref().len())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        io::{self, ErrorKind, SeekFrom},
+        sync::Arc,
+    };
+
+    use crate::io::IOHandler;
+
+    use super::FileMemReadOnly;
+
+    #[test]
+    fn test_read_only_mem_reads_shared_data() -> io::Result<()> {
+        let shared: Arc<[u8]> = Arc::from(0x0102_0304u32.to_be_bytes().as_slice());
+        let mut file = FileMemReadOnly::new(shared.clone());
+
+        assert_eq!(file.read_u32()?, 0x0102_0304);
+        assert_eq!(file.tell()?, 4);
+        assert_eq!(Arc::strong_count(&shared), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_only_mem_read_past_end_fails_without_moving() -> io::Result<()> {
+        let buf = [1u8, 2, 3];
+        let mut file = FileMemReadOnly::new(buf.as_slice());
+
+        file.seek(SeekFrom::Start(2))?;
+        let err = file.read_u16().unwrap_err();
+
+        assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+        assert_eq!(file.tell()?, 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_only_mem_rejects_writes() {
+        let buf = [0u8; 4];
+        let mut file = FileMemReadOnly::new(buf.as_slice());
+
+        let err = file.write(&[42]).unwrap_err();
+
+        assert_eq!(err.kind(), ErrorKind::PermissionDenied);
+        assert_eq!(buf, [0u8; 4]);
+    }
+
+    #[test]
+    fn test_read_only_mem_rejects_negative_seek() -> io::Result<()> {
+        let buf = [0u8; 4];
+        let mut file = FileMemReadOnly::new(buf.as_slice());
+
+        assert!(file.seek(SeekFrom::Current(-1)).is_err());
+        assert!(file.seek(SeekFrom::End(-5)).is_err());
+
+        file.seek(SeekFrom::End(-1))?;
+        assert_eq!(file.tell()?, 3);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_only_mem_reported_size_keeps_position() -> io::Result<()> {
+        let buf = [0u8; 10];
+        let mut file = FileMemReadOnly::new(buf.as_slice());
+
+        file.seek(SeekFrom::Start(6))?;
+
+        assert_eq!(file.reported_size()?, 10);
+        assert_eq!(file.tell()?, 6);
+
+        Ok(())
+    }
+}
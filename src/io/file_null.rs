@@ -75,7 +75,7 @@ impl IOHandler for FileNull {
         Ok(())
     }
 
-    fn close(self) -> Result<()> {
+    fn close(self: Box<Self>) -> Result<()> {
         Ok(())
     }
 
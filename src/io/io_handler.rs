@@ -15,7 +15,9 @@ type Result<T> = std::io::Result<T>;
 /// # Implemented Types
 /// This crate implentes `IOHandler` on the following types:
 /// - [`FileMem`]
+/// - [`FileMemReadOnly`]
 /// - [`FileNull`]
+/// - [`StreamIO`], for any other [`Read`](std::io::Read) + [`Seek`](std::io::Seek) stream
 /// - [`std::fs::File`]
 pub trait IOHandler: Debug {
     /// Pulls the exact number of bytes from this source required to fill `buf`.
@@ -86,6 +88,9 @@ pub trait IOHandler: Debug {
     /// handling upon closing I/O. [`FileMem`] and [`FileNull`] have nothing special to do when dropping, and [`File`]
     /// automatically handles it's closing via [`Drop`].
     /// 
+    /// `close()` takes a boxed receiver so that it can also be called on a <code>[Box]<dyn IOHandler></code>, such as
+    /// the one owned by a [`Profile`](crate::types::Profile).
+    /// 
     /// [`File`]: std::fs::File
     /// 
     /// # Examples
@@ -95,13 +100,13 @@ pub trait IOHandler: Debug {
     /// use std::fs::File;
     /// use lcms2::io::IOHandler;
     ///
-    /// let mut file = File::create("filename.ext").unwrap();
+    /// let mut file: Box<dyn IOHandler> = Box::new(File::create("filename.ext").unwrap());
     /// file.close(); //consumes, drops, and closes the file
     ///
     /// file.write(&[0u8]).unwrap(); //fails to compile
     /// /* borrow of moved value: `file` */
     /// ```
-    fn close(self) -> Result<()>;
+    fn close(self: Box<Self>) -> Result<()>;
 
     /// Returns the current seek position from the start of the stream.
     ///
@@ -318,6 +323,53 @@ pub trait IOHandler: Debug {
         Ok(CIEXYZ { X: x, Y: y, Z: z })
     }
 
+    /// Reads the base of a tag type: its type signature followed by 4 reserved bytes.
+    /// 
+    /// ```rust
+    /// use lcms2::io::{FileMem, IOHandler};
+    /// use lcms2::types::Signature;
+    /// 
+    /// let mut buf = *b"XYZ \0\0\0\0";
+    /// let mut mem = FileMem::new(buf.as_mut_slice());
+    /// 
+    /// assert_eq!(mem.read_type_base().unwrap(), Signature::new(b"XYZ "));
+    /// assert_eq!(mem.tell().unwrap(), 8);
+    /// ```
+    fn read_type_base(&mut self) -> Result<Signature> {
+        let sig = self.read_u32()?;
+        self.read_u32()?; // Reserved
+
+        Ok(Signature::from(sig))
+    }
+
+    /// Skips forward to the next 32 bit aligned position.
+    /// 
+    /// ```rust
+    /// use lcms2::io::{FileMem, IOHandler};
+    /// 
+    /// let mut buf = [0u8; 8];
+    /// let mut mem = FileMem::new(buf.as_mut_slice());
+    /// 
+    /// mem.read_u8().unwrap();
+    /// mem.read_alignment().unwrap();
+    /// assert_eq!(mem.tell().unwrap(), 4);
+    /// 
+    /// mem.read_alignment().unwrap(); // already aligned
+    /// assert_eq!(mem.tell().unwrap(), 4);
+    /// ```
+    fn read_alignment(&mut self) -> Result<()> {
+        let at = self.tell()?;
+        let next_aligned = align_32(at);
+        let bytes_to_next_aligned = next_aligned - at;
+
+        if bytes_to_next_aligned == 0 {
+            return Ok(());
+        }
+
+        let mut buf = [0u8; 4];
+        self.read(&mut buf[..bytes_to_next_aligned])
+    }
+
     /// Writes a u8 value.
     /// 
     /// ```rust
@@ -483,9 +535,58 @@ pub trait IOHandler: Debug {
         self.write_s15f16(value.Y)?;
         self.write_s15f16(value.Z)
     }
+
+    /// Writes the base of a tag type: its type signature followed by 4 reserved bytes.
+    /// 
+    /// ```rust
+    /// use lcms2::io::{FileMem, IOHandler};
+    /// use lcms2::types::Signature;
+    /// 
+    /// let mut buf = Vec::new();
+    /// let mut mem = FileMem::new(&mut buf);
+    /// 
+    /// mem.write_type_base(Signature::new(b"XYZ ")).unwrap();
+    /// 
+    /// assert_eq!(buf, *b"XYZ \0\0\0\0");
+    /// ```
+    fn write_type_base(&mut self, sig: Signature) -> Result<()> {
+        self.write_u32(sig.into())?;
+        self.write_u32(0) // Reserved
+    }
+
+    /// Writes zeros up to the next 32 bit aligned position.
+    /// 
+    /// ```rust
+    /// use lcms2::io::{FileMem, IOHandler};
+    /// 
+    /// let mut buf = Vec::new();
+    /// let mut mem = FileMem::new(&mut buf);
+    /// 
+    /// mem.write_u8(42).unwrap();
+    /// mem.write_alignment().unwrap();
+    /// 
+    /// assert_eq!(buf, [42, 0, 0, 0]);
+    /// ```
+    fn write_alignment(&mut self) -> Result<()> {
+        let at = self.tell()?;
+        let next_aligned = align_32(at);
+        let bytes_to_next_aligned = next_aligned - at;
+
+        if bytes_to_next_aligned == 0 {
+            return Ok(());
+        }
+
+        let buf = [0u8; 4];
+        self.write(&buf[..bytes_to_next_aligned])
+    }
 }
 
-fn s15f16_to_f64(value: S15F16) -> f64 {
+/// Rounds `value` up to the next multiple of 4.
+pub(crate) fn align_32(value: usize) -> usize {
+    (value + 3) & !3
+}
+
+pub(crate) fn s15f16_to_f64(value: S15F16) -> f64 {
     let sign = if value < 0 { -1.0 } else { 1.0 };
     let value = value.abs();
 
@@ -498,6 +599,17 @@ fn s15f16_to_f64(value: S15F16) -> f64 {
     return sign * floater;
 }
 
-fn f64_to_s15f16(value: f64) -> S15F16 {
+pub(crate) fn f64_to_s15f16(value: f64) -> S15F16 {
     ((value * 65536.0) + 0.5).floor() as S15F16
 }
+
+pub(crate) fn u8f8_to_f64(value: U8F8) -> f64 {
+    let msb = (value >> 8) & 0xFF;
+    let lsb = value & 0xFF;
+
+    msb as f64 + (lsb as f64 / 256.0)
+}
+
+pub(crate) fn f64_to_u8f8(value: f64) -> U8F8 {
+    ((f64_to_s15f16(value) >> 8) & 0xFFFF) as U8F8
+}
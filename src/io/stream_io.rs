@@ -0,0 +1,310 @@
+use std::{
+    fmt::{self, Debug, Formatter},
+    io::{Error, ErrorKind, Read, Result, Seek, SeekFrom, Write},
+};
+
+use super::IOHandler;
+
+type WriteFn<R> = fn(&mut R, &[u8]) -> Result<()>;
+type FlushFn<R> = fn(&mut R) -> Result<()>;
+
+/// A `StreamIO` object adapts any [`Read`] + [`Seek`] stream into an [`IOHandler`].
+///
+/// This allows things like a [`BufReader`], an entry inside of an archive, or a custom decrypting reader to back a
+/// [`Profile`]. A `StreamIO` object created with [`new`] is read-only, and any attempt to [`write`] into it returns an
+/// [`ErrorKind::PermissionDenied`] error. The write half is only enabled through [`new_writable`], which requires the
+/// wrapped stream to also implement [`Write`].
+///
+/// [`BufReader`]: std::io::BufReader
+/// [`Profile`]: crate::types::Profile
+/// [`new`]: StreamIO::new
+/// [`new_writable`]: StreamIO::new_writable
+/// [`write`]: IOHandler::write
+///
+/// # Examples
+/// ```
+/// use lcms2::io::{IOHandler, StreamIO};
+/// use std::io::{BufReader, Cursor};
+///
+/// let reader = BufReader::new(Cursor::new(vec![0u8, 0, 0, 42]));
+/// let mut stream = StreamIO::new(reader).unwrap();
+///
+/// assert_eq!(stream.read_u32().unwrap(), 42);
+/// assert!(stream.write_u8(0).is_err());
+/// ```
+pub struct StreamIO<R>
+where
+    R: Read + Seek,
+{
+    inner: R,
+    /// Position of the inner stream this object was created at, which all positions are relative to.
+    base: u64,
+    write: Option<WriteFn<R>>,
+    flush: Option<FlushFn<R>>,
+}
+
+impl<R> StreamIO<R>
+where
+    R: Read + Seek,
+{
+    /// Creates a new read-only `StreamIO` object wrapping the provided stream.
+    ///
+    /// The stream is used from its current position, which becomes position 0 of the `StreamIO` object. Reading a
+    /// profile embedded at some offset inside of a larger stream only requires seeking the stream there first.
+    ///
+    /// Fails if the current position of the stream can't be queried.
+    ///
+    /// # Examples
+    /// ```
+    /// use lcms2::io::{IOHandler, StreamIO};
+    /// use std::io::{Cursor, Seek, SeekFrom};
+    ///
+    /// let mut cursor = Cursor::new([0u8, 1, 2, 3]);
+    /// cursor.seek(SeekFrom::Start(2)).unwrap();
+    ///
+    /// let mut stream = StreamIO::new(cursor).unwrap();
+    /// assert_eq!(stream.tell().unwrap(), 0);
+    /// assert_eq!(stream.read_u8().unwrap(), 2);
+    /// ```
+    pub fn new(mut inner: R) -> Result<StreamIO<R>> {
+        let base = inner.stream_position()?;
+
+        Ok(StreamIO {
+            inner,
+            base,
+            write: None,
+            flush: None,
+        })
+    }
+
+    /// Returns `true` if this `StreamIO` object was created with its write half enabled.
+    pub fn is_writable(&self) -> bool {
+        self.write.is_some()
+    }
+
+    /// Gets a reference to the underlying stream.
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// Gets a mutable reference to the underlying stream.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    /// Consumes this `StreamIO` object, returning the underlying stream.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R> StreamIO<R>
+where
+    R: Read + Seek + Write,
+{
+    /// Creates a new `StreamIO` object wrapping the provided stream with both its read and write halves enabled.
+    ///
+    /// As with [`new`](StreamIO::new), the stream is used from its current position.
+    ///
+    /// # Examples
+    /// ```
+    /// use lcms2::io::{IOHandler, StreamIO};
+    /// use std::io::Cursor;
+    ///
+    /// let mut stream = StreamIO::new_writable(Cursor::new(Vec::new())).unwrap();
+    /// stream.write_u16(42).unwrap();
+    ///
+    /// assert_eq!(stream.into_inner().into_inner(), vec![0, 42]);
+    /// ```
+    pub fn new_writable(mut inner: R) -> Result<StreamIO<R>> {
+        let base = inner.stream_position()?;
+
+        Ok(StreamIO {
+            inner,
+            base,
+            write: Some(<R as Write>::write_all),
+            flush: Some(<R as Write>::flush),
+        })
+    }
+}
+
+// The wrapped stream is opaque, so that streams without a `Debug` implementation can be used as well
+impl<R> Debug for StreamIO<R>
+where
+    R: Read + Seek,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StreamIO")
+            .field("base", &self.base)
+            .field("writable", &self.is_writable())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<R> IOHandler for StreamIO<R>
+where
+    R: Read + Seek,
+{
+    fn read(&mut self, buf: &mut [u8]) -> Result<()> {
+        self.inner.read_exact(buf)
+    }
+
+    fn seek(&mut self, pos: SeekFrom) -> Result<()> {
+        let pos = match pos {
+            SeekFrom::Start(offset) => SeekFrom::Start(self.base + offset),
+            pos => pos,
+        };
+        self.inner.seek(pos)?;
+        Ok(())
+    }
+
+    fn close(mut self: Box<Self>) -> Result<()> {
+        match self.flush {
+            Some(flush) => flush(&mut self.inner),
+            None => Ok(()),
+        }
+    }
+
+    fn tell(&mut self) -> Result<usize> {
+        let pos = self.inner.stream_position()?;
+        pos.checked_sub(self.base)
+            .map(|pos| pos as usize)
+            .ok_or_else(|| {
+                Error::new(
+                    ErrorKind::InvalidInput,
+                    "tell stream error: the stream is positioned before its start",
+                )
+            })
+    }
+
+    fn write(&mut self, buf: &[u8]) -> Result<()> {
+        match self.write {
+            Some(write) => write(&mut self.inner, buf),
+            None => Err(Error::new(
+                ErrorKind::PermissionDenied,
+                "write to stream error: the stream was opened as read-only",
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        fs::File,
+        io::{self, BufReader, Cursor, ErrorKind, Seek, SeekFrom},
+    };
+
+    use crate::{
+        io::IOHandler,
+        state::Context,
+        testing::get_test_resource_path,
+        types::{signatures, Profile, TagValue},
+    };
+
+    use super::StreamIO;
+
+    #[test]
+    fn test_stream_reads_from_buffered_file() -> io::Result<()> {
+        let file = File::open(get_test_resource_path("sRGB_v4_ICC_preference.icc"))?;
+        let mut stream = StreamIO::new(BufReader::new(file))?;
+
+        stream.seek(SeekFrom::Start(36))?;
+        let mut magic = [0u8; 4];
+        stream.read(&mut magic)?;
+
+        assert_eq!(&magic, b"acsp");
+        assert_eq!(stream.tell()?, 40);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_only_stream_rejects_writes() {
+        let mut stream = StreamIO::new(Cursor::new(vec![0u8; 4])).unwrap();
+
+        let err = stream.write(&[42]).unwrap_err();
+
+        assert!(!stream.is_writable());
+        assert_eq!(err.kind(), ErrorKind::PermissionDenied);
+        assert_eq!(stream.into_inner().into_inner(), vec![0u8; 4]);
+    }
+
+    #[test]
+    fn test_writable_stream_writes_and_reads_back() -> io::Result<()> {
+        let mut stream = StreamIO::new_writable(Cursor::new(Vec::new()))?;
+
+        stream.write_u32(0x0102_0304)?;
+        stream.seek(SeekFrom::Start(0))?;
+
+        assert!(stream.is_writable());
+        assert_eq!(stream.read_u32()?, 0x0102_0304);
+        assert_eq!(stream.reported_size()?, 4);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_stream_positions_are_relative_to_its_start() -> io::Result<()> {
+        let mut cursor = Cursor::new(vec![1u8, 2, 3, 4, 5, 6]);
+        cursor.seek(SeekFrom::Start(2))?;
+        let mut stream = StreamIO::new(cursor)?;
+
+        stream.seek(SeekFrom::Start(1))?;
+        assert_eq!(stream.read_u8()?, 4);
+        assert_eq!(stream.tell()?, 2);
+        assert_eq!(stream.reported_size()?, 4);
+
+        stream.seek(SeekFrom::Current(-3))?;
+        assert!(stream.tell().is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_stream_opens_embedded_profile() -> io::Result<()> {
+        let mut context = Context::new(None);
+        let profile = std::fs::read(get_test_resource_path("sRGB_v4_ICC_preference.icc"))?;
+
+        let mut data = vec![0xaa; 100];
+        data.extend_from_slice(&profile);
+        data.extend_from_slice(&[0x55; 10]);
+        let mut cursor = Cursor::new(data);
+        cursor.seek(SeekFrom::Start(100))?;
+
+        let mut profile =
+            Profile::open_from_io_thr(&mut context, Box::new(StreamIO::new(cursor)?))?;
+
+        assert_eq!(profile.get_tag_count(), 9);
+        assert!(profile.verify_id_thr(&mut context)?);
+        assert!(matches!(
+            profile.read_tag_thr(&mut context, signatures::tag::MEDIA_WHITE_POINT)?,
+            TagValue::XYZ(_)
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_stream_without_debug_is_an_io_handler() -> io::Result<()> {
+        struct Opaque(Cursor<Vec<u8>>);
+        impl io::Read for Opaque {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                self.0.read(buf)
+            }
+        }
+        impl Seek for Opaque {
+            fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+                self.0.seek(pos)
+            }
+        }
+
+        let mut stream: Box<dyn IOHandler> =
+            Box::new(StreamIO::new(Opaque(Cursor::new(vec![0, 42])))?);
+
+        assert_eq!(stream.read_u16()?, 42);
+        assert!(format!("{:?}", stream).starts_with("StreamIO"));
+
+        Ok(())
+    }
+}
@@ -47,9 +47,31 @@ pub mod plugins;
 pub mod state;
 pub mod types;
 
+mod md5;
+
 /// The version/release of lcms2 implemented. (2.13.1)
 pub const LCMS_VERSION: u32 = 2131;
 const MATRIX_DET_TOLERANCE: f64 = 0.0001;
 
+/// Rounds and saturates `d` to the range of a [`u16`].
+pub(crate) fn quick_saturate_word(d: f64) -> u16 {
+    let d = d + 0.5;
+    if d <= 0.0 {
+        return 0;
+    }
+    if d >= 65535.0 {
+        return 0xffff;
+    }
+
+    d.floor() as u16
+}
+
+/// D50 X component, normalized to Y = 1.0
+pub const D50_X: f64 = 0.9642;
+/// D50 Y component, normalized to Y = 1.0
+pub const D50_Y: f64 = 1.0;
+/// D50 Z component, normalized to Y = 1.0
+pub const D50_Z: f64 = 0.8249;
+
 #[cfg(test)]
 mod testing;
@@ -23,6 +23,7 @@ pub use interp::MAX_INPUT_DIMENTIONS;
 pub use optimization::OPToptimizeFn;
 pub use optimization::OptimizationCollection;
 pub use optimization::OptimizationCollectionItem;
+pub(crate) use para_curve::get_parametric_curve_by_type;
 pub use para_curve::ParametricCurveEvaluator;
 pub use para_curve::ParametricCurvesCollection;
 pub use para_curve::MAX_NODES_IN_CURVE;
@@ -30,10 +31,13 @@ pub use para_curve::MINUS_INF;
 pub use para_curve::PLUS_INF;
 pub use plugin::Plugin;
 pub use plugin::PluginType;
+pub(crate) use tag::get_tag_descriptor;
 pub use tag::TagDescriptor;
 pub use tag::TagList;
 pub use tag::TagListItem;
 pub use tag::TagTypeDecoder;
+pub(crate) use tag_type::get_mpe_type_handler;
+pub(crate) use tag_type::get_tag_type_handler;
 pub use tag_type::TagTypeList;
 pub use tag_type::TagTypeReader;
 pub use tag_type::TagTypeWriter;
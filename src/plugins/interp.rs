@@ -1,5 +1,6 @@
 pub const MAX_INPUT_DIMENTIONS: usize = 15;
 
+#[derive(Clone, Debug)]
 pub enum InterpFunction {
     InterpFn16(fn(input: &[u16], output: &mut [u16], p: InterpParams)),
     InterpFnFloat(fn(input: &[f32], output: &mut [f32], p: InterpParams)),
@@ -8,6 +9,7 @@ pub enum InterpFunction {
 pub type InterpFnFactory =
     fn(input_channels: u32, output_channels: u32, flags: u32) -> InterpFunction;
 
+#[derive(Clone, Debug)]
 pub struct InterpParams {
     flags: u32,
     inputs: u32,
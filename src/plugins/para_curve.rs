@@ -1,6 +1,8 @@
 use std::fmt::Debug;
 
-use crate::MATRIX_DET_TOLERANCE;
+use once_cell::sync::Lazy;
+
+use crate::{state::Context, MATRIX_DET_TOLERANCE};
 
 pub type ParametricCurveEvaluator = fn(curve_type: i32, params: &[f64], r: f64) -> f64;
 pub const MAX_NODES_IN_CURVE: usize = 4097;
@@ -90,6 +92,26 @@ impl ParametricCurves {
     }
 }
 
+/// The parametric curves known without any plugin.
+static DEFAULT_CURVES: Lazy<ParametricCurves> = Lazy::new(ParametricCurves::default);
+
+/// Returns the evaluator and the number of parameters of the parametric curve `r#type`, searching the curves
+/// registered by plugins before the built-in ones.
+pub(crate) fn get_parametric_curve_by_type(
+    context: &Context,
+    r#type: i32,
+) -> Option<(ParametricCurveEvaluator, u32)> {
+    context
+        .curves_plugin
+        .parametric_curves
+        .iter()
+        .chain(std::iter::once(&*DEFAULT_CURVES))
+        .find_map(|c| {
+            c.is_in_set(r#type)
+                .map(|pos| (c.evaluator, c.curves[pos].parameter_count))
+        })
+}
+
 pub fn default_eval_parametric_fn(r#type: i32, params: &[f64], r: f64) -> f64 {
     match r#type {
         // Y = X ^ gamma
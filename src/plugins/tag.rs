@@ -1,8 +1,12 @@
 use std::fmt::Debug;
 
-use crate::types::Signature;
+use crate::{
+    state::Context,
+    types::{tag_types::SUPPORTED_TAGS, Signature, TagValue},
+};
 
-pub type TagTypeDecoder = fn(icc_version: f64, data: &[u8]) -> Signature;
+/// Chooses the tag type a value is written as, depending on the version of the profile.
+pub type TagTypeDecoder = fn(icc_version: f64, data: &TagValue) -> Signature;
 pub type TagList = Vec<TagListItem>;
 
 #[derive(Clone, Debug)]
@@ -11,11 +15,53 @@ pub struct TagListItem {
     pub descriptor: TagDescriptor,
 }
 
+/// Describes which tag types a tag may be stored as, and how many elements it must hold.
 #[derive(Clone)]
 pub struct TagDescriptor {
-    element_count: usize,
-    supported_types: Vec<Signature>,
-    decide_type: TagTypeDecoder,
+    pub(crate) element_count: usize,
+    pub(crate) supported_types: Vec<Signature>,
+    pub(crate) decide_type: Option<TagTypeDecoder>,
+}
+
+impl TagDescriptor {
+    /// Creates a new `TagDescriptor`.
+    ///
+    /// `supported_types` lists the tag types the tag may be stored as, in order of preference. `element_count` is the
+    /// minimum number of elements a type handler must read for the tag to be valid. Without `decide_type`, the tag is
+    /// always written as the first supported type.
+    pub fn new(
+        element_count: usize,
+        supported_types: Vec<Signature>,
+        decide_type: Option<TagTypeDecoder>,
+    ) -> Self {
+        Self {
+            element_count,
+            supported_types,
+            decide_type,
+        }
+    }
+
+    pub fn get_element_count(&self) -> usize {
+        self.element_count
+    }
+
+    pub fn get_supported_types(&self) -> &[Signature] {
+        &self.supported_types
+    }
+
+    /// Returns the tag type `data` should be written as in a profile of version `icc_version`, or `None` if the
+    /// descriptor has neither a `decide_type` function nor any supported type.
+    pub fn decide_type(&self, icc_version: f64, data: &TagValue) -> Option<Signature> {
+        match self.decide_type {
+            Some(decide_type) => Some(decide_type(icc_version, data)),
+            None => self.supported_types.first().copied(),
+        }
+    }
+
+    /// Returns `true` if the tag may be stored as `r#type`.
+    pub fn is_type_supported(&self, r#type: Signature) -> bool {
+        self.supported_types.contains(&r#type)
+    }
 }
 
 impl Debug for TagDescriptor {
@@ -27,3 +73,14 @@ impl Debug for TagDescriptor {
             .finish()
     }
 }
+
+/// Returns the descriptor of the tag `sig`, searching the tags registered by plugins before the built-in ones.
+pub(crate) fn get_tag_descriptor(context: &Context, sig: Signature) -> Option<TagDescriptor> {
+    context
+        .tags_plugin
+        .tags
+        .iter()
+        .chain(SUPPORTED_TAGS.iter())
+        .find(|item| item.signature == sig)
+        .map(|item| item.descriptor.clone())
+}
@@ -1,31 +1,65 @@
-use std::fmt::Debug;
+use std::{fmt::Debug, io::Result};
 
-use crate::{io::IOHandler, state::Context, types::Signature};
+use crate::{
+    io::IOHandler,
+    state::Context,
+    types::{
+        tag_types::{SUPPORTED_MPE_TYPES, SUPPORTED_TAG_TYPES},
+        Signature, TagValue,
+    },
+};
 
 pub type TagTypeList = Vec<TypeHandler>;
 
+/// Reads a tag of the handler's type from `io`, positioned right after the type base.
+///
+/// `size_of_tag` is the size of the tag data without the type base, and `num_items` receives the number of elements
+/// read.
 pub type TagTypeReader = fn(
     context: &mut Context,
     handler: &TypeHandler,
-    io: &dyn IOHandler,
-    num_items: &mut u32,
+    io: &mut dyn IOHandler,
+    num_items: &mut usize,
     size_of_tag: usize,
-) -> Option<Box<[u8]>>;
+) -> Result<TagValue>;
 
+/// Writes `value` as a tag of the handler's type into `io`, right after the type base.
 pub type TagTypeWriter = fn(
     context: &mut Context,
     handler: &TypeHandler,
-    io: &dyn IOHandler,
-    ptr: &[u8],
+    io: &mut dyn IOHandler,
+    value: &TagValue,
     num_items: usize,
-) -> Option<()>;
+) -> Result<()>;
 
+/// Reads and writes the data of a single tag type.
 #[derive(Clone)]
 pub struct TypeHandler {
-    signature: Signature,
-    icc_version: u32,
-    read: TagTypeReader,
-    write: TagTypeWriter,
+    pub(crate) signature: Signature,
+    pub(crate) icc_version: u32,
+    pub(crate) read: TagTypeReader,
+    pub(crate) write: TagTypeWriter,
+}
+
+impl TypeHandler {
+    /// Creates a new `TypeHandler` for the tag type `signature`.
+    pub fn new(signature: Signature, read: TagTypeReader, write: TagTypeWriter) -> Self {
+        Self {
+            signature,
+            icc_version: 0,
+            read,
+            write,
+        }
+    }
+
+    pub fn get_signature(&self) -> Signature {
+        self.signature
+    }
+
+    /// Returns the encoded version of the profile being read or written.
+    pub fn get_icc_version(&self) -> u32 {
+        self.icc_version
+    }
 }
 
 impl Debug for TypeHandler {
@@ -38,3 +72,26 @@ impl Debug for TypeHandler {
             .finish()
     }
 }
+
+/// Returns the handler of the tag type `sig`, searching the tag types registered by plugins before the built-in ones.
+pub(crate) fn get_tag_type_handler(context: &Context, sig: Signature) -> Option<TypeHandler> {
+    context
+        .tag_types_plugin
+        .tag_types
+        .iter()
+        .chain(SUPPORTED_TAG_TYPES.iter())
+        .find(|handler| handler.signature == sig)
+        .cloned()
+}
+
+/// Returns the handler of the multi process element type `sig`, searching the element types registered by plugins
+/// before the built-in ones.
+pub(crate) fn get_mpe_type_handler(context: &Context, sig: Signature) -> Option<TypeHandler> {
+    context
+        .mpe_types_plugin
+        .tag_types
+        .iter()
+        .chain(SUPPORTED_MPE_TYPES.iter())
+        .find(|handler| handler.signature == sig)
+        .cloned()
+}
@@ -1,8 +1,11 @@
+mod chromaticity;
 mod cie_xyz;
 mod curve_segment;
 mod date_time_number;
+mod dict;
 mod encoded_xyz_number;
 mod icc_header;
+mod measurement;
 mod mlu;
 mod named_color_list;
 mod pipeline;
@@ -11,13 +14,24 @@ mod profile_id;
 mod seq;
 mod signature;
 mod tag_entry;
+pub(crate) mod tag_types;
+mod tag_value;
 mod tone_curve;
+mod viewing_conditions;
 
+pub use chromaticity::Chromaticity;
+pub use chromaticity::ColorantType;
 pub use cie_xyz::CIEXYZ;
 pub use curve_segment::CurveSegment;
 pub use date_time_number::DateTimeNumber;
+pub use dict::Dict;
+pub use dict::DictEntry;
 pub use encoded_xyz_number::EncodedXYZNumber;
 pub use icc_header::ICCHeader;
+pub use measurement::Measurement;
+pub use measurement::MeasurementGeometry;
+pub use measurement::StandardIlluminant;
+pub use measurement::StandardObserver;
 pub use mlu::Mlu;
 pub use mlu::MluEntry;
 pub use named_color_list::NamedColor;
@@ -25,17 +39,24 @@ pub use named_color_list::NamedColorList;
 pub use pipeline::Pipeline;
 pub use pipeline::PipelineEvalFn;
 pub use pipeline::Stage;
+pub use pipeline::StageClutData;
+pub use pipeline::StageData;
 pub use pipeline::StageEvalFn;
+pub use pipeline::StageLoc;
+pub use pipeline::StageMatrixData;
+pub use pipeline::StageToneCurveData;
+pub use pipeline::Tab;
 pub use profile::Profile;
 pub use profile_id::ProfileID;
 pub use seq::Sequence;
 pub use seq::SequenceDescriptor;
 pub use signature::Signature;
 pub use tag_entry::TagEntry;
+pub use tag_value::TagValue;
 pub use tone_curve::ToneCurve;
+pub use viewing_conditions::ViewingConditions;
 
 #[allow(missing_docs)]
 pub mod signatures;
 
-pub const MAX_TABLE_TAG: usize = 100;
 pub const MAX_CHANNELS: usize = 16;
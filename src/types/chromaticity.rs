@@ -0,0 +1,80 @@
+/// The phosphor or colorant set a [`Chromaticity`] describes, ICC.1:2022 table 31.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ColorantType {
+    Unknown,
+    ItuRBt709,
+    SmpteRp145,
+    EbuTech3213,
+    P22,
+    P3,
+    ItuRBt2020,
+    /// A type not defined by the ICC, kept as encoded.
+    Other(u16),
+}
+
+impl From<u16> for ColorantType {
+    fn from(value: u16) -> Self {
+        match value {
+            0 => Self::Unknown,
+            1 => Self::ItuRBt709,
+            2 => Self::SmpteRp145,
+            3 => Self::EbuTech3213,
+            4 => Self::P22,
+            5 => Self::P3,
+            6 => Self::ItuRBt2020,
+            _ => Self::Other(value),
+        }
+    }
+}
+
+impl From<ColorantType> for u16 {
+    fn from(value: ColorantType) -> Self {
+        match value {
+            ColorantType::Unknown => 0,
+            ColorantType::ItuRBt709 => 1,
+            ColorantType::SmpteRp145 => 2,
+            ColorantType::EbuTech3213 => 3,
+            ColorantType::P22 => 4,
+            ColorantType::P3 => 5,
+            ColorantType::ItuRBt2020 => 6,
+            ColorantType::Other(value) => value,
+        }
+    }
+}
+
+/// The chromaticities of the phosphors or colorants of a device, one xy pair per channel.
+///
+/// # Examples
+/// ```
+/// use lcms2::types::{Chromaticity, ColorantType};
+///
+/// let srgb = Chromaticity::new(
+///     ColorantType::ItuRBt709,
+///     &[(0.64, 0.33), (0.30, 0.60), (0.15, 0.06)],
+/// );
+///
+/// assert_eq!(srgb.get_channels()[1], (0.30, 0.60));
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Chromaticity {
+    colorant_type: ColorantType,
+    channels: Vec<(f64, f64)>,
+}
+
+impl Chromaticity {
+    pub fn new(colorant_type: ColorantType, channels: &[(f64, f64)]) -> Self {
+        Self {
+            colorant_type,
+            channels: channels.to_vec(),
+        }
+    }
+
+    pub fn get_colorant_type(&self) -> ColorantType {
+        self.colorant_type
+    }
+
+    /// Returns the x and y chromaticity coordinates of each channel.
+    pub fn get_channels(&self) -> &[(f64, f64)] {
+        &self.channels
+    }
+}
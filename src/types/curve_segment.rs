@@ -1,7 +1,14 @@
+/// A segment of a [`ToneCurve`](super::ToneCurve), covering the domain `x0 < x <= x1`.
+///
+/// A segment is either a parametric function of type `r#type` (see [`ParametricCurveEvaluator`]) with up to 10
+/// parameters, or a list of sampled points equally spaced across its domain when `r#type` is 0.
+///
+/// [`ParametricCurveEvaluator`]: crate::plugins::ParametricCurveEvaluator
+#[derive(Clone, Debug, PartialEq)]
 pub struct CurveSegment {
-    x0: f32,
-    x1: f32,
-    r#type: i32,
-    params: [f64; 10],
-    sampled_points: Vec<f32>,
+    pub x0: f32,
+    pub x1: f32,
+    pub r#type: i32,
+    pub params: [f64; 10],
+    pub sampled_points: Vec<f32>,
 }
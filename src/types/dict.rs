@@ -0,0 +1,92 @@
+use super::Mlu;
+
+/// A single name/value pair of a [`Dict`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct DictEntry {
+    name: String,
+    value: String,
+    display_name: Option<Mlu>,
+    display_value: Option<Mlu>,
+}
+
+/// A dictionary of name/value pairs, each optionally with localized names and values to display, as stored in the
+/// `meta` tag.
+///
+/// # Examples
+/// ```
+/// use lcms2::types::{Dict, Mlu};
+///
+/// let mut dict = Dict::new();
+/// dict.add_entry("Source", "Scanner", None, None);
+/// dict.add_entry("Run", "42", Some(&Mlu::from_text("Print run")), None);
+///
+/// assert_eq!(dict.get_value("Run"), Some("42"));
+/// assert_eq!(dict.get_value("run"), None);
+/// assert!(dict.get(0).unwrap().get_display_name().is_none());
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Dict {
+    entries: Vec<DictEntry>,
+}
+
+impl DictEntry {
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn get_value(&self) -> &str {
+        &self.value
+    }
+
+    pub fn get_display_name(&self) -> Option<&Mlu> {
+        self.display_name.as_ref()
+    }
+
+    pub fn get_display_value(&self) -> Option<&Mlu> {
+        self.display_value.as_ref()
+    }
+}
+
+impl Dict {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends an entry. Names are not required to be unique, lookups return the first entry of a name.
+    pub fn add_entry(
+        &mut self,
+        name: &str,
+        value: &str,
+        display_name: Option<&Mlu>,
+        display_value: Option<&Mlu>,
+    ) {
+        self.entries.push(DictEntry {
+            name: name.to_string(),
+            value: value.to_string(),
+            display_name: display_name.cloned(),
+            display_value: display_value.cloned(),
+        });
+    }
+
+    pub fn get_count(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn get(&self, index: usize) -> Option<&DictEntry> {
+        self.entries.get(index)
+    }
+
+    pub fn get_entries(&self) -> &[DictEntry] {
+        &self.entries
+    }
+
+    /// Returns the first entry named `name`, which is compared exactly.
+    pub fn find(&self, name: &str) -> Option<&DictEntry> {
+        self.entries.iter().find(|entry| entry.name == name)
+    }
+
+    /// Returns the value of the first entry named `name`.
+    pub fn get_value(&self, name: &str) -> Option<&str> {
+        self.find(name).map(DictEntry::get_value)
+    }
+}
@@ -0,0 +1,132 @@
+use super::CIEXYZ;
+
+/// The standard observer of a [`Measurement`], ICC.1:2022 table 50.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StandardObserver {
+    Unknown,
+    /// CIE 1931 standard colorimetric observer (2°).
+    Cie1931,
+    /// CIE 1964 standard colorimetric observer (10°).
+    Cie1964,
+    /// An observer not defined by the ICC, kept as encoded.
+    Other(u32),
+}
+
+/// The measurement geometry of a [`Measurement`], ICC.1:2022 table 51.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MeasurementGeometry {
+    Unknown,
+    /// 0°:45° or 45°:0°.
+    ZeroFortyFive,
+    /// 0°:d or d:0°.
+    ZeroDiffuse,
+    /// A geometry not defined by the ICC, kept as encoded.
+    Other(u32),
+}
+
+/// A standard illuminant, ICC.1:2022 table 53.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StandardIlluminant {
+    Unknown,
+    D50,
+    D65,
+    D93,
+    F2,
+    D55,
+    A,
+    /// Equi-power (E).
+    E,
+    F8,
+    /// An illuminant not defined by the ICC, kept as encoded.
+    Other(u32),
+}
+
+/// The conditions colorimetric data was measured under, as stored in `meas` tags.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Measurement {
+    pub observer: StandardObserver,
+    /// The tristimulus values of the measurement backing.
+    pub backing: CIEXYZ,
+    pub geometry: MeasurementGeometry,
+    /// The measurement flare, from 0 (0 %) to 1 (100 %).
+    pub flare: f64,
+    pub illuminant: StandardIlluminant,
+}
+
+impl From<u32> for StandardObserver {
+    fn from(value: u32) -> Self {
+        match value {
+            0 => Self::Unknown,
+            1 => Self::Cie1931,
+            2 => Self::Cie1964,
+            _ => Self::Other(value),
+        }
+    }
+}
+
+impl From<StandardObserver> for u32 {
+    fn from(value: StandardObserver) -> Self {
+        match value {
+            StandardObserver::Unknown => 0,
+            StandardObserver::Cie1931 => 1,
+            StandardObserver::Cie1964 => 2,
+            StandardObserver::Other(value) => value,
+        }
+    }
+}
+
+impl From<u32> for MeasurementGeometry {
+    fn from(value: u32) -> Self {
+        match value {
+            0 => Self::Unknown,
+            1 => Self::ZeroFortyFive,
+            2 => Self::ZeroDiffuse,
+            _ => Self::Other(value),
+        }
+    }
+}
+
+impl From<MeasurementGeometry> for u32 {
+    fn from(value: MeasurementGeometry) -> Self {
+        match value {
+            MeasurementGeometry::Unknown => 0,
+            MeasurementGeometry::ZeroFortyFive => 1,
+            MeasurementGeometry::ZeroDiffuse => 2,
+            MeasurementGeometry::Other(value) => value,
+        }
+    }
+}
+
+impl From<u32> for StandardIlluminant {
+    fn from(value: u32) -> Self {
+        match value {
+            0 => Self::Unknown,
+            1 => Self::D50,
+            2 => Self::D65,
+            3 => Self::D93,
+            4 => Self::F2,
+            5 => Self::D55,
+            6 => Self::A,
+            7 => Self::E,
+            8 => Self::F8,
+            _ => Self::Other(value),
+        }
+    }
+}
+
+impl From<StandardIlluminant> for u32 {
+    fn from(value: StandardIlluminant) -> Self {
+        match value {
+            StandardIlluminant::Unknown => 0,
+            StandardIlluminant::D50 => 1,
+            StandardIlluminant::D65 => 2,
+            StandardIlluminant::D93 => 3,
+            StandardIlluminant::F2 => 4,
+            StandardIlluminant::D55 => 5,
+            StandardIlluminant::A => 6,
+            StandardIlluminant::E => 7,
+            StandardIlluminant::F8 => 8,
+            StandardIlluminant::Other(value) => value,
+        }
+    }
+}
@@ -1,3 +1,5 @@
+/// A single translation of a [`Mlu`].
+#[derive(Clone, Debug, PartialEq)]
 pub struct MluEntry {
     language: [char; 2],
     country: [char; 2],
@@ -5,7 +7,181 @@ pub struct MluEntry {
     value: String,
 }
 
+impl MluEntry {
+    pub fn get_language(&self) -> [char; 2] {
+        self.language
+    }
+
+    pub fn get_country(&self) -> [char; 2] {
+        self.country
+    }
+
+    pub fn get_value(&self) -> &str {
+        &self.value
+    }
+}
+
+/// A multi-localized string, holding translations of the same text keyed by ISO 639-1 language and ISO 3166-1 country
+/// codes.
+///
+/// # Examples
+/// ```
+/// use lcms2::types::Mlu;
+///
+/// let mut mlu = Mlu::new();
+/// mlu.set(['e', 'n'], ['U', 'S'], "Color");
+/// mlu.set(['e', 'n'], ['G', 'B'], "Colour");
+///
+/// assert_eq!(mlu.get(['e', 'n'], ['G', 'B']), Some("Colour"));
+/// // Falls back to the first translation of the language, then to the first translation
+/// assert_eq!(mlu.get(['e', 'n'], ['C', 'A']), Some("Color"));
+/// assert_eq!(mlu.get(['f', 'r'], ['F', 'R']), Some("Color"));
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
 pub struct Mlu {
     entries: Vec<MluEntry>,
 }
-// &mut Context must be passed in for all functions involving Mlu
+
+impl Mlu {
+    /// The language code of translations without a language.
+    pub const NO_LANGUAGE: [char; 2] = ['\0', '\0'];
+    /// The country code of translations without a country.
+    pub const NO_COUNTRY: [char; 2] = ['\0', '\0'];
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a `Mlu` holding a single translation without a language or a country.
+    pub fn from_text(value: &str) -> Self {
+        let mut mlu = Self::new();
+        mlu.set(Self::NO_LANGUAGE, Self::NO_COUNTRY, value);
+
+        mlu
+    }
+
+    /// Sets the translation for `language` and `country`, replacing any existing one.
+    pub fn set(&mut self, language: [char; 2], country: [char; 2], value: &str) {
+        let entry = MluEntry {
+            language,
+            country,
+            value: value.to_string(),
+        };
+
+        match self
+            .entries
+            .iter()
+            .position(|e| e.language == language && e.country == country)
+        {
+            Some(i) => self.entries[i] = entry,
+            None => self.entries.push(entry),
+        }
+    }
+
+    /// Returns the translation best matching `language` and `country`.
+    ///
+    /// If there is no exact match, the first translation of `language` is returned, and if there is none either, the
+    /// first translation overall.
+    pub fn get(&self, language: [char; 2], country: [char; 2]) -> Option<&str> {
+        self.search(language, country)
+            .map(|i| self.entries[i].value.as_str())
+    }
+
+    /// Returns the language and country codes of the translation [`get`](Mlu::get) would return.
+    pub fn get_translation(
+        &self,
+        language: [char; 2],
+        country: [char; 2],
+    ) -> Option<([char; 2], [char; 2])> {
+        self.search(language, country)
+            .map(|i| (self.entries[i].language, self.entries[i].country))
+    }
+
+    pub fn get_translations_count(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns the language and country codes of the `index`th translation.
+    pub fn get_translation_codes(&self, index: usize) -> Option<([char; 2], [char; 2])> {
+        self.entries.get(index).map(|e| (e.language, e.country))
+    }
+
+    pub fn get_entries(&self) -> &[MluEntry] {
+        &self.entries
+    }
+
+    fn search(&self, language: [char; 2], country: [char; 2]) -> Option<usize> {
+        if self.entries.is_empty() {
+            return None;
+        }
+
+        let mut best = None;
+        for (i, entry) in self.entries.iter().enumerate() {
+            if entry.language == language {
+                if entry.country == country {
+                    return Some(i);
+                }
+                best = best.or(Some(i));
+            }
+        }
+
+        // No string found. Return First one
+        Some(best.unwrap_or(0))
+    }
+}
+
+/// Encodes a language or country code as stored in ICC profiles.
+pub(crate) fn code_to_u16(code: [char; 2]) -> u16 {
+    u16::from_be_bytes([code[0] as u32 as u8, code[1] as u32 as u8])
+}
+
+/// Decodes a language or country code as stored in ICC profiles.
+pub(crate) fn u16_to_code(value: u16) -> [char; 2] {
+    let bytes = value.to_be_bytes();
+    [bytes[0] as char, bytes[1] as char]
+}
+
+#[cfg(test)]
+mod test {
+    use super::{code_to_u16, u16_to_code, Mlu};
+
+    #[test]
+    fn test_set_replaces_existing_translation() {
+        let mut mlu = Mlu::new();
+
+        mlu.set(['e', 'n'], ['U', 'S'], "first");
+        mlu.set(['e', 'n'], ['U', 'S'], "second");
+
+        assert_eq!(mlu.get_translations_count(), 1);
+        assert_eq!(mlu.get(['e', 'n'], ['U', 'S']), Some("second"));
+    }
+
+    #[test]
+    fn test_get_translation_falls_back() {
+        let mut mlu = Mlu::new();
+        mlu.set(['d', 'e'], ['D', 'E'], "Farbe");
+        mlu.set(['e', 'n'], ['G', 'B'], "Colour");
+        mlu.set(['e', 'n'], ['U', 'S'], "Color");
+
+        assert_eq!(
+            mlu.get_translation(['e', 'n'], ['U', 'S']),
+            Some((['e', 'n'], ['U', 'S']))
+        );
+        assert_eq!(
+            mlu.get_translation(['e', 'n'], ['A', 'U']),
+            Some((['e', 'n'], ['G', 'B']))
+        );
+        assert_eq!(
+            mlu.get_translation(Mlu::NO_LANGUAGE, Mlu::NO_COUNTRY),
+            Some((['d', 'e'], ['D', 'E']))
+        );
+        assert_eq!(Mlu::new().get(['e', 'n'], ['U', 'S']), None);
+    }
+
+    #[test]
+    fn test_codes_round_trip() {
+        assert_eq!(code_to_u16(['e', 'n']), 0x656e);
+        assert_eq!(u16_to_code(0x656e), ['e', 'n']);
+        assert_eq!(code_to_u16(Mlu::NO_LANGUAGE), 0);
+    }
+}
@@ -1,11 +1,29 @@
+use crate::state::{Context, ErrorCode};
+
 use super::MAX_CHANNELS;
 
+/// A single color of a [`NamedColorList`].
+#[derive(Clone, Debug, PartialEq)]
 pub struct NamedColor {
     name: String,
     pcs: [u16; 3],
     device_colorant: [u16; MAX_CHANNELS],
 }
 
+/// A list of named colors, such as spot colors, each with PCS and device coordinates.
+///
+/// # Examples
+/// ```
+/// use lcms2::{state::Context, types::NamedColorList};
+///
+/// let mut context = Context::new(None);
+/// let mut list = NamedColorList::new(&mut context, 4, "PANTONE ", " C").unwrap();
+/// list.append("185", [0x8000, 0xc000, 0xa000], Some(&[0, 0xffff, 0xcccc, 0]));
+///
+/// assert_eq!(list.index_of("185"), Some(0));
+/// assert_eq!(list.get(0).unwrap().get_device_colorant()[1], 0xffff);
+/// ```
+#[derive(Clone, Debug, PartialEq)]
 pub struct NamedColorList {
     prefix: String,
     suffix: String,
@@ -13,3 +31,123 @@ pub struct NamedColorList {
     colorant_count: u32,
 }
 // &mut Context must be passed in for all functions involving NamedColorList
+
+impl NamedColor {
+    /// Returns the root name of the color, without the prefix and suffix of its list.
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn get_pcs(&self) -> [u16; 3] {
+        self.pcs
+    }
+
+    /// Returns the device coordinates of the color, of which only the first
+    /// [`get_colorant_count`](NamedColorList::get_colorant_count) are meaningful.
+    pub fn get_device_colorant(&self) -> &[u16; MAX_CHANNELS] {
+        &self.device_colorant
+    }
+}
+
+impl NamedColorList {
+    /// Creates an empty list of colors with `colorant_count` device coordinates each, and whose names are surrounded
+    /// by `prefix` and `suffix`.
+    pub fn new(
+        context: &mut Context,
+        colorant_count: u32,
+        prefix: &str,
+        suffix: &str,
+    ) -> Option<NamedColorList> {
+        if colorant_count as usize > MAX_CHANNELS {
+            context.signal_error(
+                ErrorCode::Range,
+                format!("Too many device coordinates '{}'", colorant_count),
+            );
+            return None;
+        }
+
+        Some(NamedColorList {
+            prefix: prefix.to_string(),
+            suffix: suffix.to_string(),
+            list: Vec::new(),
+            colorant_count,
+        })
+    }
+
+    /// Appends a color. Without `colorant`, or when it is shorter than the colorant count, the missing device
+    /// coordinates are 0.
+    pub fn append(&mut self, name: &str, pcs: [u16; 3], colorant: Option<&[u16]>) {
+        let mut device_colorant = [0u16; MAX_CHANNELS];
+        if let Some(colorant) = colorant {
+            let n = colorant.len().min(self.colorant_count as usize);
+            device_colorant[..n].copy_from_slice(&colorant[..n]);
+        }
+
+        self.list.push(NamedColor {
+            name: name.to_string(),
+            pcs,
+            device_colorant,
+        });
+    }
+
+    pub fn get_prefix(&self) -> &str {
+        &self.prefix
+    }
+
+    pub fn get_suffix(&self) -> &str {
+        &self.suffix
+    }
+
+    pub fn get_colorant_count(&self) -> u32 {
+        self.colorant_count
+    }
+
+    pub fn get_count(&self) -> usize {
+        self.list.len()
+    }
+
+    pub fn get(&self, index: usize) -> Option<&NamedColor> {
+        self.list.get(index)
+    }
+
+    pub fn get_colors(&self) -> &[NamedColor] {
+        &self.list
+    }
+
+    /// Returns the index of the color named `name`, ignoring ASCII case.
+    pub fn index_of(&self, name: &str) -> Option<usize> {
+        self.list
+            .iter()
+            .position(|color| color.name.eq_ignore_ascii_case(name))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{state::Context, types::MAX_CHANNELS};
+
+    use super::NamedColorList;
+
+    #[test]
+    fn test_new_rejects_too_many_colorants() {
+        let mut context = Context::new(None);
+
+        assert!(NamedColorList::new(&mut context, MAX_CHANNELS as u32 + 1, "", "").is_none());
+        assert!(NamedColorList::new(&mut context, MAX_CHANNELS as u32, "", "").is_some());
+    }
+
+    #[test]
+    fn test_append_keeps_colorant_count_coordinates() {
+        let mut context = Context::new(None);
+        let mut list = NamedColorList::new(&mut context, 2, "", "").unwrap();
+
+        list.append("red", [1, 2, 3], Some(&[4, 5, 6]));
+        list.append("Green", [7, 8, 9], None);
+
+        assert_eq!(list.get_count(), 2);
+        assert_eq!(list.get(0).unwrap().get_device_colorant()[..3], [4, 5, 0]);
+        assert_eq!(list.get(1).unwrap().get_device_colorant()[..2], [0, 0]);
+        assert_eq!(list.index_of("GREEN"), Some(1));
+        assert_eq!(list.index_of("blue"), None);
+    }
+}
@@ -1,45 +1,731 @@
-use std::sync::Arc;
+use crate::{
+    plugins::MAX_INPUT_DIMENTIONS,
+    quick_saturate_word,
+    state::{Context, ErrorCode},
+};
 
-use crate::{state::Context, plugins::InterpParams};
-
-use super::{Signature, ToneCurve};
+use super::{signatures::stage, Signature, ToneCurve, MAX_CHANNELS};
 
 pub type StageEvalFn = fn(context: &mut Context, r#in: &[f32], out: &mut [f32], mpe: &Stage);
+
+/// A single processing element of a [`Pipeline`], mapping `input_channels` values in the `0.0..=1.0` domain to
+/// `output_channels` values.
+#[derive(Clone, Debug)]
 pub struct Stage {
     r#type: Signature,
     implements: Signature,
     input_channels: u32,
     output_channels: u32,
     eval: StageEvalFn,
-    data: Arc<Box<[u8]>>,
+    data: StageData,
+}
+
+/// The parameters of a [`Stage`], depending on its type.
+#[derive(Clone, Debug)]
+pub enum StageData {
+    None,
+    ToneCurves(StageToneCurveData),
+    Matrix(StageMatrixData),
+    CLut(StageClutData),
 }
 
+#[derive(Clone, Debug)]
 pub struct StageToneCurveData {
-    curves: Arc<Vec<ToneCurve>>,
+    curves: Vec<ToneCurve>,
 }
+
+#[derive(Clone, Debug)]
 pub struct StageMatrixData {
     double: Box<[f64]>,
-    offset: Box<[f64]>,
+    offset: Option<Box<[f64]>>,
 }
+
+#[derive(Clone, Debug)]
 pub enum Tab {
     U16(Box<[u16]>),
     F32(Box<[f32]>),
 }
+
+#[derive(Clone, Debug)]
 pub struct StageClutData {
     tab: Tab,
-    params: Box<[InterpParams]>,
+    grid_points: Box<[u32]>,
+}
+
+/// Where to insert a [`Stage`] into a [`Pipeline`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StageLoc {
+    AtBegin,
+    AtEnd,
 }
 
+#[derive(Clone, Debug)]
 pub enum PipelineEvalFn {
     U16(fn(r#in: &[u16], out: &mut [u16], data: Box<[u8]>)),
     Float(fn(r#in: &[f32], out: &mut [f32], data: Box<[u8]>)),
 }
+
+/// A chain of [`Stage`]s, each one feeding the next.
+#[derive(Clone, Debug)]
 pub struct Pipeline {
-    elements: Vec<Arc<Stage>>,
+    elements: Vec<Stage>,
     input_channels: u32,
     output_channels: u32,
-    data: Arc<Box<[u8]>>,
-    eval: PipelineEvalFn,
-    save_as_8_bits: bool,
+    pub(crate) save_as_8_bits: bool,
 }
 // &mut Context must be passed in for all functions involving Pipeline
+
+impl StageToneCurveData {
+    pub fn get_curves(&self) -> &[ToneCurve] {
+        &self.curves
+    }
+}
+
+impl StageMatrixData {
+    /// Returns the matrix coefficients, in row major order with one row per output channel.
+    pub fn get_matrix(&self) -> &[f64] {
+        &self.double
+    }
+
+    pub fn get_offset(&self) -> Option<&[f64]> {
+        self.offset.as_deref()
+    }
+}
+
+impl StageClutData {
+    pub fn get_table(&self) -> &Tab {
+        &self.tab
+    }
+
+    /// Returns the number of grid points of each input dimension.
+    pub fn get_grid_points(&self) -> &[u32] {
+        &self.grid_points
+    }
+}
+
+impl Stage {
+    fn new(
+        r#type: Signature,
+        input_channels: u32,
+        output_channels: u32,
+        eval: StageEvalFn,
+        data: StageData,
+    ) -> Stage {
+        Stage {
+            r#type,
+            implements: r#type,
+            input_channels,
+            output_channels,
+            eval,
+            data,
+        }
+    }
+
+    /// Creates a stage holding one curve per channel. Without `curves`, the stage holds identity curves.
+    pub fn new_tone_curves(
+        context: &mut Context,
+        channels: u32,
+        curves: Option<&[ToneCurve]>,
+    ) -> Option<Stage> {
+        let curves = match curves {
+            Some(curves) => {
+                if curves.len() != channels as usize {
+                    context.signal_error(
+                        ErrorCode::Range,
+                        format!("Expected {} curves, got {}", channels, curves.len()),
+                    );
+                    return None;
+                }
+                curves.to_vec()
+            }
+            None => {
+                let identity = ToneCurve::build_gamma(context, 1.0)?;
+                vec![identity; channels as usize]
+            }
+        };
+
+        Some(Self::new(
+            stage::CURVE_SET_ELEM_TYPE,
+            channels,
+            channels,
+            evaluate_curves,
+            StageData::ToneCurves(StageToneCurveData { curves }),
+        ))
+    }
+
+    /// Creates a stage passing `channels` values through unchanged.
+    pub fn new_identity(channels: u32) -> Stage {
+        Self::new(
+            stage::IDENTITY_ELEM_TYPE,
+            channels,
+            channels,
+            evaluate_identity,
+            StageData::None,
+        )
+    }
+
+    /// Creates a stage multiplying its input by a `rows` x `cols` matrix, given in row major order, and then adding
+    /// `offset`.
+    pub fn new_matrix(
+        context: &mut Context,
+        rows: u32,
+        cols: u32,
+        matrix: &[f64],
+        offset: Option<&[f64]>,
+    ) -> Option<Stage> {
+        let n = rows as usize * cols as usize;
+
+        if n == 0 || matrix.len() < n || offset.is_some_and(|offset| offset.len() < rows as usize)
+        {
+            context.signal_error(
+                ErrorCode::Range,
+                format!("Invalid {}x{} matrix", rows, cols),
+            );
+            return None;
+        }
+
+        Some(Self::new(
+            stage::MATRIX_ELEM_TYPE,
+            cols,
+            rows,
+            evaluate_matrix,
+            StageData::Matrix(StageMatrixData {
+                double: matrix[..n].into(),
+                offset: offset.map(|offset| offset[..rows as usize].into()),
+            }),
+        ))
+    }
+
+    /// Creates a CLUT stage with a different number of grid points per input dimension, holding 16 bit values. Without
+    /// `table`, the CLUT is filled with zeros.
+    pub fn new_clut_16bit_granular(
+        context: &mut Context,
+        grid_points: &[u32],
+        input_channels: u32,
+        output_channels: u32,
+        table: Option<&[u16]>,
+    ) -> Option<Stage> {
+        let n = clut_size(context, grid_points, input_channels, output_channels)?;
+        let tab = match table {
+            Some(table) if table.len() < n => {
+                context.signal_error(ErrorCode::Range, "CLUT table is too small");
+                return None;
+            }
+            Some(table) => table[..n].into(),
+            None => vec![0u16; n].into_boxed_slice(),
+        };
+
+        Some(Self::new(
+            stage::C_LUT_ELEM_TYPE,
+            input_channels,
+            output_channels,
+            evaluate_clut,
+            StageData::CLut(StageClutData {
+                tab: Tab::U16(tab),
+                grid_points: grid_points[..input_channels as usize].into(),
+            }),
+        ))
+    }
+
+    /// Creates a CLUT stage with `grid_points` grid points in every input dimension, holding 16 bit values.
+    pub fn new_clut_16bit(
+        context: &mut Context,
+        grid_points: u32,
+        input_channels: u32,
+        output_channels: u32,
+        table: Option<&[u16]>,
+    ) -> Option<Stage> {
+        let grid_points = [grid_points; MAX_INPUT_DIMENTIONS];
+        let dims = (input_channels as usize).min(MAX_INPUT_DIMENTIONS);
+
+        Self::new_clut_16bit_granular(
+            context,
+            &grid_points[..dims],
+            input_channels,
+            output_channels,
+            table,
+        )
+    }
+
+    /// Creates a CLUT stage with a different number of grid points per input dimension, holding floating point
+    /// values. Without `table`, the CLUT is filled with zeros.
+    pub fn new_clut_float_granular(
+        context: &mut Context,
+        grid_points: &[u32],
+        input_channels: u32,
+        output_channels: u32,
+        table: Option<&[f32]>,
+    ) -> Option<Stage> {
+        let n = clut_size(context, grid_points, input_channels, output_channels)?;
+        let tab = match table {
+            Some(table) if table.len() < n => {
+                context.signal_error(ErrorCode::Range, "CLUT table is too small");
+                return None;
+            }
+            Some(table) => table[..n].into(),
+            None => vec![0f32; n].into_boxed_slice(),
+        };
+
+        Some(Self::new(
+            stage::C_LUT_ELEM_TYPE,
+            input_channels,
+            output_channels,
+            evaluate_clut,
+            StageData::CLut(StageClutData {
+                tab: Tab::F32(tab),
+                grid_points: grid_points[..input_channels as usize].into(),
+            }),
+        ))
+    }
+
+    /// Creates a stage converting Lab from the V2 16 bit encoding, where `0xff00` is L* = 100, to the V4 one, where it
+    /// is `0xffff`.
+    pub fn new_lab_v2_to_v4(context: &mut Context) -> Option<Stage> {
+        let scale = 65535.0 / 65280.0;
+        let matrix = [scale, 0.0, 0.0, 0.0, scale, 0.0, 0.0, 0.0, scale];
+
+        let mut stage = Self::new_matrix(context, 3, 3, &matrix, None)?;
+        stage.implements = stage::LAB_V2_TO_V4;
+
+        Some(stage)
+    }
+
+    /// Creates a stage converting Lab from the V4 16 bit encoding to the V2 one, the reverse of
+    /// [`new_lab_v2_to_v4`](Stage::new_lab_v2_to_v4).
+    pub fn new_lab_v4_to_v2(context: &mut Context) -> Option<Stage> {
+        let scale = 65280.0 / 65535.0;
+        let matrix = [scale, 0.0, 0.0, 0.0, scale, 0.0, 0.0, 0.0, scale];
+
+        let mut stage = Self::new_matrix(context, 3, 3, &matrix, None)?;
+        stage.implements = stage::LAB_V4_TO_V2;
+
+        Some(stage)
+    }
+
+    pub fn get_type(&self) -> Signature {
+        self.r#type
+    }
+
+    /// Returns the function this stage implements, which may be more specific than its type.
+    pub fn get_implements(&self) -> Signature {
+        self.implements
+    }
+
+    pub fn get_input_channels(&self) -> u32 {
+        self.input_channels
+    }
+
+    pub fn get_output_channels(&self) -> u32 {
+        self.output_channels
+    }
+
+    pub fn get_data(&self) -> &StageData {
+        &self.data
+    }
+
+    /// Evaluates the stage in floating point.
+    pub fn eval(&self, context: &mut Context, r#in: &[f32], out: &mut [f32]) {
+        (self.eval)(context, r#in, out, self)
+    }
+}
+
+impl Pipeline {
+    /// Creates an empty pipeline. Its channels are then taken from the stages inserted into it.
+    pub fn new(context: &mut Context, input_channels: u32, output_channels: u32) -> Option<Pipeline> {
+        // A value of zero in channels is allowed as placeholder
+        if input_channels as usize >= MAX_CHANNELS || output_channels as usize >= MAX_CHANNELS {
+            context.signal_error(
+                ErrorCode::Range,
+                format!(
+                    "Couldn't create pipeline of {} input and {} output channels",
+                    input_channels, output_channels
+                ),
+            );
+            return None;
+        }
+
+        Some(Pipeline {
+            elements: Vec::new(),
+            input_channels,
+            output_channels,
+            save_as_8_bits: false,
+        })
+    }
+
+    /// Inserts `stage` at `location`, returning `false` if its channels don't match the neighbouring stage.
+    pub fn insert_stage(
+        &mut self,
+        context: &mut Context,
+        location: StageLoc,
+        stage: Stage,
+    ) -> bool {
+        let matches = match location {
+            StageLoc::AtBegin => self
+                .elements
+                .first()
+                .is_none_or(|first| first.input_channels == stage.output_channels),
+            StageLoc::AtEnd => self
+                .elements
+                .last()
+                .is_none_or(|last| last.output_channels == stage.input_channels),
+        };
+
+        if !matches {
+            context.signal_error(
+                ErrorCode::Range,
+                "Couldn't insert stage: its channels don't match the pipeline",
+            );
+            return false;
+        }
+
+        match location {
+            StageLoc::AtBegin => self.elements.insert(0, stage),
+            StageLoc::AtEnd => self.elements.push(stage),
+        }
+
+        self.input_channels = self.elements[0].input_channels;
+        self.output_channels = self.elements[self.elements.len() - 1].output_channels;
+
+        true
+    }
+
+    /// Removes and returns the stage at `location`, or `None` if the pipeline is empty.
+    pub fn remove_stage(&mut self, location: StageLoc) -> Option<Stage> {
+        if self.elements.is_empty() {
+            return None;
+        }
+
+        let stage = match location {
+            StageLoc::AtBegin => self.elements.remove(0),
+            StageLoc::AtEnd => self.elements.pop()?,
+        };
+
+        // An empty pipeline keeps its channels as placeholder
+        if let (Some(first), Some(last)) = (self.elements.first(), self.elements.last()) {
+            self.input_channels = first.input_channels;
+            self.output_channels = last.output_channels;
+        }
+
+        Some(stage)
+    }
+
+    pub fn get_stages(&self) -> &[Stage] {
+        &self.elements
+    }
+
+    pub fn get_input_channels(&self) -> u32 {
+        self.input_channels
+    }
+
+    pub fn get_output_channels(&self) -> u32 {
+        self.output_channels
+    }
+
+    /// Returns whether the pipeline should be saved with 8 bit precision when possible.
+    pub fn get_save_as_8_bits(&self) -> bool {
+        self.save_as_8_bits
+    }
+
+    pub fn set_save_as_8_bits(&mut self, value: bool) {
+        self.save_as_8_bits = value;
+    }
+
+    /// Evaluates the pipeline in floating point, on values in the `0.0..=1.0` domain.
+    pub fn eval_float(&self, context: &mut Context, r#in: &[f32], out: &mut [f32]) {
+        let mut storage = [[0f32; MAX_STAGE_CHANNELS]; 2];
+        let mut phase = 0;
+
+        let n = self.input_channels as usize;
+        storage[phase][..n].copy_from_slice(&r#in[..n]);
+
+        for stage in self.elements.iter() {
+            let [a, b] = &mut storage;
+            let (src, dst) = if phase == 0 { (a, b) } else { (b, a) };
+
+            stage.eval(context, src, dst);
+            phase ^= 1;
+        }
+
+        let n = self.output_channels as usize;
+        out[..n].copy_from_slice(&storage[phase][..n]);
+    }
+
+    /// Evaluates the pipeline on 16 bit values, going through floating point.
+    pub fn eval_u16(&self, context: &mut Context, r#in: &[u16], out: &mut [u16]) {
+        let mut r#in_f = [0f32; MAX_STAGE_CHANNELS];
+        let mut out_f = [0f32; MAX_STAGE_CHANNELS];
+
+        for (f, v) in r#in_f.iter_mut().zip(r#in[..self.input_channels as usize].iter()) {
+            *f = *v as f32 / 65535.0;
+        }
+
+        self.eval_float(context, &r#in_f, &mut out_f);
+
+        for (v, f) in out[..self.output_channels as usize].iter_mut().zip(out_f.iter()) {
+            *v = quick_saturate_word(*f as f64 * 65535.0);
+        }
+    }
+}
+
+/// The maximum number of channels flowing between two stages.
+const MAX_STAGE_CHANNELS: usize = 128;
+
+/// Returns the number of entries of a CLUT, signaling an error if it is invalid.
+fn clut_size(
+    context: &mut Context,
+    grid_points: &[u32],
+    input_channels: u32,
+    output_channels: u32,
+) -> Option<usize> {
+    if input_channels as usize > MAX_INPUT_DIMENTIONS {
+        context.signal_error(
+            ErrorCode::Range,
+            format!(
+                "Too many input channels ({} channels, max={})",
+                input_channels, MAX_INPUT_DIMENTIONS
+            ),
+        );
+        return None;
+    }
+
+    let size = cube_size(grid_points, input_channels as usize)
+        .and_then(|size| size.checked_mul(output_channels as usize))
+        .filter(|size| *size > 0);
+    if size.is_none() {
+        context.signal_error(ErrorCode::Range, "Invalid CLUT grid points");
+    }
+
+    size
+}
+
+/// Returns the number of grid nodes of a CLUT, or `None` if any dimension has less than 2 points.
+pub(crate) fn cube_size(grid_points: &[u32], dims: usize) -> Option<usize> {
+    if grid_points.len() < dims {
+        return None;
+    }
+
+    grid_points[..dims].iter().try_fold(1usize, |size, points| {
+        if *points <= 1 {
+            return None;
+        }
+        size.checked_mul(*points as usize)
+    })
+}
+
+fn evaluate_identity(_context: &mut Context, r#in: &[f32], out: &mut [f32], mpe: &Stage) {
+    let n = mpe.input_channels as usize;
+    out[..n].copy_from_slice(&r#in[..n]);
+}
+
+fn evaluate_curves(_context: &mut Context, r#in: &[f32], out: &mut [f32], mpe: &Stage) {
+    if let StageData::ToneCurves(data) = &mpe.data {
+        for (i, curve) in data.curves.iter().enumerate() {
+            out[i] = curve.eval_f32(r#in[i]);
+        }
+    }
+}
+
+fn evaluate_matrix(_context: &mut Context, r#in: &[f32], out: &mut [f32], mpe: &Stage) {
+    if let StageData::Matrix(data) = &mpe.data {
+        let cols = mpe.input_channels as usize;
+
+        // Input is already in 0..1.0 notation
+        for i in 0..mpe.output_channels as usize {
+            let row = &data.double[i * cols..(i + 1) * cols];
+            let mut tmp = row
+                .iter()
+                .zip(r#in.iter())
+                .map(|(m, v)| m * *v as f64)
+                .sum::<f64>();
+
+            if let Some(offset) = &data.offset {
+                tmp += offset[i];
+            }
+
+            out[i] = tmp as f32;
+        }
+    }
+}
+
+/// Evaluates a CLUT by multilinear interpolation of the grid nodes surrounding the input.
+fn evaluate_clut(_context: &mut Context, r#in: &[f32], out: &mut [f32], mpe: &Stage) {
+    let data = match &mpe.data {
+        StageData::CLut(data) => data,
+        _ => return,
+    };
+
+    let inputs = mpe.input_channels as usize;
+    let outputs = mpe.output_channels as usize;
+
+    // The first input varies the slowest, and the outputs of a node are contiguous
+    let mut strides = [0usize; MAX_INPUT_DIMENTIONS];
+    let mut stride = outputs;
+    for i in (0..inputs).rev() {
+        strides[i] = stride;
+        stride *= data.grid_points[i] as usize;
+    }
+
+    let mut base = 0;
+    let mut rest = [0f32; MAX_INPUT_DIMENTIONS];
+    let mut step = [0usize; MAX_INPUT_DIMENTIONS];
+    for i in 0..inputs {
+        let domain = (data.grid_points[i] - 1) as f32;
+        let v = if r#in[i].is_nan() {
+            0.0
+        } else {
+            r#in[i].clamp(0.0, 1.0) * domain
+        };
+
+        let cell = (v.floor() as usize).min(domain as usize);
+        base += cell * strides[i];
+        rest[i] = v - cell as f32;
+        step[i] = if cell < domain as usize { strides[i] } else { 0 };
+    }
+
+    out[..outputs].fill(0.0);
+    for corner in 0..1usize << inputs {
+        let mut weight = 1.0;
+        let mut index = base;
+        for i in 0..inputs {
+            if corner & (1 << i) != 0 {
+                weight *= rest[i];
+                index += step[i];
+            } else {
+                weight *= 1.0 - rest[i];
+            }
+        }
+
+        if weight == 0.0 {
+            continue;
+        }
+
+        for (o, value) in out[..outputs].iter_mut().enumerate() {
+            let node = match &data.tab {
+                Tab::U16(tab) => tab[index + o] as f32 / 65535.0,
+                Tab::F32(tab) => tab[index + o],
+            };
+            *value += weight * node;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        state::Context,
+        types::{signatures::stage, ToneCurve},
+    };
+
+    use super::{Pipeline, Stage, StageData, StageLoc};
+
+    #[test]
+    fn test_pipeline_takes_channels_from_stages() {
+        let mut context = Context::new(None);
+        let mut lut = Pipeline::new(&mut context, 0, 0).unwrap();
+
+        let matrix = [1.0, 0.0, 0.0, 0.0, 1.0, 0.0];
+        let matrix = Stage::new_matrix(&mut context, 2, 3, &matrix, None).unwrap();
+        let curves = Stage::new_tone_curves(&mut context, 3, None).unwrap();
+
+        assert!(lut.insert_stage(&mut context, StageLoc::AtEnd, matrix));
+        assert!(lut.insert_stage(&mut context, StageLoc::AtBegin, curves));
+
+        assert_eq!(lut.get_input_channels(), 3);
+        assert_eq!(lut.get_output_channels(), 2);
+        assert_eq!(lut.get_stages()[0].get_type(), stage::CURVE_SET_ELEM_TYPE);
+    }
+
+    #[test]
+    fn test_pipeline_rejects_mismatched_stages() {
+        let mut context = Context::new(None);
+        let mut lut = Pipeline::new(&mut context, 3, 3).unwrap();
+
+        assert!(lut.insert_stage(&mut context, StageLoc::AtEnd, Stage::new_identity(3)));
+        assert!(!lut.insert_stage(&mut context, StageLoc::AtEnd, Stage::new_identity(4)));
+        assert_eq!(lut.get_stages().len(), 1);
+    }
+
+    #[test]
+    fn test_eval_matrix_with_offset() {
+        let mut context = Context::new(None);
+        let mut lut = Pipeline::new(&mut context, 2, 1).unwrap();
+        let stage = Stage::new_matrix(&mut context, 1, 2, &[0.5, 0.25], Some(&[0.125])).unwrap();
+        lut.insert_stage(&mut context, StageLoc::AtEnd, stage);
+
+        let mut out = [0f32];
+        lut.eval_float(&mut context, &[1.0, 1.0], &mut out);
+
+        assert_eq!(out[0], 0.875);
+    }
+
+    #[test]
+    fn test_eval_clut_interpolates() {
+        let mut context = Context::new(None);
+        // Two inputs, one output: the sum of the inputs over two
+        let table = [0u16, 0x8000, 0x8000, 0xffff];
+        let clut = Stage::new_clut_16bit(&mut context, 2, 2, 1, Some(&table)).unwrap();
+
+        let mut out = [0f32];
+        clut.eval(&mut context, &[1.0, 0.0], &mut out);
+        assert!((out[0] - 0x8000 as f32 / 65535.0).abs() < 1e-6);
+
+        clut.eval(&mut context, &[0.5, 0.5], &mut out);
+        assert!((out[0] - 0.5).abs() < 1e-4);
+
+        clut.eval(&mut context, &[1.0, 1.0], &mut out);
+        assert_eq!(out[0], 1.0);
+    }
+
+    #[test]
+    fn test_clut_rejects_bad_grids() {
+        let mut context = Context::new(None);
+
+        assert!(Stage::new_clut_16bit(&mut context, 1, 3, 3, None).is_none());
+        assert!(Stage::new_clut_16bit(&mut context, 2, 16, 3, None).is_none());
+        assert!(Stage::new_clut_16bit(&mut context, 2, 3, 3, Some(&[0; 23])).is_none());
+        assert!(Stage::new_clut_float_granular(&mut context, &[2, 3], 2, 1, None).is_some());
+    }
+
+    #[test]
+    fn test_remove_stage_updates_channels() {
+        let mut context = Context::new(None);
+        let mut lut = Pipeline::new(&mut context, 3, 3).unwrap();
+        let matrix = Stage::new_matrix(&mut context, 1, 3, &[1.0, 1.0, 1.0], None).unwrap();
+        lut.insert_stage(&mut context, StageLoc::AtEnd, Stage::new_identity(3));
+        lut.insert_stage(&mut context, StageLoc::AtEnd, matrix);
+
+        let removed = lut.remove_stage(StageLoc::AtEnd).unwrap();
+        assert_eq!(removed.get_type(), stage::MATRIX_ELEM_TYPE);
+        assert_eq!(lut.get_output_channels(), 3);
+
+        assert!(lut.remove_stage(StageLoc::AtBegin).is_some());
+        assert!(lut.remove_stage(StageLoc::AtBegin).is_none());
+        assert!(lut.get_stages().is_empty());
+    }
+
+    #[test]
+    fn test_lab_v2_v4_stages_are_inverse() {
+        let mut context = Context::new(None);
+        let mut lut = Pipeline::new(&mut context, 3, 3).unwrap();
+        let v2_to_v4 = Stage::new_lab_v2_to_v4(&mut context).unwrap();
+        let v4_to_v2 = Stage::new_lab_v4_to_v2(&mut context).unwrap();
+        lut.insert_stage(&mut context, StageLoc::AtEnd, v2_to_v4);
+        lut.insert_stage(&mut context, StageLoc::AtEnd, v4_to_v2);
+
+        let mut out = [0u16; 3];
+        lut.eval_u16(&mut context, &[0xff00, 0x8080, 0], &mut out);
+
+        assert_eq!(out, [0xff00, 0x8080, 0]);
+        assert_eq!(lut.get_stages()[0].get_implements(), stage::LAB_V2_TO_V4);
+        assert!(matches!(lut.get_stages()[1].get_data(), StageData::Matrix(_)));
+    }
+
+    #[test]
+    fn test_tone_curves_stage_checks_count() {
+        let mut context = Context::new(None);
+        let curve = ToneCurve::build_gamma(&mut context, 2.2).unwrap();
+
+        assert!(Stage::new_tone_curves(&mut context, 2, Some(std::slice::from_ref(&curve))).is_none());
+        assert!(Stage::new_tone_curves(&mut context, 1, Some(&[curve])).is_some());
+    }
+}
@@ -1,24 +1,28 @@
 use std::{
     fs::File,
-    io::{self, Error, ErrorKind},
+    io::{self, Error, ErrorKind, SeekFrom},
     path::Path,
 };
 
 use chrono::Utc;
 
 use crate::{
-    io::{AccessMode, IOHandler},
+    io::{f64_to_s15f16, s15f16_to_f64, AccessMode, FileMem, FileMemReadOnly, FileNull, IOHandler},
+    md5::Md5,
+    plugins::{get_tag_descriptor, get_tag_type_handler},
     state::{Context, ErrorCode, GLOBAL_CONTEXT},
+    D50_X, D50_Y, D50_Z,
 };
 
 use super::{
-    icc_header::ICCHeaderConverter, signatures, tag_entry::TagEntryConverter, ProfileID, Signature,
-    MAX_TABLE_TAG,
+    icc_header::ICCHeaderConverter, signatures, tag_entry::TagEntryConverter,
+    tag_types::encode_lab_v2, Dict, EncodedXYZNumber, ICCHeader, Pipeline, ProfileID, Sequence,
+    Signature, Stage, StageLoc, TagEntry, TagValue, CIEXYZ,
 };
 
 #[derive(Debug)]
-pub struct Profile {
-    io: Option<Box<dyn IOHandler>>,
+pub struct Profile<'a> {
+    io: Option<Box<dyn IOHandler + 'a>>,
     created: chrono::NaiveDateTime,
     version: u32,
     device_class: Signature,
@@ -30,34 +34,48 @@ pub struct Profile {
     model: u32,
     attributes: u64,
     creator: u32,
+    illuminant: CIEXYZ,
     profile_id: ProfileID,
-    tag_count: usize,
-    tag_names: [Signature; MAX_TABLE_TAG],
-    tag_linked: [Option<Signature>; MAX_TABLE_TAG],
-    tag_sizes: [usize; MAX_TABLE_TAG],
-    tag_offsets: [usize; MAX_TABLE_TAG],
-    tag_save_as_raw: [bool; MAX_TABLE_TAG],
+    tags: Vec<ProfileTag>,
     is_write: bool,
 }
 
-impl Profile {
-    pub fn get_io_handler(&self) -> Option<&Box<dyn IOHandler>> {
-        self.io.as_ref()
-    }
-    pub fn get_tag_count(&self) -> usize {
-        self.tag_count
-    }
-    pub fn get_tag_signature(&self, n: usize) -> Signature {
-        if n > self.tag_count {
-            Signature::default()
-        } else {
-            self.tag_names[n]
-        }
+/// An entry in the tag directory of a [`Profile`]
+#[derive(Clone, Debug)]
+struct ProfileTag {
+    /// The tag signature
+    sig: Signature,
+    /// The signature of the tag this tag shares its data with
+    linked: Option<Signature>,
+    /// Start of tag in the profile's I/O
+    offset: usize,
+    /// Size in bytes
+    size: usize,
+    /// Raw tag data held in memory, replacing the data in the profile's I/O
+    raw: Option<Vec<u8>>,
+    /// Whether the tag was written by the user, so its data lives in memory rather than in the profile's I/O
+    in_memory: bool,
+    /// The tag type the value was read or written as
+    r#type: Option<Signature>,
+    /// The typed value of the tag, cached once read
+    value: Option<TagValue>,
+}
+
+impl PartialEq for ProfileTag {
+    fn eq(&self, other: &Self) -> bool {
+        // The cached value and its type are derived from the rest of the fields, so they're left out.
+        self.sig == other.sig
+            && self.linked == other.linked
+            && self.offset == other.offset
+            && self.size == other.size
+            && self.raw == other.raw
+            && self.in_memory == other.in_memory
     }
+}
 
-    pub fn new() -> Self {
+impl Default for Profile<'_> {
+    fn default() -> Self {
         Self {
-            tag_count: 0,
             version: 0x02100000,
             created: Utc::now().naive_utc(),
             // mutex stuff for the future goes here
@@ -69,46 +87,230 @@ impl Profile {
             manufacturer: 0,
             model: 0,
             attributes: 0,
-            creator: 0,
+            creator: signatures::LCMS_SIGNATURE.into(),
+            illuminant: CIEXYZ {
+                X: D50_X,
+                Y: D50_Y,
+                Z: D50_Z,
+            },
             profile_id: ProfileID { id16: [0u16; 8] },
-            tag_names: [Signature::default(); MAX_TABLE_TAG],
-            tag_linked: [None; MAX_TABLE_TAG],
-            tag_sizes: [0; MAX_TABLE_TAG],
-            tag_offsets: [0; MAX_TABLE_TAG],
-            tag_save_as_raw: [false; MAX_TABLE_TAG],
+            tags: Vec::new(),
             is_write: false,
             io: None,
         }
     }
+}
+
+impl<'a> Profile<'a> {
+    pub fn get_io_handler(&self) -> Option<&(dyn IOHandler + 'a)> {
+        self.io.as_deref()
+    }
+    pub fn get_tag_count(&self) -> usize {
+        self.tags.len()
+    }
+    pub fn get_tag_signature(&self, n: usize) -> Signature {
+        match self.tags.get(n) {
+            Some(tag) => tag.sig,
+            None => Signature::default(),
+        }
+    }
+
+    pub fn get_device_class(&self) -> Signature {
+        self.device_class
+    }
+    pub fn set_device_class(&mut self, sig: Signature) {
+        self.device_class = sig;
+    }
+    pub fn get_color_space(&self) -> Signature {
+        self.color_space
+    }
+    pub fn set_color_space(&mut self, sig: Signature) {
+        self.color_space = sig;
+    }
+    pub fn get_pcs(&self) -> Signature {
+        self.pcs
+    }
+    pub fn set_pcs(&mut self, sig: Signature) {
+        self.pcs = sig;
+    }
+    /// Returns the profile version as encoded in the header (e.g. `0x04300000` for 4.3).
+    pub fn get_encoded_version(&self) -> u32 {
+        self.version
+    }
+    pub fn set_encoded_version(&mut self, version: u32) {
+        self.version = version;
+    }
+    /// Returns the profile version as a decimal number (e.g. `4.3`).
+    pub fn get_version(&self) -> f64 {
+        let n = self.version >> 16;
+
+        base_to_base(n, 16, 10) as f64 / 100.0
+    }
+    /// Sets the profile version from a decimal number (e.g. `4.3` is encoded as `0x04300000`).
+    pub fn set_version(&mut self, version: f64) {
+        self.version = base_to_base((version * 100.0 + 0.5).floor() as u32, 10, 16) << 16;
+    }
+    pub fn get_rendering_intent(&self) -> u32 {
+        self.rendering_intent
+    }
+    pub fn set_rendering_intent(&mut self, intent: u32) {
+        self.rendering_intent = intent;
+    }
+    pub fn get_flags(&self) -> u32 {
+        self.flags
+    }
+    pub fn set_flags(&mut self, flags: u32) {
+        self.flags = flags;
+    }
+    pub fn get_attributes(&self) -> u64 {
+        self.attributes
+    }
+    pub fn set_attributes(&mut self, attributes: u64) {
+        self.attributes = attributes;
+    }
+    pub fn get_manufacturer(&self) -> u32 {
+        self.manufacturer
+    }
+    pub fn set_manufacturer(&mut self, manufacturer: u32) {
+        self.manufacturer = manufacturer;
+    }
+    pub fn get_model(&self) -> u32 {
+        self.model
+    }
+    pub fn set_model(&mut self, model: u32) {
+        self.model = model;
+    }
+    pub fn get_creator(&self) -> u32 {
+        self.creator
+    }
+    pub fn set_creator(&mut self, creator: u32) {
+        self.creator = creator;
+    }
+    pub fn get_creation_date_time(&self) -> chrono::NaiveDateTime {
+        self.created
+    }
+    pub fn set_creation_date_time(&mut self, created: chrono::NaiveDateTime) {
+        self.created = created;
+    }
+    pub fn get_profile_id(&self) -> ProfileID {
+        self.profile_id
+    }
+    pub fn set_profile_id(&mut self, profile_id: ProfileID) {
+        self.profile_id = profile_id;
+    }
+    /// Returns the PCS illuminant stored in the header.
+    pub fn get_illuminant(&self) -> CIEXYZ {
+        self.illuminant
+    }
+    pub fn set_illuminant(&mut self, illuminant: CIEXYZ) {
+        self.illuminant = illuminant;
+    }
+
+    pub fn new() -> Self {
+        Self::default()
+    }
     pub fn open_from_file<P: AsRef<Path>>(
         filename: P,
         mode: AccessMode,
-    ) -> io::Result<Box<Profile>> {
+    ) -> io::Result<Box<Profile<'a>>> {
         let mut context = GLOBAL_CONTEXT.lock().unwrap();
         Self::open_from_file_thr(&mut context, filename, mode)
     }
+    /// Opens a profile from a file.
+    ///
+    /// In [`AccessMode::Read`], the header and tag directory are read right away. In [`AccessMode::Write`], the file is
+    /// created (or truncated) and an empty profile bound to it is returned. Header fields and tags can then be set, and
+    /// the profile is written into the file when it is [`close`](Profile::close)d.
     pub fn open_from_file_thr<P>(
         context: &mut Context,
         filename: P,
         mode: AccessMode,
-    ) -> io::Result<Box<Profile>>
+    ) -> io::Result<Box<Profile<'a>>>
     where
         P: AsRef<Path>,
     {
+        let io: Box<dyn IOHandler + 'a> = match mode {
+            AccessMode::Read => Box::new(File::open(filename)?),
+            AccessMode::Write => Box::new(File::create(filename)?),
+        };
+
+        Self::open_from_io_with_mode_thr(context, io, mode)
+    }
+    pub fn open_from_mem(mem: &'a [u8]) -> io::Result<Box<Profile<'a>>> {
+        let mut context = GLOBAL_CONTEXT.lock().unwrap();
+        Self::open_from_mem_thr(&mut context, mem)
+    }
+    pub fn open_from_mem_thr(context: &mut Context, mem: &'a [u8]) -> io::Result<Box<Profile<'a>>> {
+        Self::open_from_io_thr(context, Box::new(FileMemReadOnly::new(mem)))
+    }
+    pub fn open_from_io(io: Box<dyn IOHandler + 'a>) -> io::Result<Box<Profile<'a>>> {
+        let mut context = GLOBAL_CONTEXT.lock().unwrap();
+        Self::open_from_io_thr(&mut context, io)
+    }
+    pub fn open_from_io_thr(
+        context: &mut Context,
+        io: Box<dyn IOHandler + 'a>,
+    ) -> io::Result<Box<Profile<'a>>> {
+        Self::open_from_io_with_mode_thr(context, io, AccessMode::Read)
+    }
+    pub fn open_from_io_with_mode(
+        io: Box<dyn IOHandler + 'a>,
+        mode: AccessMode,
+    ) -> io::Result<Box<Profile<'a>>> {
+        let mut context = GLOBAL_CONTEXT.lock().unwrap();
+        Self::open_from_io_with_mode_thr(&mut context, io, mode)
+    }
+    /// Opens a profile from an [`IOHandler`].
+    ///
+    /// In [`AccessMode::Write`], `io` is used as the destination of the profile, which is written into it when the
+    /// profile is [`close`](Profile::close)d.
+    pub fn open_from_io_with_mode_thr(
+        context: &mut Context,
+        io: Box<dyn IOHandler + 'a>,
+        mode: AccessMode,
+    ) -> io::Result<Box<Profile<'a>>> {
         let mut profile = Box::new(Self::new());
 
-        if let AccessMode::Read = mode {
-            profile.io = Some(Box::new(File::open(filename)?));
-        } else {
-            profile.io = Some(Box::new(File::create(filename)?));
-            profile.is_write = true;
-        };
+        profile.io = Some(io);
 
-        profile.read_header_thr(context)?;
+        match mode {
+            AccessMode::Read => profile.read_header_thr(context)?,
+            AccessMode::Write => profile.is_write = true,
+        }
 
         Ok(profile)
     }
 
+    pub fn is_write(&self) -> bool {
+        self.is_write
+    }
+
+    pub fn close(self: Box<Self>) -> io::Result<()> {
+        let mut context = GLOBAL_CONTEXT.lock().unwrap();
+        self.close_thr(&mut context)
+    }
+    /// Closes the profile, consuming it.
+    ///
+    /// If the profile was opened in [`AccessMode::Write`], it is first saved into its destination. Any error while
+    /// saving is returned after the underlying [`IOHandler`] is closed.
+    // Profiles are handed out boxed, and taking the box keeps borrowed I/O released as soon as the profile is closed.
+    #[allow(clippy::boxed_local)]
+    pub fn close_thr(mut self: Box<Self>, context: &mut Context) -> io::Result<()> {
+        let mut io = match self.io.take() {
+            Some(io) => io,
+            None => return Ok(()),
+        };
+
+        let saved = if self.is_write {
+            self.save_to_io_thr(context, io.as_mut()).map(|_| ())
+        } else {
+            Ok(())
+        };
+        let closed = io.close();
+
+        saved.and(closed)
+    }
+
     fn read_header(&mut self) -> io::Result<()> {
         let mut context = GLOBAL_CONTEXT.lock().unwrap();
 
@@ -162,16 +364,25 @@ impl Profile {
         // Get creation date/time
         self.created = header.date.into();
 
+        self.illuminant = CIEXYZ {
+            X: s15f16_to_f64(header.illuminant.x),
+            Y: s15f16_to_f64(header.illuminant.y),
+            Z: s15f16_to_f64(header.illuminant.z),
+        };
+
         // The profile ID are 32 raw bytes
         self.profile_id = header.profile_id;
 
         // Read tag directory
         let tag_count = io.read_u32()? as usize;
-        if tag_count > MAX_TABLE_TAG {
+
+        // The directory itself must fit inside the file.
+        if tag_count > header_size.saturating_sub(io.tell()?) / 12 {
             context.signal_error(ErrorCode::Range, format!("Too many tags {}", tag_count));
             return err;
         }
 
+        self.tags = Vec::with_capacity(tag_count);
         for _ in 0..tag_count {
             let mut buf = [0u8; 12];
             io.read(&mut buf)?;
@@ -179,126 +390,1899 @@ impl Profile {
             let tag = TagEntryConverter::from_bytes(buf);
 
             // Perform some sanity check. Offset + size should fall inside file.
-            if tag.offset + tag.size > header_size as u32 || tag.offset + tag.size < tag.offset {
-                continue;
+            match tag.offset.checked_add(tag.size) {
+                Some(end) if end as usize <= header_size => (),
+                _ => continue,
             }
 
-            self.tag_names[self.tag_count] = tag.sig;
-            self.tag_offsets[self.tag_count] = tag.offset as usize;
-            self.tag_sizes[self.tag_count] = tag.size as usize;
+            let offset = tag.offset as usize;
+            let size = tag.size as usize;
+
+            // Search for links. A tag sharing its data with a previous one is linked to it.
+            let linked = self
+                .tags
+                .iter()
+                .find(|prev| prev.offset == offset && prev.size == size && prev.sig != tag.sig)
+                .map(|prev| prev.sig);
+
+            self.tags.push(ProfileTag {
+                sig: tag.sig,
+                linked,
+                offset,
+                size,
+                raw: None,
+                in_memory: false,
+                r#type: None,
+                value: None,
+            });
+        }
+
+        Ok(())
+    }
+
+    pub fn save_to_file<P: AsRef<Path>>(&mut self, filename: P) -> io::Result<usize> {
+        let mut context = GLOBAL_CONTEXT.lock().unwrap();
+        self.save_to_file_thr(&mut context, filename)
+    }
+    pub fn save_to_file_thr<P: AsRef<Path>>(
+        &mut self,
+        context: &mut Context,
+        filename: P,
+    ) -> io::Result<usize> {
+        let mut file = Box::new(File::create(filename)?);
+        let used_space = self.save_to_io_thr(context, file.as_mut())?;
+        file.close()?;
+
+        Ok(used_space)
+    }
+    pub fn save_to_mem(&mut self) -> io::Result<Vec<u8>> {
+        let mut context = GLOBAL_CONTEXT.lock().unwrap();
+        self.save_to_mem_thr(&mut context)
+    }
+    pub fn save_to_mem_thr(&mut self, context: &mut Context) -> io::Result<Vec<u8>> {
+        let mut mem = FileMem::new(Vec::new());
+        self.save_to_io_thr(context, &mut mem)?;
+
+        Ok(mem.cursor.into_inner())
+    }
+    pub fn save_to_io(&mut self, io: &mut dyn IOHandler) -> io::Result<usize> {
+        let mut context = GLOBAL_CONTEXT.lock().unwrap();
+        self.save_to_io_thr(&mut context, io)
+    }
+    /// Writes the profile into `io`, returning the number of bytes written.
+    ///
+    /// Saving is done in two passes. The first pass writes into a [`FileNull`] to compute the tag directory and the
+    /// total size of the profile, and the second pass writes the header, the directory and the tag data into memory.
+    /// V4 profiles then get their profile ID computed (see [`compute_md5`](Profile::compute_md5)) before the whole
+    /// profile is written into `io` starting from its current position.
+    pub fn save_to_io_thr(
+        &mut self,
+        context: &mut Context,
+        io: &mut dyn IOHandler,
+    ) -> io::Result<usize> {
+        let mut data = self.serialize(context)?;
+
+        if self.version >= 0x04000000 {
+            self.profile_id = compute_profile_id(&data)?;
+            data[84..100].copy_from_slice(&self.profile_id.to_bytes());
+        }
+
+        io.write(&data)?;
+
+        Ok(data.len())
+    }
+
+    pub fn compute_md5(&mut self) -> io::Result<ProfileID> {
+        let mut context = GLOBAL_CONTEXT.lock().unwrap();
+        self.compute_md5_thr(&mut context)
+    }
+    /// Computes the profile ID of the profile as it would be saved.
+    ///
+    /// As specified in ICC.1:2022 clause 7.2.18, the ID is the MD5 digest of the whole profile with the profile flags,
+    /// rendering intent and profile ID header fields set to zero.
+    pub fn compute_md5_thr(&mut self, context: &mut Context) -> io::Result<ProfileID> {
+        let data = self.serialize(context)?;
+
+        compute_profile_id(&data)
+    }
+
+    pub fn verify_id(&mut self) -> io::Result<bool> {
+        let mut context = GLOBAL_CONTEXT.lock().unwrap();
+        self.verify_id_thr(&mut context)
+    }
+    /// Returns whether the profile ID stored in the header matches the data of the profile.
+    ///
+    /// Profiles read from an [`IOHandler`] are checked against the bytes they were read from, any other profile is
+    /// checked against the data it would be saved as. A profile without an ID (all zeros) never matches.
+    pub fn verify_id_thr(&mut self, context: &mut Context) -> io::Result<bool> {
+        if self.profile_id.to_bytes() == [0u8; 16] {
+            return Ok(false);
+        }
 
-            // Search for links
-            for j in 0..self.tag_count {
-                if self.tag_offsets[j] == tag.offset as usize
-                    && self.tag_sizes[j] == tag.size as usize
-                {
-                    self.tag_linked[j] = Some(self.tag_names[j]);
+        let data = match self.io {
+            Some(ref mut io) if !self.is_write => {
+                io.seek(SeekFrom::Start(0))?;
+                let header_size = io.read_u32()? as usize;
+                let size = header_size.min(io.reported_size()?);
+                if size < 128 {
+                    return Err(Error::from(ErrorKind::InvalidData));
                 }
+
+                let mut data = vec![0u8; size];
+                io.seek(SeekFrom::Start(0))?;
+                io.read(&mut data)?;
+
+                data
             }
+            _ => self.serialize(context)?,
+        };
+
+        Ok(compute_profile_id(&data)? == self.profile_id)
+    }
+
+    /// Serializes the profile into memory.
+    fn serialize(&mut self, context: &mut Context) -> io::Result<Vec<u8>> {
+        // Pass #1 does compute offsets
+        let mut null = FileNull::new();
+        let placeholder = self
+            .tags
+            .iter()
+            .map(|tag| TagEntry {
+                sig: tag.sig,
+                offset: 0,
+                size: 0,
+            })
+            .collect::<Vec<_>>();
+        self.write_header(&mut null, 0, &placeholder)?;
+        let directory = self.save_tags(context, &mut null)?;
+        let used_space = null.reported_size()?;
+
+        // Pass #2 does save to memory
+        let mut mem = FileMem::new(Vec::with_capacity(used_space));
+        self.write_header(&mut mem, used_space, &directory)?;
+        self.save_tags(context, &mut mem)?;
 
-            self.tag_count += 1;
+        Ok(mem.cursor.into_inner())
+    }
+
+    fn write_header(
+        &self,
+        io: &mut dyn IOHandler,
+        used_space: usize,
+        directory: &[TagEntry],
+    ) -> io::Result<()> {
+        let header = ICCHeader {
+            size: used_space as u32,
+            cmm_id: signatures::LCMS_SIGNATURE,
+            version: self.version,
+            device_class: self.device_class,
+            color_space: self.color_space,
+            pcs: self.pcs,
+            date: self.created.into(),
+            magic: signatures::MAGIC_NUMBER,
+            platform: if cfg!(windows) {
+                signatures::platform::MICROSOFT
+            } else {
+                signatures::platform::MACINTOSH
+            },
+            flags: self.flags,
+            manufacturer: Signature::from(self.manufacturer),
+            model: self.model,
+            attributes: self.attributes,
+            rendering_intent: self.rendering_intent,
+            illuminant: EncodedXYZNumber {
+                x: f64_to_s15f16(self.illuminant.X),
+                y: f64_to_s15f16(self.illuminant.Y),
+                z: f64_to_s15f16(self.illuminant.Z),
+            },
+            creator: Signature::from(self.creator),
+            profile_id: self.profile_id,
+            reserved: [0u8; 28],
+        };
+
+        io.write(&ICCHeaderConverter::to_bytes(header))?;
+
+        // Saves tag directory
+        io.write_u32(directory.len() as u32)?;
+        for entry in directory {
+            io.write(&TagEntryConverter::to_bytes(*entry))?;
         }
 
         Ok(())
     }
 
-    fn search_one_tag(&self, sig: Signature) -> Option<usize> {
-        for i in 0..self.tag_count {
-            if sig == self.tag_names[i] {
-                return Some(i);
+    fn save_tags(
+        &mut self,
+        context: &mut Context,
+        io: &mut dyn IOHandler,
+    ) -> io::Result<Vec<TagEntry>> {
+        let mut directory = self
+            .tags
+            .iter()
+            .map(|tag| TagEntry {
+                sig: tag.sig,
+                offset: 0,
+                size: 0,
+            })
+            .collect::<Vec<_>>();
+        for (i, tag) in self.tags.iter().enumerate() {
+            // Linked tags are not written, they share the data of the tag they are linked to.
+            if tag.linked.is_some() {
+                continue;
+            }
+
+            let begin = io.tell()?;
+
+            if let Some(ref raw) = tag.raw {
+                io.write(raw)?;
+            } else if let (true, Some(value)) = (tag.in_memory, &tag.value) {
+                self.write_tag_value(context, tag.sig, value, io)?;
+            } else {
+                // Reach here if we are copying a tag from a disk-based ICC profile which has not been modified by user.
+                let orig = match self.io {
+                    Some(ref mut b) => b.as_mut(),
+                    None => {
+                        context.signal_error(
+                            ErrorCode::Write,
+                            format!("no data available for tag '{:?}'", tag.sig),
+                        );
+                        return Err(Error::from(ErrorKind::InvalidData));
+                    }
+                };
+
+                let mut data = vec![0u8; tag.size];
+                orig.seek(SeekFrom::Start(tag.offset as u64))?;
+                orig.read(&mut data)?;
+                io.write(&data)?;
             }
+
+            directory[i].offset = begin as u32;
+            directory[i].size = (io.tell()? - begin) as u32;
+
+            // Align to 32 bit boundary.
+            io.write_alignment()?;
         }
-        None
+
+        self.set_links(context, &mut directory)?;
+
+        Ok(directory)
     }
-    fn search_tag(&self, mut sig: Signature, follow_links: bool) -> Option<usize> {
-        loop {
-            let n = self.search_one_tag(sig);
-            if let Option::None = n {
-                return None;
+
+    /// Writes the typed value of the tag `sig`, preceded by its type base, using the type handler its tag descriptor
+    /// decides on for the version of this profile.
+    fn write_tag_value(
+        &self,
+        context: &mut Context,
+        sig: Signature,
+        value: &TagValue,
+        io: &mut dyn IOHandler,
+    ) -> io::Result<()> {
+        let descriptor = match get_tag_descriptor(context, sig) {
+            Some(descriptor) => descriptor,
+            None => {
+                context.signal_error(
+                    ErrorCode::Internal,
+                    format!("(Internal) no descriptor for tag '{:?}'", sig),
+                );
+                return Err(Error::from(ErrorKind::InvalidData));
             }
-            if !follow_links {
-                return n;
+        };
+
+        let r#type = match descriptor.decide_type(self.get_version(), value) {
+            Some(r#type) => r#type,
+            None => {
+                context.signal_error(
+                    ErrorCode::Internal,
+                    format!("(Internal) no type for tag '{:?}'", sig),
+                );
+                return Err(Error::from(ErrorKind::InvalidData));
             }
+        };
 
-            let n = n.unwrap();
-            let linked_sig = self.tag_linked[n];
+        let mut handler = match get_tag_type_handler(context, r#type) {
+            Some(handler) => handler,
+            None => {
+                context.signal_error(
+                    ErrorCode::Internal,
+                    format!("(Internal) no handler for tag '{:?}'", sig),
+                );
+                return Err(Error::from(ErrorKind::InvalidData));
+            }
+        };
+        handler.icc_version = self.version;
 
-            match linked_sig {
-                Some(value) => sig = value,
-                None => return Some(n),
+        io.write_type_base(r#type)?;
+        (handler.write)(context, &handler, io, value, descriptor.element_count).inspect_err(|_| {
+            context.signal_error(
+                ErrorCode::Write,
+                format!("Couldn't write type '{:?}'", r#type),
+            );
+        })
+    }
+
+    /// Points the directory entries of linked tags to the data of the tags they are linked to.
+    fn set_links(&self, context: &mut Context, directory: &mut [TagEntry]) -> io::Result<()> {
+        for (i, tag) in self.tags.iter().enumerate() {
+            let linked = match tag.linked {
+                Some(linked) => linked,
+                None => continue,
+            };
+
+            match self.search_tag(linked, true) {
+                Some(j) if self.tags[j].linked.is_none() => {
+                    directory[i].offset = directory[j].offset;
+                    directory[i].size = directory[j].size;
+                }
+                _ => {
+                    context.signal_error(
+                        ErrorCode::Write,
+                        format!(
+                            "tag '{:?}' is linked to missing tag '{:?}'",
+                            tag.sig, linked
+                        ),
+                    );
+                    return Err(Error::from(ErrorKind::InvalidData));
+                }
             }
         }
+
+        Ok(())
     }
-}
 
-impl PartialEq for Profile {
-    fn eq(&self, other: &Self) -> bool {
-        self.created == other.created
-            && self.version == other.version
-            && self.device_class == other.device_class
-            && self.color_space == other.color_space
-            && self.pcs == other.pcs
-            && self.rendering_intent == other.rendering_intent
-            && self.flags == other.flags
-            && self.manufacturer == other.manufacturer
-            && self.model == other.model
-            && self.attributes == other.attributes
-            && self.creator == other.creator
-            && self.profile_id == other.profile_id
-            && self.tag_count == other.tag_count
-            && self.tag_names == other.tag_names
-            && self.tag_linked == other.tag_linked
-            && self.tag_sizes == other.tag_sizes
-            && self.tag_offsets == other.tag_offsets
-            && self.tag_save_as_raw == other.tag_save_as_raw
-            && self.is_write == other.is_write
+    pub fn read_tag(&mut self, sig: Signature) -> io::Result<&TagValue> {
+        let mut context = GLOBAL_CONTEXT.lock().unwrap();
+        self.read_tag_thr(&mut context, sig)
     }
-}
+    /// Reads a tag through the [`TypeHandler`] of its tag type, returning its typed value.
+    ///
+    /// The tag type must be one of the types supported by the [`TagDescriptor`] of the tag. Links are followed, and
+    /// the value is cached, so reading the same tag again doesn't touch the profile's I/O.
+    ///
+    /// [`TypeHandler`]: crate::plugins::TypeHandler
+    /// [`TagDescriptor`]: crate::plugins::TagDescriptor
+    pub fn read_tag_thr(&mut self, context: &mut Context, sig: Signature) -> io::Result<&TagValue> {
+        let n = match self.search_tag(sig, true) {
+            Some(n) => n,
+            None => return Err(Error::from(ErrorKind::NotFound)),
+        };
 
-#[cfg(test)]
-mod test {
-    use chrono::NaiveDate;
+        let value = match self.tags[n].value.take() {
+            Some(value) => value,
+            None => self.read_tag_value(context, sig, n)?,
+        };
 
-    use super::*;
-    use std::io;
+        Ok(self.tags[n].value.insert(value))
+    }
 
-    use crate::{state::Context, testing::get_test_resource_path};
+    pub fn read_lut_tag(&mut self, sig: Signature) -> io::Result<Pipeline> {
+        let mut context = GLOBAL_CONTEXT.lock().unwrap();
+        self.read_lut_tag_thr(&mut context, sig)
+    }
+    /// Reads a LUT based tag (`A2Bx`, `B2Ax`, `gamt` or `prex`) as a [`Pipeline`] whose Lab sides always use the V4
+    /// 16 bit encoding.
+    ///
+    /// lut16 tags of profiles with a Lab PCS hold Lab in the legacy V2 encoding, where L* = 100 is `0xff00` rather than
+    /// `0xffff`, so stages converting from and to it are added around them. Any other tag is returned as read.
+    ///
+    /// The returned pipeline can be written back with [`write_lut_tag`](Profile::write_lut_tag), which removes those
+    /// stages.
+    pub fn read_lut_tag_thr(
+        &mut self,
+        context: &mut Context,
+        sig: Signature,
+    ) -> io::Result<Pipeline> {
+        use signatures::tag_type;
 
-    #[test]
-    fn test_load_file() -> io::Result<()> {
-        let mut context = Context::new(None);
-        Profile::open_from_file_thr(
-            &mut context,
-            get_test_resource_path("sRGB_v4_ICC_preference.icc"),
-            AccessMode::Read,
+        let mut lut = match self.read_tag_thr(context, sig)? {
+            TagValue::Pipeline(lut) => lut.clone(),
+            _ => return Err(Error::from(ErrorKind::InvalidData)),
+        };
+
+        // We need to adjust data only for Lab16
+        let r#type = self.search_tag(sig, true).and_then(|n| self.tags[n].r#type);
+        if r#type != Some(tag_type::LUT16) {
+            return Ok(lut);
+        }
+        let (lab_in, lab_out) = self.get_lab_lut16_sides(sig);
+
+        if lab_in {
+            let stage = Stage::new_lab_v4_to_v2(context)
+                .ok_or_else(|| Error::from(ErrorKind::InvalidData))?;
+            if !lut.insert_stage(context, StageLoc::AtBegin, stage) {
+                return Err(Error::from(ErrorKind::InvalidData));
+            }
+        }
+        if lab_out {
+            let stage = Stage::new_lab_v2_to_v4(context)
+                .ok_or_else(|| Error::from(ErrorKind::InvalidData))?;
+            if !lut.insert_stage(context, StageLoc::AtEnd, stage) {
+                return Err(Error::from(ErrorKind::InvalidData));
+            }
+        }
+
+        Ok(lut)
+    }
+
+    pub fn write_lut_tag(&mut self, sig: Signature, lut: &Pipeline) -> io::Result<()> {
+        let mut context = GLOBAL_CONTEXT.lock().unwrap();
+        self.write_lut_tag_thr(&mut context, sig, lut)
+    }
+    /// Writes a LUT based tag (`A2Bx`, `B2Ax`, `gamt` or `prex`) from a [`Pipeline`] whose Lab sides use the V4 16 bit
+    /// encoding, the reverse of [`read_lut_tag`](Profile::read_lut_tag).
+    ///
+    /// If the tag is to be written as lut16 in a profile with a Lab PCS, its Lab sides are converted to the legacy V2
+    /// encoding first. The type is decided for the current version of the profile, so the version should be set
+    /// before writing.
+    pub fn write_lut_tag_thr(
+        &mut self,
+        context: &mut Context,
+        sig: Signature,
+        lut: &Pipeline,
+    ) -> io::Result<()> {
+        let value = TagValue::Pipeline(lut.clone());
+
+        // We need to adjust data only for Lab16
+        let r#type = get_tag_descriptor(context, sig)
+            .and_then(|descriptor| descriptor.decide_type(self.get_version(), &value));
+        if r#type != Some(signatures::tag_type::LUT16) {
+            return self.write_tag_thr(context, sig, value);
+        }
+        let (lab_in, lab_out) = self.get_lab_lut16_sides(sig);
+
+        let lut = encode_lab_v2(context, lut, lab_in, lab_out)?;
+        self.write_tag_thr(context, sig, TagValue::Pipeline(lut))
+    }
+
+    /// Returns whether the input and the output of the lut16 tag `sig` hold Lab in the V2 encoding, which is the case
+    /// for the sides of LUT based tags facing a Lab PCS or a Lab device.
+    fn get_lab_lut16_sides(&self, sig: Signature) -> (bool, bool) {
+        use signatures::{color_space, tag};
+
+        if self.pcs != color_space::LAB {
+            return (false, false);
+        }
+
+        let device_is_lab = self.color_space == color_space::LAB;
+        match sig {
+            tag::A_TO_B0 | tag::A_TO_B1 | tag::A_TO_B2 => (device_is_lab, true),
+            tag::B_TO_A0 | tag::B_TO_A1 | tag::B_TO_A2 => (true, device_is_lab),
+            tag::GAMUT => (true, false),
+            tag::PREVIEW0 | tag::PREVIEW1 | tag::PREVIEW2 => (true, true),
+            _ => (false, false),
+        }
+    }
+
+    pub fn read_profile_sequence(&mut self) -> io::Result<Sequence> {
+        let mut context = GLOBAL_CONTEXT.lock().unwrap();
+        self.read_profile_sequence_thr(&mut context)
+    }
+    /// Reads the profiles this profile was built from, joining the descriptions of the `pseq` tag with the IDs and
+    /// descriptions of the `psid` tag.
+    ///
+    /// When only one of the tags is present it is returned as read, and when they don't describe the same number of
+    /// profiles the `pseq` tag wins.
+    pub fn read_profile_sequence_thr(&mut self, context: &mut Context) -> io::Result<Sequence> {
+        use signatures::tag;
+
+        let mut read = |sig| match self.read_tag_thr(context, sig) {
+            Ok(TagValue::Sequence(seq)) => Ok(Some(seq.clone())),
+            Ok(_) => Err(Error::from(ErrorKind::InvalidData)),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err),
+        };
+        let desc = read(tag::PROFILE_SEQUENCE_DESC)?;
+        let ids = read(tag::PROFILE_SEQUENCE_ID)?;
+
+        match (desc, ids) {
+            (Some(desc), Some(ids)) => Ok(desc.merge_ids(&ids)),
+            (Some(seq), None) | (None, Some(seq)) => Ok(seq),
+            (None, None) => Err(Error::from(ErrorKind::NotFound)),
+        }
+    }
+
+    pub fn write_profile_sequence(&mut self, seq: &Sequence) -> io::Result<()> {
+        let mut context = GLOBAL_CONTEXT.lock().unwrap();
+        self.write_profile_sequence_thr(&mut context, seq)
+    }
+    /// Writes the profiles this profile was built from as a `pseq` tag, and also as a `psid` tag in V4 profiles.
+    pub fn write_profile_sequence_thr(
+        &mut self,
+        context: &mut Context,
+        seq: &Sequence,
+    ) -> io::Result<()> {
+        use signatures::tag;
+
+        self.write_tag_thr(
+            context,
+            tag::PROFILE_SEQUENCE_DESC,
+            TagValue::Sequence(seq.clone()),
         )?;
 
+        if self.get_version() >= 4.0 {
+            self.write_tag_thr(
+                context,
+                tag::PROFILE_SEQUENCE_ID,
+                TagValue::Sequence(seq.clone()),
+            )?;
+        }
+
         Ok(())
     }
 
-    #[test]
-    fn test_file_loads_with_proper_data_and_endianness() -> io::Result<()> {
-        let mut context = Context::new(None);
-        let mut expected_names = [Signature::default(); MAX_TABLE_TAG];
-        expected_names[..9].copy_from_slice(&[
-            Signature::new(b"desc"),
-            Signature::new(b"A2B0"),
-            Signature::new(b"A2B1"),
-            Signature::new(b"B2A0"),
-            Signature::new(b"B2A1"),
-            Signature::new(b"rig0"),
-            Signature::new(b"wtpt"),
-            Signature::new(b"cprt"),
-            Signature::new(b"chad"),
-        ]);
-        let mut expected_sizes = [0usize; MAX_TABLE_TAG];
-        expected_sizes[..9].copy_from_slice(&[118, 29712, 436, 29748, 508, 12, 20, 118, 44]);
-        let mut expected_offsets = [0usize; MAX_TABLE_TAG];
-        expected_offsets[..9]
-            .copy_from_slice(&[240, 360, 30072, 30508, 60256, 60764, 60776, 60796, 60916]);
-        let expected = Box::new(Profile {
-            io: None,
-            created: NaiveDate::from_ymd(2007, 07, 25).and_hms(0, 5, 37),
+    pub fn metadata(&mut self) -> io::Result<Dict> {
+        let mut context = GLOBAL_CONTEXT.lock().unwrap();
+        self.metadata_thr(&mut context)
+    }
+    /// Reads the name/value pairs of the `meta` tag. Look values up with [`Dict::get_value`].
+    pub fn metadata_thr(&mut self, context: &mut Context) -> io::Result<Dict> {
+        match self.read_tag_thr(context, signatures::tag::META)? {
+            TagValue::Dict(dict) => Ok(dict.clone()),
+            _ => Err(Error::from(ErrorKind::InvalidData)),
+        }
+    }
+
+    /// Reads the value of the `n`th tag, which is described by the tag descriptor of `sig`.
+    fn read_tag_value(
+        &mut self,
+        context: &mut Context,
+        sig: Signature,
+        n: usize,
+    ) -> io::Result<TagValue> {
+        let tag = &self.tags[n];
+
+        if tag.size < 8 {
+            context.signal_error(
+                ErrorCode::CorruptionDetected,
+                format!("Corrupted tag '{:?}'", sig),
+            );
+            return Err(Error::from(ErrorKind::InvalidData));
+        }
+
+        let mut mem;
+        let io: &mut dyn IOHandler = match (&tag.raw, &mut self.io) {
+            (Some(raw), _) => {
+                mem = FileMemReadOnly::new(raw.as_slice());
+                &mut mem
+            }
+            (None, Some(io)) if !self.is_write => {
+                io.seek(SeekFrom::Start(tag.offset as u64))?;
+                io.as_mut()
+            }
+            _ => {
+                context.signal_error(
+                    ErrorCode::CorruptionDetected,
+                    format!("Corrupted profile: no data available for tag '{:?}'", sig),
+                );
+                return Err(Error::from(ErrorKind::InvalidData));
+            }
+        };
+
+        // Search for support on this tag
+        let descriptor = match get_tag_descriptor(context, sig) {
+            Some(descriptor) => descriptor,
+            None => {
+                context.signal_error(
+                    ErrorCode::UnknownExtension,
+                    format!("Unknown tag type '{:?}' found.", sig),
+                );
+                return Err(Error::from(ErrorKind::InvalidData));
+            }
+        };
+
+        // if supported, get type and check if in list
+        let base_type = io.read_type_base()?;
+        if !descriptor.is_type_supported(base_type) {
+            context.signal_error(
+                ErrorCode::UnknownExtension,
+                format!("Unsupported type '{:?}' for tag '{:?}'", base_type, sig),
+            );
+            return Err(Error::from(ErrorKind::InvalidData));
+        }
+
+        let mut handler = match get_tag_type_handler(context, base_type) {
+            Some(handler) => handler,
+            None => {
+                context.signal_error(
+                    ErrorCode::UnknownExtension,
+                    format!("Unknown tag type '{:?}' found.", base_type),
+                );
+                return Err(Error::from(ErrorKind::InvalidData));
+            }
+        };
+        handler.icc_version = self.version;
+
+        let mut element_count = 0;
+        let value = (handler.read)(context, &handler, io, &mut element_count, tag.size - 8)
+            .inspect_err(|_| {
+                // The tag type is supported, but something wrong happened and we cannot read the tag.
+                context.signal_error(
+                    ErrorCode::CorruptionDetected,
+                    format!("Corrupted tag '{:?}'", sig),
+                );
+            })?;
+
+        // This is a weird error that may be a symptom of something more serious, the number of stored items is
+        // actually less than the number of required elements.
+        if element_count < descriptor.element_count {
+            context.signal_error(
+                ErrorCode::CorruptionDetected,
+                format!(
+                    "'{:?}' Inconsistent number of items: expected {}, got {}",
+                    sig, descriptor.element_count, element_count
+                ),
+            );
+            return Err(Error::from(ErrorKind::InvalidData));
+        }
+
+        self.tags[n].r#type = Some(base_type);
+        Ok(value)
+    }
+
+    pub fn write_tag(&mut self, sig: Signature, value: TagValue) -> io::Result<()> {
+        let mut context = GLOBAL_CONTEXT.lock().unwrap();
+        self.write_tag_thr(&mut context, sig, value)
+    }
+    /// Creates (or replaces) a tag with a typed value.
+    ///
+    /// The tag type the value is written as is chosen by the [`TagDescriptor`] of the tag for the version of the
+    /// profile when it gets saved, so for example a description is written as `desc` in V2 profiles and as `mluc` in
+    /// V4 profiles.
+    ///
+    /// Values are written as given. Pipelines using the V4 16 bit Lab encoding, as returned by
+    /// [`read_lut_tag`](Profile::read_lut_tag), should be written with [`write_lut_tag`](Profile::write_lut_tag).
+    ///
+    /// [`TagDescriptor`]: crate::plugins::TagDescriptor
+    pub fn write_tag_thr(
+        &mut self,
+        context: &mut Context,
+        sig: Signature,
+        value: TagValue,
+    ) -> io::Result<()> {
+        let descriptor = match get_tag_descriptor(context, sig) {
+            Some(descriptor) => descriptor,
+            None => {
+                context.signal_error(
+                    ErrorCode::UnknownExtension,
+                    format!("Unsupported tag '{:?}'", sig),
+                );
+                return Err(Error::from(ErrorKind::InvalidInput));
+            }
+        };
+
+        // Now we need to know which type to use. It depends on the version.
+        let r#type = match descriptor.decide_type(self.get_version(), &value) {
+            Some(r#type) => r#type,
+            None => {
+                context.signal_error(
+                    ErrorCode::UnknownExtension,
+                    format!("No type to write tag '{:?}' as", sig),
+                );
+                return Err(Error::from(ErrorKind::InvalidInput));
+            }
+        };
+
+        // Does the tag support this type, and do we have a handler for it?
+        if !descriptor.is_type_supported(r#type) || get_tag_type_handler(context, r#type).is_none()
+        {
+            context.signal_error(
+                ErrorCode::UnknownExtension,
+                format!("Unsupported type '{:?}' for tag '{:?}'", r#type, sig),
+            );
+            return Err(Error::from(ErrorKind::InvalidInput));
+        }
+
+        let tag = ProfileTag {
+            sig,
+            linked: None,
+            offset: 0,
+            size: 0,
+            raw: None,
+            in_memory: true,
+            r#type: Some(r#type),
+            value: Some(value),
+        };
+
+        match self.search_one_tag(sig) {
+            Some(i) => self.tags[i] = tag,
+            None => self.tags.push(tag),
+        }
+
+        Ok(())
+    }
+
+    pub fn read_raw_tag(&mut self, sig: Signature) -> io::Result<Vec<u8>> {
+        let mut context = GLOBAL_CONTEXT.lock().unwrap();
+        self.read_raw_tag_thr(&mut context, sig)
+    }
+    /// Reads the data of a tag as raw bytes, including its 8 byte type base.
+    ///
+    /// Tags read from the profile's I/O are returned as stored, without going through any type handler. Tags written
+    /// with [`write_tag`](Profile::write_tag) are serialized by their type handler, as they would be when saved.
+    ///
+    /// Links are followed, so reading a linked tag returns the data of the tag it is linked to.
+    pub fn read_raw_tag_thr(
+        &mut self,
+        context: &mut Context,
+        sig: Signature,
+    ) -> io::Result<Vec<u8>> {
+        let n = match self.search_tag(sig, true) {
+            Some(n) => n,
+            None => return Err(Error::from(ErrorKind::NotFound)),
+        };
+        let tag = &self.tags[n];
+
+        if let Some(ref raw) = tag.raw {
+            return Ok(raw.clone());
+        }
+
+        if let (true, Some(value)) = (tag.in_memory, &tag.value) {
+            let mut mem = FileMem::new(Vec::new());
+            self.write_tag_value(context, tag.sig, value, &mut mem)?;
+            return Ok(mem.cursor.into_inner());
+        }
+
+        let io = match self.io {
+            Some(ref mut b) if !self.is_write => b.as_mut(),
+            _ => return Err(Error::from(ErrorKind::NotFound)),
+        };
+
+        let mut data = vec![0u8; tag.size];
+        io.seek(SeekFrom::Start(tag.offset as u64))?;
+        io.read(&mut data)?;
+
+        Ok(data)
+    }
+
+    /// Creates (or replaces) a tag with raw bytes, which are saved as-is without going through any type handler.
+    ///
+    /// `data` must include the 8 byte type base of the tag.
+    pub fn write_raw_tag(&mut self, sig: Signature, data: &[u8]) {
+        let tag = ProfileTag {
+            sig,
+            linked: None,
+            offset: 0,
+            size: data.len(),
+            raw: Some(data.to_vec()),
+            in_memory: true,
+            r#type: None,
+            value: None,
+        };
+
+        match self.search_one_tag(sig) {
+            Some(i) => self.tags[i] = tag,
+            None => self.tags.push(tag),
+        }
+    }
+
+    /// Creates (or replaces) the tag `dest` as a link to the tag `src`, so that both tags share the same data.
+    ///
+    /// Reading `dest` returns the data of `src`, and when saving, the data is written once and referenced by both
+    /// directory entries.
+    pub fn link_tag(&mut self, dest: Signature, src: Signature) {
+        let tag = ProfileTag {
+            sig: dest,
+            linked: Some(src),
+            offset: 0,
+            size: 0,
+            raw: None,
+            in_memory: false,
+            r#type: None,
+            value: None,
+        };
+
+        match self.search_one_tag(dest) {
+            Some(i) => self.tags[i] = tag,
+            None => self.tags.push(tag),
+        }
+    }
+
+    /// Returns the signature of the tag `sig` is linked to, if any.
+    pub fn tag_linked_to(&self, sig: Signature) -> Option<Signature> {
+        self.search_one_tag(sig).and_then(|i| self.tags[i].linked)
+    }
+
+    fn search_one_tag(&self, sig: Signature) -> Option<usize> {
+        self.tags.iter().position(|tag| tag.sig == sig)
+    }
+    fn search_tag(&self, mut sig: Signature, follow_links: bool) -> Option<usize> {
+        // A chain of links can't be longer than the directory itself, anything longer is a cycle.
+        for _ in 0..=self.tags.len() {
+            let n = self.search_one_tag(sig)?;
+            if !follow_links {
+                return Some(n);
+            }
+
+            match self.tags[n].linked {
+                Some(value) => sig = value,
+                None => return Some(n),
+            }
+        }
+
+        None
+    }
+}
+
+/// Computes the MD5 profile ID of a serialized profile, with the profile flags, rendering intent and profile ID fields
+/// taken as zero.
+/// Computes the profile ID of the serialized profile `data`, which must hold at least the 128 byte header.
+fn compute_profile_id(data: &[u8]) -> io::Result<ProfileID> {
+    if data.len() < 128 {
+        return Err(Error::from(ErrorKind::InvalidData));
+    }
+
+    let mut md5 = Md5::new();
+
+    md5.update(&data[..44]);
+    md5.update(&[0u8; 4]); // Profile flags
+    md5.update(&data[48..64]);
+    md5.update(&[0u8; 4]); // Rendering intent
+    md5.update(&data[68..84]);
+    md5.update(&[0u8; 16]); // Profile ID
+    md5.update(&data[100..]);
+
+    Ok(ProfileID::from_bytes(md5.finish()))
+}
+
+/// Converts the digits of `value` from `base_in` to `base_out`, as used by the BCD encoded header version.
+fn base_to_base(mut value: u32, base_in: u32, base_out: u32) -> u32 {
+    let mut digits = Vec::new();
+    while value > 0 {
+        digits.push(value % base_in);
+        value /= base_in;
+    }
+
+    digits
+        .iter()
+        .rev()
+        .fold(0, |out, digit| out * base_out + digit)
+}
+
+impl PartialEq for Profile<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.created == other.created
+            && self.version == other.version
+            && self.device_class == other.device_class
+            && self.color_space == other.color_space
+            && self.pcs == other.pcs
+            && self.rendering_intent == other.rendering_intent
+            && self.flags == other.flags
+            && self.manufacturer == other.manufacturer
+            && self.model == other.model
+            && self.attributes == other.attributes
+            && self.creator == other.creator
+            && self.illuminant == other.illuminant
+            && self.profile_id == other.profile_id
+            && self.tags == other.tags
+            && self.is_write == other.is_write
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::NaiveDate;
+
+    use super::*;
+    use std::io;
+
+    use crate::{
+        io::{FileMem, StreamIO},
+        plugins::{TagDescriptor, TagListItem, TypeHandler},
+        state::Context,
+        testing::{get_temp_file_path, get_test_resource_path},
+    };
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use test_case::test_case;
+
+    #[test]
+    fn test_load_file() -> io::Result<()> {
+        let mut context = Context::new(None);
+        Profile::open_from_file_thr(
+            &mut context,
+            get_test_resource_path("sRGB_v4_ICC_preference.icc"),
+            AccessMode::Read,
+        )?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_mem() -> io::Result<()> {
+        let mut context = Context::new(None);
+        let data = std::fs::read(get_test_resource_path("sRGB_v4_ICC_preference.icc"))?;
+
+        let from_mem = Profile::open_from_mem_thr(&mut context, &data)?;
+        let from_file = Profile::open_from_file_thr(
+            &mut context,
+            get_test_resource_path("sRGB_v4_ICC_preference.icc"),
+            AccessMode::Read,
+        )?;
+
+        assert_eq!(from_mem, from_file);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_io() -> io::Result<()> {
+        let mut context = Context::new(None);
+        let data = std::fs::read(get_test_resource_path("sRGB_v4_ICC_preference.icc"))?;
+
+        let profile = Profile::open_from_io_thr(&mut context, Box::new(FileMem::new(data)))?;
+
+        assert_eq!(profile.get_tag_count(), 9);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_shared_mem_without_copy() -> io::Result<()> {
+        let mut context = Context::new(None);
+        let data: std::sync::Arc<[u8]> =
+            std::fs::read(get_test_resource_path("sRGB_v4_ICC_preference.icc"))?.into();
+
+        let profile =
+            Profile::open_from_io_thr(&mut context, Box::new(FileMemReadOnly::new(data.clone())))?;
+
+        assert_eq!(profile.get_tag_count(), 9);
+        assert_eq!(std::sync::Arc::strong_count(&data), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_stream() -> io::Result<()> {
+        let mut context = Context::new(None);
+        let file = File::open(get_test_resource_path("sRGB_v4_ICC_preference.icc"))?;
+
+        let profile = Profile::open_from_io_thr(
+            &mut context,
+            Box::new(StreamIO::new(io::BufReader::new(file))?),
+        )?;
+
+        assert_eq!(profile.get_tag_count(), 9);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_save_to_mem_round_trips() -> io::Result<()> {
+        let mut context = Context::new(None);
+        let original = std::fs::read(get_test_resource_path("sRGB_v4_ICC_preference.icc"))?;
+        let mut profile = Profile::open_from_mem_thr(&mut context, &original)?;
+
+        let saved = profile.save_to_mem_thr(&mut context)?;
+        let reopened = Profile::open_from_mem_thr(&mut context, &saved)?;
+
+        assert_eq!(
+            u32::from_be_bytes(saved[0..4].try_into().unwrap()) as usize,
+            saved.len()
+        );
+        assert_eq!(reopened.get_tag_count(), profile.get_tag_count());
+        assert_eq!(reopened.version, profile.version);
+        assert_eq!(reopened.created, profile.created);
+        assert_eq!(reopened.profile_id, profile.profile_id);
+        for (tag, reopened_tag) in profile.tags.iter().zip(reopened.tags.iter()) {
+            assert_eq!(reopened_tag.sig, tag.sig);
+            assert_eq!(reopened_tag.size, tag.size);
+            assert_eq!(reopened_tag.offset % 4, 0);
+
+            let expected = &original[tag.offset..tag.offset + tag.size];
+            let actual = &saved[reopened_tag.offset..reopened_tag.offset + reopened_tag.size];
+            assert_eq!(actual, expected);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_save_to_io_matches_save_to_mem() -> io::Result<()> {
+        let mut context = Context::new(None);
+        let mut profile = Profile::open_from_file_thr(
+            &mut context,
+            get_test_resource_path("sRGB_v4_ICC_preference.icc"),
+            AccessMode::Read,
+        )?;
+
+        let mut null = FileNull::new();
+        let used_space = profile.save_to_io_thr(&mut context, &mut null)?;
+        let saved = profile.save_to_mem_thr(&mut context)?;
+
+        assert_eq!(used_space, saved.len());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_save_to_file() -> io::Result<()> {
+        let mut context = Context::new(None);
+        let filename = get_temp_file_path("test_save_to_file");
+        let mut profile = Profile::open_from_file_thr(
+            &mut context,
+            get_test_resource_path("sRGB_v4_ICC_preference.icc"),
+            AccessMode::Read,
+        )?;
+
+        let used_space = profile.save_to_file_thr(&mut context, &filename)?;
+        let reopened = Profile::open_from_file_thr(&mut context, &filename, AccessMode::Read)?;
+
+        assert_eq!(std::fs::metadata(&filename)?.len() as usize, used_space);
+        assert_eq!(reopened.get_tag_count(), 9);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_save_empty_profile() -> io::Result<()> {
+        let mut context = Context::new(None);
+        let mut profile = Profile::new();
+
+        let saved = profile.save_to_mem_thr(&mut context)?;
+
+        assert_eq!(saved.len(), 132);
+        assert_eq!(&saved[36..40], b"acsp");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_mode_saves_on_close() -> io::Result<()> {
+        let mut context = Context::new(None);
+        let filename = get_temp_file_path("test_write_mode_saves_on_close");
+
+        let mut profile = Profile::open_from_file_thr(&mut context, &filename, AccessMode::Write)?;
+        assert!(profile.is_write());
+
+        profile.device_class = signatures::profile_class::DISPLAY;
+        profile.color_space = signatures::color_space::RGB;
+        profile.pcs = signatures::color_space::XYZ;
+        profile.version = 0x04300000;
+        profile.close_thr(&mut context)?;
+
+        let reopened = Profile::open_from_file_thr(&mut context, &filename, AccessMode::Read)?;
+
+        assert!(!reopened.is_write());
+        assert_eq!(reopened.device_class, signatures::profile_class::DISPLAY);
+        assert_eq!(reopened.color_space, signatures::color_space::RGB);
+        assert_eq!(reopened.pcs, signatures::color_space::XYZ);
+        assert_eq!(reopened.version, 0x04300000);
+        assert_eq!(reopened.get_tag_count(), 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_mode_io_saves_on_close() -> io::Result<()> {
+        let mut context = Context::new(None);
+        let mut buf = Vec::new();
+
+        let profile = Profile::open_from_io_with_mode_thr(
+            &mut context,
+            Box::new(FileMem::new(&mut buf)),
+            AccessMode::Write,
+        )?;
+        profile.close_thr(&mut context)?;
+
+        assert_eq!(buf.len(), 132);
+        Profile::open_from_mem_thr(&mut context, &buf)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_header_accessors_read_values() -> io::Result<()> {
+        let mut context = Context::new(None);
+        let profile = Profile::open_from_file_thr(
+            &mut context,
+            get_test_resource_path("sRGB_v4_ICC_preference.icc"),
+            AccessMode::Read,
+        )?;
+
+        assert_eq!(
+            profile.get_device_class(),
+            signatures::profile_class::COLOR_SPACE
+        );
+        assert_eq!(profile.get_color_space(), signatures::color_space::RGB);
+        assert_eq!(profile.get_pcs(), signatures::color_space::LAB);
+        assert_eq!(profile.get_encoded_version(), 0x04200000);
+        assert_eq!(profile.get_version(), 4.2);
+        assert_eq!(
+            profile.get_creation_date_time(),
+            NaiveDate::from_ymd_opt(2007, 7, 25)
+                .unwrap()
+                .and_hms_opt(0, 5, 37)
+                .unwrap()
+        );
+        assert!((profile.get_illuminant().X - D50_X).abs() < 0.0001);
+        assert!((profile.get_illuminant().Z - D50_Z).abs() < 0.0001);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_set_version_encodes_bcd() {
+        let mut profile = Profile::new();
+
+        profile.set_version(4.3);
+        assert_eq!(profile.get_encoded_version(), 0x04300000);
+        assert_eq!(profile.get_version(), 4.3);
+
+        profile.set_version(2.1);
+        assert_eq!(profile.get_encoded_version(), 0x02100000);
+
+        profile.set_encoded_version(0x04400000);
+        assert_eq!(profile.get_version(), 4.4);
+    }
+
+    #[test]
+    fn test_header_setters_round_trip_through_save() -> io::Result<()> {
+        let mut context = Context::new(None);
+        let created = NaiveDate::from_ymd_opt(2022, 3, 14)
+            .unwrap()
+            .and_hms_opt(15, 9, 26)
+            .unwrap();
+        let illuminant = CIEXYZ {
+            X: 0.5,
+            Y: 1.0,
+            Z: 0.25,
+        };
+        let mut profile = Profile::new();
+
+        profile.set_device_class(signatures::profile_class::OUTPUT);
+        profile.set_color_space(signatures::color_space::CMYK);
+        profile.set_pcs(signatures::color_space::LAB);
+        profile.set_version(4.3);
+        profile.set_rendering_intent(1);
+        profile.set_flags(2);
+        profile.set_attributes(3);
+        profile.set_manufacturer(Signature::new(b"ACME").into());
+        profile.set_model(5);
+        profile.set_creator(Signature::new(b"test").into());
+        profile.set_creation_date_time(created);
+        profile.set_illuminant(illuminant);
+
+        let saved = profile.save_to_mem_thr(&mut context)?;
+        let reopened = Profile::open_from_mem_thr(&mut context, &saved)?;
+
+        assert_eq!(
+            reopened.get_device_class(),
+            signatures::profile_class::OUTPUT
+        );
+        assert_eq!(reopened.get_color_space(), signatures::color_space::CMYK);
+        assert_eq!(reopened.get_pcs(), signatures::color_space::LAB);
+        assert_eq!(reopened.get_encoded_version(), 0x04300000);
+        assert_eq!(reopened.get_rendering_intent(), 1);
+        assert_eq!(reopened.get_flags(), 2);
+        assert_eq!(reopened.get_attributes(), 3);
+        assert_eq!(reopened.get_manufacturer(), Signature::new(b"ACME").into());
+        assert_eq!(reopened.get_model(), 5);
+        assert_eq!(reopened.get_creator(), Signature::new(b"test").into());
+        assert_eq!(reopened.get_creation_date_time(), created);
+        assert_eq!(reopened.get_illuminant(), illuminant);
+
+        Ok(())
+    }
+
+    /// Builds a minimal profile with `tag_count` distinct 4 byte tags.
+    fn build_profile_with_tags(tag_count: usize) -> Vec<u8> {
+        let data_start = 128 + 4 + 12 * tag_count;
+        let size = data_start + 4 * tag_count;
+        let mut buf = vec![0u8; size];
+
+        buf[0..4].copy_from_slice(&(size as u32).to_be_bytes());
+        buf[24..26].copy_from_slice(&2022u16.to_be_bytes());
+        buf[26..28].copy_from_slice(&1u16.to_be_bytes());
+        buf[28..30].copy_from_slice(&1u16.to_be_bytes());
+        buf[36..40].copy_from_slice(b"acsp");
+        buf[128..132].copy_from_slice(&(tag_count as u32).to_be_bytes());
+        for i in 0..tag_count {
+            let entry = 132 + 12 * i;
+            let offset = data_start + 4 * i;
+
+            buf[entry..entry + 4].copy_from_slice(&(0x7000_0000u32 + i as u32).to_be_bytes());
+            buf[entry + 4..entry + 8].copy_from_slice(&(offset as u32).to_be_bytes());
+            buf[entry + 8..entry + 12].copy_from_slice(&4u32.to_be_bytes());
+            buf[offset..offset + 4].copy_from_slice(&(i as u32).to_be_bytes());
+        }
+
+        buf
+    }
+
+    #[test]
+    fn test_load_profile_with_more_than_100_tags() -> io::Result<()> {
+        let mut context = Context::new(None);
+        let data = build_profile_with_tags(250);
+
+        let mut profile = Profile::open_from_mem_thr(&mut context, &data)?;
+
+        assert_eq!(profile.get_tag_count(), 250);
+        assert_eq!(
+            profile.get_tag_signature(249),
+            Signature::from(0x7000_0000u32 + 249)
+        );
+        assert_eq!(profile.get_tag_signature(250), Signature::default());
+
+        let saved = profile.save_to_mem_thr(&mut context)?;
+        let reopened = Profile::open_from_mem_thr(&mut context, &saved)?;
+
+        assert_eq!(reopened.get_tag_count(), 250);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_rejects_directory_larger_than_file() {
+        let mut context = Context::new(None);
+        let mut data = build_profile_with_tags(2);
+        data[128..132].copy_from_slice(&u32::MAX.to_be_bytes());
+
+        assert!(Profile::open_from_mem_thr(&mut context, &data).is_err());
+    }
+
+    #[test]
+    fn test_load_skips_tags_outside_of_file() -> io::Result<()> {
+        let mut context = Context::new(None);
+        let mut data = build_profile_with_tags(2);
+        data[132 + 4..132 + 8].copy_from_slice(&u32::MAX.to_be_bytes());
+
+        let profile = Profile::open_from_mem_thr(&mut context, &data)?;
+
+        assert_eq!(profile.get_tag_count(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_links_tags_sharing_data() -> io::Result<()> {
+        let mut context = Context::new(None);
+        let mut data = build_profile_with_tags(3);
+        let first = Signature::from(0x7000_0000u32);
+        let second = Signature::from(0x7000_0001u32);
+        // Point the second tag at the data of the first one
+        let first_offset = data[136..140].to_vec();
+        data[148..152].copy_from_slice(&first_offset);
+
+        let profile = Profile::open_from_mem_thr(&mut context, &data)?;
+
+        assert_eq!(profile.tag_linked_to(first), None);
+        assert_eq!(profile.tag_linked_to(second), Some(first));
+        assert_eq!(profile.search_tag(second, true), Some(0));
+        assert_eq!(profile.search_tag(second, false), Some(1));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_save_writes_linked_data_once() -> io::Result<()> {
+        let mut context = Context::new(None);
+        let mut profile = Profile::open_from_file_thr(
+            &mut context,
+            get_test_resource_path("sRGB_v4_ICC_preference.icc"),
+            AccessMode::Read,
+        )?;
+        let unlinked_size = profile.save_to_mem_thr(&mut context)?.len();
+
+        profile.link_tag(signatures::tag::A_TO_B1, signatures::tag::A_TO_B0);
+        profile.link_tag(signatures::tag::A_TO_B2, signatures::tag::A_TO_B1);
+        let saved = profile.save_to_mem_thr(&mut context)?;
+        let reopened = Profile::open_from_mem_thr(&mut context, &saved)?;
+
+        let a2b0 = reopened
+            .search_tag(signatures::tag::A_TO_B0, false)
+            .unwrap();
+        let a2b1 = reopened
+            .search_tag(signatures::tag::A_TO_B1, false)
+            .unwrap();
+        let a2b2 = reopened
+            .search_tag(signatures::tag::A_TO_B2, false)
+            .unwrap();
+
+        assert_eq!(reopened.get_tag_count(), 10);
+        // A2B1 data is no longer written, and A2B2 only adds a directory entry
+        assert_eq!(saved.len(), unlinked_size - 436 + 12);
+        assert_eq!(reopened.tags[a2b1].offset, reopened.tags[a2b0].offset);
+        assert_eq!(reopened.tags[a2b2].offset, reopened.tags[a2b0].offset);
+        assert_eq!(
+            reopened.tag_linked_to(signatures::tag::A_TO_B1),
+            Some(signatures::tag::A_TO_B0)
+        );
+        assert_eq!(
+            reopened.tag_linked_to(signatures::tag::A_TO_B2),
+            Some(signatures::tag::A_TO_B0)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_save_rejects_dangling_links() {
+        let mut context = Context::new(None);
+        let mut profile = Profile::new();
+
+        profile.link_tag(signatures::tag::A_TO_B1, signatures::tag::A_TO_B0);
+
+        assert!(profile.save_to_mem_thr(&mut context).is_err());
+    }
+
+    #[test]
+    fn test_search_tag_stops_on_link_cycles() {
+        let mut profile = Profile::new();
+
+        profile.link_tag(signatures::tag::A_TO_B0, signatures::tag::A_TO_B1);
+        profile.link_tag(signatures::tag::A_TO_B1, signatures::tag::A_TO_B0);
+
+        assert_eq!(profile.search_tag(signatures::tag::A_TO_B0, true), None);
+    }
+
+    #[test]
+    fn test_verify_id_of_file() -> io::Result<()> {
+        let mut context = Context::new(None);
+        let mut profile = Profile::open_from_file_thr(
+            &mut context,
+            get_test_resource_path("sRGB_v4_ICC_preference.icc"),
+            AccessMode::Read,
+        )?;
+
+        assert!(profile.verify_id_thr(&mut context)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_verify_id_ignores_flags_and_intent_but_detects_corruption() -> io::Result<()> {
+        let mut context = Context::new(None);
+        let mut data = std::fs::read(get_test_resource_path("sRGB_v4_ICC_preference.icc"))?;
+
+        data[47] = 1; // flags
+        data[67] = 3; // rendering intent
+        let mut profile = Profile::open_from_mem_thr(&mut context, &data)?;
+        assert!(profile.verify_id_thr(&mut context)?);
+        drop(profile);
+
+        let last = data.len() - 1;
+        data[last] ^= 0xFF;
+        let mut profile = Profile::open_from_mem_thr(&mut context, &data)?;
+        assert!(!profile.verify_id_thr(&mut context)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_verify_id_rejects_truncated_header() -> io::Result<()> {
+        let mut context = Context::new(None);
+        let mut data = std::fs::read(get_test_resource_path("sRGB_v4_ICC_preference.icc"))?;
+
+        data[..4].copy_from_slice(&50u32.to_be_bytes());
+        data[128..132].copy_from_slice(&0u32.to_be_bytes()); // No tags
+        let mut profile = Profile::open_from_mem_thr(&mut context, &data)?;
+
+        let err = profile.verify_id_thr(&mut context).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        assert_eq!(
+            compute_profile_id(&data[..50]).unwrap_err().kind(),
+            ErrorKind::InvalidData
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_save_populates_v4_profile_id() -> io::Result<()> {
+        let mut context = Context::new(None);
+        let mut profile = Profile::new();
+        profile.set_version(4.3);
+        profile.set_rendering_intent(1);
+
+        let expected = profile.compute_md5_thr(&mut context)?;
+        let saved = profile.save_to_mem_thr(&mut context)?;
+        let mut reopened = Profile::open_from_mem_thr(&mut context, &saved)?;
+
+        assert_ne!(expected.to_bytes(), [0u8; 16]);
+        assert_eq!(profile.get_profile_id(), expected);
+        assert_eq!(reopened.get_profile_id(), expected);
+        assert!(reopened.verify_id_thr(&mut context)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_save_leaves_v2_profile_id_empty() -> io::Result<()> {
+        let mut context = Context::new(None);
+        let mut profile = Profile::new();
+        profile.set_version(2.1);
+
+        let saved = profile.save_to_mem_thr(&mut context)?;
+        let mut reopened = Profile::open_from_mem_thr(&mut context, &saved)?;
+
+        assert_eq!(&saved[84..100], &[0u8; 16]);
+        assert!(!reopened.verify_id_thr(&mut context)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_raw_tag() -> io::Result<()> {
+        let mut context = Context::new(None);
+        let mut profile = Profile::open_from_file_thr(
+            &mut context,
+            get_test_resource_path("sRGB_v4_ICC_preference.icc"),
+            AccessMode::Read,
+        )?;
+
+        let wtpt = profile.read_raw_tag(signatures::tag::MEDIA_WHITE_POINT)?;
+
+        assert_eq!(wtpt.len(), 20);
+        assert_eq!(&wtpt[0..4], b"XYZ ");
+        assert_eq!(
+            profile
+                .read_raw_tag(signatures::tag::GRAY_TRC)
+                .unwrap_err()
+                .kind(),
+            ErrorKind::NotFound
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_raw_tag_serializes_written_tags() -> io::Result<()> {
+        let mut context = Context::new(None);
+        let white = TagValue::XYZ(CIEXYZ {
+            X: 0.9642,
+            Y: 1.0,
+            Z: 0.8249,
+        });
+
+        let data = std::fs::read(get_test_resource_path("sRGB_v4_ICC_preference.icc"))?;
+        // Both in a new profile and in one opened for reading
+        let mut new = Profile::new();
+        let mut opened = Profile::open_from_mem_thr(&mut context, &data)?;
+
+        for profile in [&mut new, opened.as_mut()] {
+            profile.write_tag_thr(
+                &mut context,
+                signatures::tag::MEDIA_WHITE_POINT,
+                white.clone(),
+            )?;
+
+            let raw = profile.read_raw_tag_thr(&mut context, signatures::tag::MEDIA_WHITE_POINT)?;
+            assert_eq!(raw.len(), 20);
+            assert_eq!(&raw[0..4], b"XYZ ");
+
+            let saved = profile.save_to_mem_thr(&mut context)?;
+            let mut reopened = Profile::open_from_mem_thr(&mut context, &saved)?;
+            assert_eq!(
+                reopened.read_raw_tag(signatures::tag::MEDIA_WHITE_POINT)?,
+                raw
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_raw_tag_round_trips() -> io::Result<()> {
+        let mut context = Context::new(None);
+        let private = Signature::new(b"priv");
+        let data = b"zzzz\0\0\0\0odd length".to_vec();
+        let mut profile = Profile::open_from_file_thr(
+            &mut context,
+            get_test_resource_path("sRGB_v4_ICC_preference.icc"),
+            AccessMode::Read,
+        )?;
+
+        profile.write_raw_tag(private, &data);
+        assert_eq!(profile.read_raw_tag(private)?, data);
+
+        let saved = profile.save_to_mem_thr(&mut context)?;
+        let mut reopened = Profile::open_from_mem_thr(&mut context, &saved)?;
+
+        assert_eq!(reopened.get_tag_count(), 10);
+        assert_eq!(reopened.read_raw_tag(private)?, data);
+        assert_eq!(
+            reopened.read_raw_tag(signatures::tag::A_TO_B0)?,
+            profile.read_raw_tag(signatures::tag::A_TO_B0)?
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_copy_raw_tag_between_profiles() -> io::Result<()> {
+        let mut context = Context::new(None);
+        let mut source = Profile::open_from_file_thr(
+            &mut context,
+            get_test_resource_path("sRGB_v4_ICC_preference.icc"),
+            AccessMode::Read,
+        )?;
+        let mut destination = Profile::new();
+
+        let desc = source.read_raw_tag(signatures::tag::PROFILE_DESCRIPTION)?;
+        destination.write_raw_tag(signatures::tag::PROFILE_DESCRIPTION, &desc);
+        destination.link_tag(
+            signatures::tag::COPYRIGHT,
+            signatures::tag::PROFILE_DESCRIPTION,
+        );
+
+        let saved = destination.save_to_mem_thr(&mut context)?;
+        let mut reopened = Profile::open_from_mem_thr(&mut context, &saved)?;
+
+        assert_eq!(
+            reopened.read_raw_tag(signatures::tag::PROFILE_DESCRIPTION)?,
+            desc
+        );
+        assert_eq!(reopened.read_raw_tag(signatures::tag::COPYRIGHT)?, desc);
+
+        Ok(())
+    }
+
+    fn read_xyz_type(
+        _context: &mut Context,
+        _handler: &TypeHandler,
+        io: &mut dyn IOHandler,
+        num_items: &mut usize,
+        _size_of_tag: usize,
+    ) -> io::Result<TagValue> {
+        *num_items = 1;
+        Ok(TagValue::XYZ(io.read_xyz()?))
+    }
+
+    static COUNTED_XYZ_READS: AtomicUsize = AtomicUsize::new(0);
+
+    fn read_counted_xyz_type(
+        context: &mut Context,
+        handler: &TypeHandler,
+        io: &mut dyn IOHandler,
+        num_items: &mut usize,
+        size_of_tag: usize,
+    ) -> io::Result<TagValue> {
+        COUNTED_XYZ_READS.fetch_add(1, Ordering::SeqCst);
+        read_xyz_type(context, handler, io, num_items, size_of_tag)
+    }
+
+    fn write_xyz_type(
+        _context: &mut Context,
+        _handler: &TypeHandler,
+        io: &mut dyn IOHandler,
+        value: &TagValue,
+        _num_items: usize,
+    ) -> io::Result<()> {
+        match value {
+            TagValue::XYZ(xyz) => io.write_xyz(*xyz),
+            _ => Err(Error::from(ErrorKind::InvalidInput)),
+        }
+    }
+
+    /// Creates a context knowing how to read `wtpt` and `cprt` as `XYZ ` tags.
+    fn xyz_context(element_count: usize) -> Context {
+        let mut context = Context::new(None);
+        let descriptor = TagDescriptor::new(element_count, vec![signatures::tag_type::XYZ], None);
+
+        for signature in [
+            signatures::tag::MEDIA_WHITE_POINT,
+            signatures::tag::COPYRIGHT,
+        ] {
+            context.tags_plugin.tags.push(TagListItem {
+                signature,
+                descriptor: descriptor.clone(),
+            });
+        }
+        context.tag_types_plugin.tag_types.push(TypeHandler::new(
+            signatures::tag_type::XYZ,
+            read_xyz_type,
+            write_xyz_type,
+        ));
+
+        context
+    }
+
+    #[test]
+    fn test_read_tag_dispatches_to_type_handler() -> io::Result<()> {
+        let mut context = xyz_context(1);
+        context.tag_types_plugin.tag_types[0].read = read_counted_xyz_type;
+        let mut profile = Profile::open_from_file_thr(
+            &mut context,
+            get_test_resource_path("sRGB_v4_ICC_preference.icc"),
+            AccessMode::Read,
+        )?;
+
+        let wtpt = match profile.read_tag_thr(&mut context, signatures::tag::MEDIA_WHITE_POINT)? {
+            TagValue::XYZ(xyz) => *xyz,
+            value => panic!("unexpected tag value {:?}", value),
+        };
+        profile.read_tag_thr(&mut context, signatures::tag::MEDIA_WHITE_POINT)?;
+
+        assert_eq!(
+            wtpt,
+            CIEXYZ {
+                X: s15f16_to_f64(0x0000F6D6),
+                Y: s15f16_to_f64(0x00010000),
+                Z: s15f16_to_f64(0x0000D32D),
+            }
+        );
+        // The second read comes from the cache
+        assert_eq!(COUNTED_XYZ_READS.load(Ordering::SeqCst), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_tag_reads_raw_tags() -> io::Result<()> {
+        let mut context = xyz_context(1);
+        let mut data = b"XYZ \0\0\0\0".to_vec();
+        for value in [0x0001_0000u32, 0x0002_0000, 0x0000_8000] {
+            data.extend_from_slice(&value.to_be_bytes());
+        }
+        let mut profile = Profile::new();
+        profile.write_raw_tag(signatures::tag::MEDIA_WHITE_POINT, &data);
+
+        match profile.read_tag_thr(&mut context, signatures::tag::MEDIA_WHITE_POINT)? {
+            TagValue::XYZ(xyz) => assert_eq!(
+                *xyz,
+                CIEXYZ {
+                    X: 1.0,
+                    Y: 2.0,
+                    Z: 0.5
+                }
+            ),
+            value => panic!("unexpected tag value {:?}", value),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_tag_rejects_unsupported_types() -> io::Result<()> {
+        let mut context = xyz_context(1);
+        let mut profile = Profile::open_from_file_thr(
+            &mut context,
+            get_test_resource_path("sRGB_v4_ICC_preference.icc"),
+            AccessMode::Read,
+        )?;
+
+        // cprt is stored as mluc, which isn't in the descriptor
+        let unsupported = profile
+            .read_tag_thr(&mut context, signatures::tag::COPYRIGHT)
+            .unwrap_err();
+        // Private tags have no descriptor at all
+        let wtpt = profile.read_raw_tag(signatures::tag::MEDIA_WHITE_POINT)?;
+        profile.write_raw_tag(Signature::new(b"priv"), &wtpt);
+        let unknown = profile
+            .read_tag_thr(&mut context, Signature::new(b"priv"))
+            .unwrap_err();
+        let missing = profile
+            .read_tag_thr(&mut context, signatures::tag::GRAY_TRC)
+            .unwrap_err();
+
+        assert_eq!(unsupported.kind(), ErrorKind::InvalidData);
+        assert_eq!(unknown.kind(), ErrorKind::InvalidData);
+        assert_eq!(missing.kind(), ErrorKind::NotFound);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_tag_rejects_too_few_elements() -> io::Result<()> {
+        let mut context = xyz_context(2);
+        let mut profile = Profile::open_from_file_thr(
+            &mut context,
+            get_test_resource_path("sRGB_v4_ICC_preference.icc"),
+            AccessMode::Read,
+        )?;
+
+        let err = profile
+            .read_tag_thr(&mut context, signatures::tag::MEDIA_WHITE_POINT)
+            .unwrap_err();
+
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_tag_follows_links() -> io::Result<()> {
+        let mut context = xyz_context(1);
+        let mut profile = Profile::open_from_file_thr(
+            &mut context,
+            get_test_resource_path("sRGB_v4_ICC_preference.icc"),
+            AccessMode::Read,
+        )?;
+        let wtpt = match profile.read_tag_thr(&mut context, signatures::tag::MEDIA_WHITE_POINT)? {
+            TagValue::XYZ(xyz) => *xyz,
+            value => panic!("unexpected tag value {:?}", value),
+        };
+
+        profile.link_tag(
+            signatures::tag::COPYRIGHT,
+            signatures::tag::MEDIA_WHITE_POINT,
+        );
+
+        match profile.read_tag_thr(&mut context, signatures::tag::COPYRIGHT)? {
+            TagValue::XYZ(xyz) => assert_eq!(*xyz, wtpt),
+            value => panic!("unexpected tag value {:?}", value),
+        }
+
+        Ok(())
+    }
+
+    /// Writes `XYZ ` in V4 profiles and `xyz2` in older ones.
+    fn decide_xyz_type(icc_version: f64, _data: &TagValue) -> Signature {
+        if icc_version >= 4.0 {
+            signatures::tag_type::XYZ
+        } else {
+            Signature::new(b"xyz2")
+        }
+    }
+
+    /// Creates a context knowing how to write `wtpt` as either `XYZ ` or `xyz2` depending on the version.
+    fn versioned_xyz_context() -> Context {
+        let mut context = xyz_context(1);
+        context.tags_plugin.tags[0].descriptor = TagDescriptor::new(
+            1,
+            vec![signatures::tag_type::XYZ, Signature::new(b"xyz2")],
+            Some(decide_xyz_type),
+        );
+        context.tag_types_plugin.tag_types.push(TypeHandler::new(
+            Signature::new(b"xyz2"),
+            read_xyz_type,
+            write_xyz_type,
+        ));
+
+        context
+    }
+
+    #[test]
+    fn test_write_tag_round_trips() -> io::Result<()> {
+        let mut context = xyz_context(1);
+        let white = CIEXYZ {
+            X: D50_X,
+            Y: D50_Y,
+            Z: D50_Z,
+        };
+        let mut profile = Profile::new();
+        profile.set_version(4.3);
+
+        profile.write_tag_thr(
+            &mut context,
+            signatures::tag::MEDIA_WHITE_POINT,
+            TagValue::XYZ(white),
+        )?;
+        let saved = profile.save_to_mem_thr(&mut context)?;
+        let mut reopened = Profile::open_from_mem_thr(&mut context, &saved)?;
+
+        let raw = reopened.read_raw_tag(signatures::tag::MEDIA_WHITE_POINT)?;
+        assert_eq!(raw.len(), 20);
+        assert_eq!(&raw[0..8], b"XYZ \0\0\0\0");
+        match reopened.read_tag_thr(&mut context, signatures::tag::MEDIA_WHITE_POINT)? {
+            TagValue::XYZ(xyz) => {
+                assert!((xyz.X - white.X).abs() < 1e-4);
+                assert!((xyz.Y - white.Y).abs() < 1e-4);
+                assert!((xyz.Z - white.Z).abs() < 1e-4);
+            }
+            value => panic!("unexpected tag value {:?}", value),
+        }
+
+        Ok(())
+    }
+
+    #[test_case(2.1, b"xyz2"; "v2")]
+    #[test_case(4.3, b"XYZ "; "v4")]
+    fn test_write_tag_decides_type_by_version(version: f64, expected: &[u8; 4]) -> io::Result<()> {
+        let mut context = versioned_xyz_context();
+        let mut profile = Profile::new();
+        profile.set_version(version);
+
+        profile.write_tag_thr(
+            &mut context,
+            signatures::tag::MEDIA_WHITE_POINT,
+            TagValue::XYZ(CIEXYZ::default()),
+        )?;
+        let saved = profile.save_to_mem_thr(&mut context)?;
+        let mut reopened = Profile::open_from_mem_thr(&mut context, &saved)?;
+
+        assert_eq!(
+            &reopened.read_raw_tag(signatures::tag::MEDIA_WHITE_POINT)?[0..4],
+            expected
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_tag_rejects_descriptors_without_types() {
+        let mut context = xyz_context(1);
+        context.tags_plugin.tags[0].descriptor = TagDescriptor::new(1, Vec::new(), None);
+        let mut profile = Profile::new();
+
+        let err = profile
+            .write_tag_thr(
+                &mut context,
+                signatures::tag::MEDIA_WHITE_POINT,
+                TagValue::XYZ(CIEXYZ::default()),
+            )
+            .unwrap_err();
+
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn test_write_tag_rejects_unknown_tags() {
+        let mut context = xyz_context(1);
+        let mut profile = Profile::new();
+
+        let err = profile
+            .write_tag_thr(
+                &mut context,
+                Signature::new(b"priv"),
+                TagValue::XYZ(CIEXYZ::default()),
+            )
+            .unwrap_err();
+
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+        assert_eq!(profile.get_tag_count(), 0);
+    }
+
+    #[test]
+    fn test_write_tag_replaces_existing_tag() -> io::Result<()> {
+        let mut context = xyz_context(1);
+        let mut profile = Profile::open_from_file_thr(
+            &mut context,
+            get_test_resource_path("sRGB_v4_ICC_preference.icc"),
+            AccessMode::Read,
+        )?;
+        let black = TagValue::XYZ(CIEXYZ::default());
+
+        profile.write_tag_thr(&mut context, signatures::tag::MEDIA_WHITE_POINT, black)?;
+        let saved = profile.save_to_mem_thr(&mut context)?;
+        let mut reopened = Profile::open_from_mem_thr(&mut context, &saved)?;
+
+        assert_eq!(reopened.get_tag_count(), 9);
+        match reopened.read_tag_thr(&mut context, signatures::tag::MEDIA_WHITE_POINT)? {
+            TagValue::XYZ(xyz) => assert_eq!(*xyz, CIEXYZ::default()),
+            value => panic!("unexpected tag value {:?}", value),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_mem_rejects_non_icc_data() {
+        let mut context = Context::new(None);
+        let data = [0u8; 256];
+
+        assert!(Profile::open_from_mem_thr(&mut context, &data).is_err());
+    }
+
+    #[test]
+    fn test_file_loads_with_proper_data_and_endianness() -> io::Result<()> {
+        let mut context = Context::new(None);
+        let expected_names = [
+            Signature::new(b"desc"),
+            Signature::new(b"A2B0"),
+            Signature::new(b"A2B1"),
+            Signature::new(b"B2A0"),
+            Signature::new(b"B2A1"),
+            Signature::new(b"rig0"),
+            Signature::new(b"wtpt"),
+            Signature::new(b"cprt"),
+            Signature::new(b"chad"),
+        ];
+        let expected_sizes = [118, 29712, 436, 29748, 508, 12, 20, 118, 44];
+        let expected_offsets = [240, 360, 30072, 30508, 60256, 60764, 60776, 60796, 60916];
+        let expected_tags = (0..9)
+            .map(|i| ProfileTag {
+                sig: expected_names[i],
+                linked: None,
+                offset: expected_offsets[i],
+                size: expected_sizes[i],
+                raw: None,
+                in_memory: false,
+                r#type: None,
+                value: None,
+            })
+            .collect();
+        let expected = Box::new(Profile {
+            io: None,
+            created: NaiveDate::from_ymd(2007, 07, 25).and_hms(0, 5, 37),
             version: 0x04200000,
             device_class: Signature::new(b"spac"),
             color_space: Signature::new(b"RGB "),
@@ -309,18 +2293,18 @@ mod test {
             model: 0,
             attributes: 0,
             creator: 0,
+            illuminant: CIEXYZ {
+                X: s15f16_to_f64(0x0000F6D6),
+                Y: s15f16_to_f64(0x00010000),
+                Z: s15f16_to_f64(0x0000D32D),
+            },
             profile_id: ProfileID {
                 id8: [
                     0x34, 0x56, 0x2a, 0xbf, 0x99, 0x4c, 0xcd, 0x06, 0x6d, 0x2c, 0x57, 0x21, 0xd0,
                     0xd6, 0x8c, 0x5d,
                 ],
             },
-            tag_count: 9,
-            tag_names: expected_names,
-            tag_linked: [None; MAX_TABLE_TAG],
-            tag_sizes: expected_sizes,
-            tag_offsets: expected_offsets,
-            tag_save_as_raw: [false; MAX_TABLE_TAG],
+            tags: expected_tags,
             is_write: false,
         });
         let actual = Profile::open_from_file_thr(
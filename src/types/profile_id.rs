@@ -7,6 +7,17 @@ pub union ProfileID {
     pub id32: [u32; 4],
 }
 
+impl ProfileID {
+    /// Creates a `ProfileID` from its 16 raw bytes.
+    pub const fn from_bytes(bytes: [u8; 16]) -> Self {
+        Self { id8: bytes }
+    }
+    /// Returns the 16 raw bytes of this `ProfileID`.
+    pub fn to_bytes(&self) -> [u8; 16] {
+        unsafe { self.id8 }
+    }
+}
+
 impl std::fmt::Debug for ProfileID {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
         let mut value = String::with_capacity(64);
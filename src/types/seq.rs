@@ -2,6 +2,8 @@ use std::sync::Arc;
 
 use super::{Mlu, ProfileID, Signature};
 
+/// The description of one of the profiles a [`Sequence`] is made of.
+#[derive(Clone, Debug)]
 pub struct SequenceDescriptor {
     device_mfg: Signature,
     device_model: Signature,
@@ -13,7 +15,152 @@ pub struct SequenceDescriptor {
     description: Arc<Mlu>,
 }
 
+/// The profiles a device link or abstract profile was built from, in the order they were applied.
+///
+/// # Examples
+/// ```
+/// use lcms2::types::{Mlu, Sequence, SequenceDescriptor};
+///
+/// let mut descriptor = SequenceDescriptor::new();
+/// descriptor.set_model(Mlu::from_text("Press"));
+///
+/// let mut seq = Sequence::new();
+/// seq.push(descriptor);
+///
+/// assert_eq!(seq.get_count(), 1);
+/// assert_eq!(seq.get(0).unwrap().get_model().get(Mlu::NO_LANGUAGE, Mlu::NO_COUNTRY), Some("Press"));
+/// ```
+#[derive(Clone, Debug, Default)]
 pub struct Sequence {
     seq: Vec<SequenceDescriptor>,
 }
 // &mut Context must be passed in for all functions involving Sequence
+
+impl Default for SequenceDescriptor {
+    fn default() -> Self {
+        Self {
+            device_mfg: Signature::default(),
+            device_model: Signature::default(),
+            attributes: 0,
+            technology: Signature::default(),
+            profile_id: ProfileID::from_bytes([0; 16]),
+            manufacturer: Default::default(),
+            model: Default::default(),
+            description: Default::default(),
+        }
+    }
+}
+
+impl SequenceDescriptor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get_device_mfg(&self) -> Signature {
+        self.device_mfg
+    }
+
+    pub fn set_device_mfg(&mut self, device_mfg: Signature) {
+        self.device_mfg = device_mfg;
+    }
+
+    pub fn get_device_model(&self) -> Signature {
+        self.device_model
+    }
+
+    pub fn set_device_model(&mut self, device_model: Signature) {
+        self.device_model = device_model;
+    }
+
+    /// Returns the device attributes of the profile, as in its header.
+    pub fn get_attributes(&self) -> u64 {
+        self.attributes
+    }
+
+    pub fn set_attributes(&mut self, attributes: u64) {
+        self.attributes = attributes;
+    }
+
+    pub fn get_technology(&self) -> Signature {
+        self.technology
+    }
+
+    pub fn set_technology(&mut self, technology: Signature) {
+        self.technology = technology;
+    }
+
+    pub fn get_profile_id(&self) -> ProfileID {
+        self.profile_id
+    }
+
+    pub fn set_profile_id(&mut self, profile_id: ProfileID) {
+        self.profile_id = profile_id;
+    }
+
+    pub fn get_manufacturer(&self) -> &Mlu {
+        &self.manufacturer
+    }
+
+    pub fn set_manufacturer(&mut self, manufacturer: Mlu) {
+        self.manufacturer = Arc::new(manufacturer);
+    }
+
+    pub fn get_model(&self) -> &Mlu {
+        &self.model
+    }
+
+    pub fn set_model(&mut self, model: Mlu) {
+        self.model = Arc::new(model);
+    }
+
+    /// Returns the description of the profile, which is only stored in `psid` tags.
+    pub fn get_description(&self) -> &Mlu {
+        &self.description
+    }
+
+    pub fn set_description(&mut self, description: Mlu) {
+        self.description = Arc::new(description);
+    }
+}
+
+impl Sequence {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, descriptor: SequenceDescriptor) {
+        self.seq.push(descriptor);
+    }
+
+    pub fn get_count(&self) -> usize {
+        self.seq.len()
+    }
+
+    pub fn get(&self, index: usize) -> Option<&SequenceDescriptor> {
+        self.seq.get(index)
+    }
+
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut SequenceDescriptor> {
+        self.seq.get_mut(index)
+    }
+
+    pub fn get_descriptors(&self) -> &[SequenceDescriptor] {
+        &self.seq
+    }
+
+    /// Joins the descriptions of a `pseq` tag with the IDs and descriptions of a `psid` tag of the same profile.
+    ///
+    /// When the two sequences don't have the same length they can't be matched, and `self` is returned as is.
+    pub fn merge_ids(mut self, ids: &Sequence) -> Sequence {
+        if self.seq.len() != ids.seq.len() {
+            return self;
+        }
+
+        for (descriptor, id) in self.seq.iter_mut().zip(ids.seq.iter()) {
+            descriptor.profile_id = id.profile_id;
+            descriptor.description = id.description.clone();
+        }
+
+        self
+    }
+}
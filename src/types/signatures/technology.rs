@@ -26,3 +26,45 @@ pub const MOTION_PICTURE_FILM_SCANNER: Signature = Signature::new(b"mpfs");
 pub const MOTION_PICTURE_FILM_RECORDER: Signature = Signature::new(b"mpfr");
 pub const DIGITAL_MOTION_PICTURE_CAMERA: Signature = Signature::new(b"dmpc");
 pub const DIGITAL_CINEMA_PROJECTOR: Signature = Signature::new(b"dcpj");
+
+/// Returns the human-readable name of the technology `sig`, as given by ICC.1:2022 table 29.
+///
+/// ```
+/// use lcms2::types::signatures::technology;
+///
+/// assert_eq!(technology::get_description(technology::INKJET_PRINTER), Some("Ink jet printer"));
+/// assert_eq!(technology::get_description(b"????".into()), None);
+/// ```
+pub fn get_description(sig: Signature) -> Option<&'static str> {
+    let description = match sig {
+        DIGITAL_CAMERA => "Digital camera",
+        FILM_SCANNER => "Film scanner",
+        REFLECTIVE_SCANNER => "Reflective scanner",
+        INKJET_PRINTER => "Ink jet printer",
+        THERMAL_WAX_PRINTER => "Thermal wax printer",
+        ELECTROPHOTOGRAPHIC_PRINTER => "Electrophotographic printer",
+        ELECTROSTATIC_PRINTER => "Electrostatic printer",
+        DYE_SUBLIMATION_PRINTER => "Dye sublimation printer",
+        PHOTOGRAPHIC_PAPER_PRINTER => "Photographic paper printer",
+        FILM_WRITER => "Film writer",
+        VIDEO_MONITOR => "Video monitor",
+        VIDEO_CAMERA => "Video camera",
+        PROJECTION_TELEVISION => "Projection television",
+        CRT_DISPLAY => "Cathode ray tube display",
+        PM_DISPLAY => "Passive matrix display",
+        AM_DISPLAY => "Active matrix display",
+        PHOTO_CD => "Photo CD",
+        PHOTO_IMAGE_SETTER => "Photographic image setter",
+        GRAVURE => "Gravure",
+        OFFSET_LITHOGRAPHY => "Offset lithography",
+        SILKSCREEN => "Silkscreen",
+        FLEXOGRAPHY => "Flexography",
+        MOTION_PICTURE_FILM_SCANNER => "Motion picture film scanner",
+        MOTION_PICTURE_FILM_RECORDER => "Motion picture film recorder",
+        DIGITAL_MOTION_PICTURE_CAMERA => "Digital motion picture camera",
+        DIGITAL_CINEMA_PROJECTOR => "Digital cinema projector",
+        _ => return None,
+    };
+
+    Some(description)
+}
@@ -0,0 +1,614 @@
+//! The built-in tag descriptors and tag type handlers, as defined by ICC.1:2022 clauses 9 and 10.
+
+mod colorant;
+mod curve;
+mod dict;
+mod lut;
+mod measurement;
+mod mpe;
+mod named_color;
+mod numeric;
+mod sequence;
+mod text;
+mod vcgt;
+
+pub(crate) use lut::encode_lab_v2;
+
+use std::io::{Error, ErrorKind, Result, SeekFrom};
+
+use once_cell::sync::Lazy;
+
+use crate::{
+    io::IOHandler,
+    plugins::{TagDescriptor, TagList, TagListItem, TagTypeDecoder, TagTypeList, TypeHandler},
+};
+
+use super::{
+    signatures::{stage, tag, tag_type},
+    Signature, TagValue,
+};
+
+/// Corbis wrote XYZ tags using this broken type signature.
+const CORBIS_BROKEN_XYZ_TYPE: Signature = Signature::new(&[0x17, 0xA5, 0x05, 0xB8]);
+/// Monaco wrote curve tags using this broken type signature.
+const MONACO_BROKEN_CURVE_TYPE: Signature = Signature::new(&[0x94, 0x78, 0xEE, 0x00]);
+
+/// The tag types known without any plugin, searched after the tag types registered by plugins.
+pub(crate) static SUPPORTED_TAG_TYPES: Lazy<TagTypeList> = Lazy::new(|| {
+    vec![
+        TypeHandler::new(tag_type::CURVE, curve::read_curve, curve::write_curve),
+        TypeHandler::new(
+            tag_type::PARAMETRIC_CURVE,
+            curve::read_parametric_curve,
+            curve::write_parametric_curve,
+        ),
+        TypeHandler::new(
+            MONACO_BROKEN_CURVE_TYPE,
+            curve::read_curve,
+            curve::write_curve,
+        ),
+        TypeHandler::new(tag_type::XYZ, numeric::read_xyz, numeric::write_xyz),
+        TypeHandler::new(
+            CORBIS_BROKEN_XYZ_TYPE,
+            numeric::read_xyz,
+            numeric::write_xyz,
+        ),
+        TypeHandler::new(
+            tag_type::S15_FIXED16_ARRAY,
+            numeric::read_s15_fixed16_array,
+            numeric::write_s15_fixed16_array,
+        ),
+        TypeHandler::new(
+            tag_type::U16_FIXED16_ARRAY,
+            numeric::read_u16_fixed16_array,
+            numeric::write_u16_fixed16_array,
+        ),
+        TypeHandler::new(
+            tag_type::UINT8_ARRAY,
+            numeric::read_u8_array,
+            numeric::write_u8_array,
+        ),
+        TypeHandler::new(
+            tag_type::UINT16_ARRAY,
+            numeric::read_u16_array,
+            numeric::write_u16_array,
+        ),
+        TypeHandler::new(
+            tag_type::UINT32_ARRAY,
+            numeric::read_u32_array,
+            numeric::write_u32_array,
+        ),
+        TypeHandler::new(
+            tag_type::UINT64_ARRAY,
+            numeric::read_u64_array,
+            numeric::write_u64_array,
+        ),
+        TypeHandler::new(
+            tag_type::MULTI_LOCALIZED_UNICODE,
+            text::read_mlu,
+            text::write_mlu,
+        ),
+        TypeHandler::new(
+            tag_type::TEXT_DESCRIPTION,
+            text::read_text_description,
+            text::write_text_description,
+        ),
+        TypeHandler::new(tag_type::TEXT, text::read_text, text::write_text),
+        TypeHandler::new(tag_type::LUT8, lut::read_lut8, lut::write_lut8),
+        TypeHandler::new(tag_type::LUT16, lut::read_lut16, lut::write_lut16),
+        TypeHandler::new(
+            tag_type::LUTA_TO_B,
+            lut::read_lut_a_to_b,
+            lut::write_lut_a_to_b,
+        ),
+        TypeHandler::new(
+            tag_type::LUTB_TO_A,
+            lut::read_lut_b_to_a,
+            lut::write_lut_b_to_a,
+        ),
+        TypeHandler::new(
+            tag_type::MULTI_PROCESS_ELEMENT,
+            mpe::read_mpe,
+            mpe::write_mpe,
+        ),
+        TypeHandler::new(
+            tag_type::NAMED_COLOR2,
+            named_color::read_named_color,
+            named_color::write_named_color,
+        ),
+        TypeHandler::new(
+            tag_type::PROFILE_SEQUENCE_DESC,
+            sequence::read_profile_sequence_desc,
+            sequence::write_profile_sequence_desc,
+        ),
+        TypeHandler::new(
+            tag_type::PROFILE_SEQUENCE_ID,
+            sequence::read_profile_sequence_id,
+            sequence::write_profile_sequence_id,
+        ),
+        TypeHandler::new(
+            tag_type::CHROMATICITY,
+            colorant::read_chromaticity,
+            colorant::write_chromaticity,
+        ),
+        TypeHandler::new(
+            tag_type::COLORANT_ORDER,
+            colorant::read_colorant_order,
+            colorant::write_colorant_order,
+        ),
+        TypeHandler::new(
+            tag_type::COLORANT_TABLE,
+            named_color::read_colorant_table,
+            named_color::write_colorant_table,
+        ),
+        TypeHandler::new(
+            tag_type::MEASUREMENT,
+            measurement::read_measurement,
+            measurement::write_measurement,
+        ),
+        TypeHandler::new(
+            tag_type::VIEWING_CONDITIONS,
+            measurement::read_viewing_conditions,
+            measurement::write_viewing_conditions,
+        ),
+        TypeHandler::new(
+            tag_type::DATE_TIME,
+            numeric::read_date_time,
+            numeric::write_date_time,
+        ),
+        TypeHandler::new(
+            tag_type::SIGNATURE,
+            numeric::read_signature,
+            numeric::write_signature,
+        ),
+        TypeHandler::new(tag_type::VCGT, vcgt::read_vcgt, vcgt::write_vcgt),
+        TypeHandler::new(tag_type::DICT, dict::read_dict, dict::write_dict),
+    ]
+});
+
+/// The multi process element types known without any plugin, searched after the element types registered by plugins.
+pub(crate) static SUPPORTED_MPE_TYPES: Lazy<TagTypeList> = Lazy::new(|| {
+    vec![
+        // Ignore those elements for now
+        TypeHandler::new(
+            stage::B_ACS_ELEM_TYPE,
+            mpe::read_mpe_acs,
+            mpe::write_mpe_acs,
+        ),
+        TypeHandler::new(
+            stage::E_ACS_ELEM_TYPE,
+            mpe::read_mpe_acs,
+            mpe::write_mpe_acs,
+        ),
+        // Real ones
+        TypeHandler::new(
+            stage::CURVE_SET_ELEM_TYPE,
+            mpe::read_mpe_curve,
+            mpe::write_mpe_curve,
+        ),
+        TypeHandler::new(
+            stage::MATRIX_ELEM_TYPE,
+            mpe::read_mpe_matrix,
+            mpe::write_mpe_matrix,
+        ),
+        TypeHandler::new(
+            stage::C_LUT_ELEM_TYPE,
+            mpe::read_mpe_clut,
+            mpe::write_mpe_clut,
+        ),
+    ]
+});
+
+/// The tags known without any plugin, searched after the tags registered by plugins.
+pub(crate) static SUPPORTED_TAGS: Lazy<TagList> = Lazy::new(|| {
+    let a2b = || {
+        descriptor(
+            1,
+            &[tag_type::LUT16, tag_type::LUTA_TO_B, tag_type::LUT8],
+            Some(decide_lut_type_a2b),
+        )
+    };
+    let b2a = || {
+        descriptor(
+            1,
+            &[tag_type::LUT16, tag_type::LUTB_TO_A, tag_type::LUT8],
+            Some(decide_lut_type_b2a),
+        )
+    };
+    let colorant = || {
+        descriptor(
+            1,
+            &[tag_type::XYZ, CORBIS_BROKEN_XYZ_TYPE],
+            Some(decide_xyz_type),
+        )
+    };
+    let trc = || {
+        descriptor(
+            1,
+            &[
+                tag_type::CURVE,
+                tag_type::PARAMETRIC_CURVE,
+                MONACO_BROKEN_CURVE_TYPE,
+            ],
+            Some(decide_curve_type),
+        )
+    };
+    let text_desc = || {
+        descriptor(
+            1,
+            &[
+                tag_type::TEXT_DESCRIPTION,
+                tag_type::MULTI_LOCALIZED_UNICODE,
+                tag_type::TEXT,
+            ],
+            Some(decide_text_desc_type),
+        )
+    };
+    let single = |r#type: Signature| descriptor(1, &[r#type], None);
+
+    vec![
+        item(tag::A_TO_B0, a2b()),
+        item(tag::A_TO_B1, a2b()),
+        item(tag::A_TO_B2, a2b()),
+        item(tag::B_TO_A0, b2a()),
+        item(tag::B_TO_A1, b2a()),
+        item(tag::B_TO_A2, b2a()),
+        // Allow corbis and its broken XYZ type
+        item(tag::RED_COLORANT, colorant()),
+        item(tag::GREEN_COLORANT, colorant()),
+        item(tag::BLUE_COLORANT, colorant()),
+        item(tag::RED_TRC, trc()),
+        item(tag::GREEN_TRC, trc()),
+        item(tag::BLUE_TRC, trc()),
+        item(tag::CALIBRATION_DATE_TIME, single(tag_type::DATE_TIME)),
+        item(tag::CHAR_TARGET, single(tag_type::TEXT)),
+        item(
+            tag::CHROMATIC_ADAPTATION,
+            descriptor(9, &[tag_type::S15_FIXED16_ARRAY], None),
+        ),
+        item(tag::CHROMATICITY, single(tag_type::CHROMATICITY)),
+        item(tag::COLORANT_ORDER, single(tag_type::COLORANT_ORDER)),
+        item(tag::COLORANT_TABLE, single(tag_type::COLORANT_TABLE)),
+        item(tag::COLORANT_TABLE_OUT, single(tag_type::COLORANT_TABLE)),
+        item(
+            tag::COPYRIGHT,
+            descriptor(
+                1,
+                &[
+                    tag_type::TEXT,
+                    tag_type::MULTI_LOCALIZED_UNICODE,
+                    tag_type::TEXT_DESCRIPTION,
+                ],
+                Some(decide_text_type),
+            ),
+        ),
+        item(tag::DATE_TIME, single(tag_type::DATE_TIME)),
+        item(tag::DEVICE_MFG_DESC, text_desc()),
+        item(tag::DEVICE_MODEL_DESC, text_desc()),
+        item(tag::GAMUT, b2a()),
+        item(
+            tag::GRAY_TRC,
+            descriptor(
+                1,
+                &[tag_type::CURVE, tag_type::PARAMETRIC_CURVE],
+                Some(decide_curve_type),
+            ),
+        ),
+        item(tag::LUMINANCE, single(tag_type::XYZ)),
+        item(
+            tag::MEDIA_BLACK_POINT,
+            descriptor(1, &[tag_type::XYZ, CORBIS_BROKEN_XYZ_TYPE], None),
+        ),
+        item(
+            tag::MEDIA_WHITE_POINT,
+            descriptor(1, &[tag_type::XYZ, CORBIS_BROKEN_XYZ_TYPE], None),
+        ),
+        item(tag::NAMED_COLOR2, single(tag_type::NAMED_COLOR2)),
+        item(tag::PREVIEW0, b2a()),
+        item(tag::PREVIEW1, b2a()),
+        item(tag::PREVIEW2, b2a()),
+        item(tag::PROFILE_DESCRIPTION, text_desc()),
+        item(
+            tag::PROFILE_SEQUENCE_DESC,
+            single(tag_type::PROFILE_SEQUENCE_DESC),
+        ),
+        item(tag::TECHNOLOGY, single(tag_type::SIGNATURE)),
+        item(
+            tag::COLORIMETRIC_INTENT_IMAGE_STATE,
+            single(tag_type::SIGNATURE),
+        ),
+        item(
+            tag::PERCEPTUAL_RENDERING_INTENT_GAMUT,
+            single(tag_type::SIGNATURE),
+        ),
+        item(
+            tag::SATURATION_RENDERING_INTENT_GAMUT,
+            single(tag_type::SIGNATURE),
+        ),
+        item(tag::MEASUREMENT, single(tag_type::MEASUREMENT)),
+        item(tag::PS2_CRD0, single(tag_type::DATA)),
+        item(tag::PS2_CRD1, single(tag_type::DATA)),
+        item(tag::PS2_CRD2, single(tag_type::DATA)),
+        item(tag::PS2_CRD3, single(tag_type::DATA)),
+        item(tag::PS2_CSA, single(tag_type::DATA)),
+        item(tag::PS2_RENDERING_INTENT, single(tag_type::DATA)),
+        item(tag::VIEWING_COND_DESC, text_desc()),
+        item(tag::UCR_BG, single(tag_type::UCR_BG)),
+        item(tag::CRD_INFO, single(tag_type::CRD_INFO)),
+        item(tag::D_TO_B0, single(tag_type::MULTI_PROCESS_ELEMENT)),
+        item(tag::D_TO_B1, single(tag_type::MULTI_PROCESS_ELEMENT)),
+        item(tag::D_TO_B2, single(tag_type::MULTI_PROCESS_ELEMENT)),
+        item(tag::D_TO_B3, single(tag_type::MULTI_PROCESS_ELEMENT)),
+        item(tag::B_TO_D0, single(tag_type::MULTI_PROCESS_ELEMENT)),
+        item(tag::B_TO_D1, single(tag_type::MULTI_PROCESS_ELEMENT)),
+        item(tag::B_TO_D2, single(tag_type::MULTI_PROCESS_ELEMENT)),
+        item(tag::B_TO_D3, single(tag_type::MULTI_PROCESS_ELEMENT)),
+        item(tag::SCREENING_DESC, single(tag_type::TEXT_DESCRIPTION)),
+        item(
+            tag::VIEWING_CONDITIONS,
+            single(tag_type::VIEWING_CONDITIONS),
+        ),
+        item(tag::SCREENING, single(tag_type::SCREENING)),
+        item(tag::VCGT, single(tag_type::VCGT)),
+        item(tag::META, single(tag_type::DICT)),
+        item(
+            tag::PROFILE_SEQUENCE_ID,
+            single(tag_type::PROFILE_SEQUENCE_ID),
+        ),
+        item(
+            tag::PROFILE_DESCRIPTION_ML,
+            single(tag_type::MULTI_LOCALIZED_UNICODE),
+        ),
+        item(
+            tag::ARGYLL_ARTS,
+            descriptor(9, &[tag_type::S15_FIXED16_ARRAY], None),
+        ),
+    ]
+});
+
+fn item(signature: Signature, descriptor: TagDescriptor) -> TagListItem {
+    TagListItem {
+        signature,
+        descriptor,
+    }
+}
+
+fn descriptor(
+    element_count: usize,
+    supported_types: &[Signature],
+    decide_type: Option<TagTypeDecoder>,
+) -> TagDescriptor {
+    TagDescriptor::new(element_count, supported_types.to_vec(), decide_type)
+}
+
+fn decide_xyz_type(_icc_version: f64, _data: &TagValue) -> Signature {
+    tag_type::XYZ
+}
+
+/// Single segment parametric curves of the types defined by the ICC are written as `para` in V4 profiles.
+fn decide_curve_type(icc_version: f64, data: &TagValue) -> Signature {
+    let curve = match data {
+        TagValue::Curve(curve) => curve,
+        _ => return tag_type::CURVE,
+    };
+
+    if icc_version < 4.0 || curve.get_segments().len() != 1 {
+        return tag_type::CURVE;
+    }
+
+    match curve.get_parametric_type() {
+        1..=5 => tag_type::PARAMETRIC_CURVE,
+        _ => tag_type::CURVE,
+    }
+}
+
+fn decide_text_type(icc_version: f64, _data: &TagValue) -> Signature {
+    if icc_version >= 4.0 {
+        tag_type::MULTI_LOCALIZED_UNICODE
+    } else {
+        tag_type::TEXT
+    }
+}
+
+fn decide_text_desc_type(icc_version: f64, _data: &TagValue) -> Signature {
+    if icc_version >= 4.0 {
+        tag_type::MULTI_LOCALIZED_UNICODE
+    } else {
+        tag_type::TEXT_DESCRIPTION
+    }
+}
+
+fn decide_lut_type_a2b(icc_version: f64, data: &TagValue) -> Signature {
+    decide_lut_type(icc_version, data, tag_type::LUTA_TO_B)
+}
+
+fn decide_lut_type_b2a(icc_version: f64, data: &TagValue) -> Signature {
+    decide_lut_type(icc_version, data, tag_type::LUTB_TO_A)
+}
+
+/// Pipelines flagged to be saved as 8 bits always use `mft1`. Otherwise V2 profiles use `mft2`, and V4 profiles use
+/// `v4_type`.
+fn decide_lut_type(icc_version: f64, data: &TagValue, v4_type: Signature) -> Signature {
+    if let TagValue::Pipeline(lut) = data {
+        if lut.save_as_8_bits {
+            return tag_type::LUT8;
+        }
+    }
+
+    if icc_version < 4.0 {
+        tag_type::LUT16
+    } else {
+        v4_type
+    }
+}
+
+/// Reads a table of `count` offset and size pairs, and calls `read_element` with the offset of each element, relative
+/// to `base_offset`. Elements are read in order, and their sizes are only checked against the tag.
+pub(super) fn read_position_table(
+    io: &mut dyn IOHandler,
+    count: usize,
+    base_offset: usize,
+    size_of_tag: usize,
+    mut read_element: impl FnMut(&mut dyn IOHandler, usize) -> Result<()>,
+) -> Result<()> {
+    let mut offsets = Vec::with_capacity(count.min(size_of_tag / 8));
+    for _ in 0..count {
+        let offset = io.read_u32()? as usize;
+        let size = io.read_u32()? as usize;
+
+        // Leave 8 bytes for the type base in front of the data
+        if offset
+            .checked_add(size)
+            .is_none_or(|end| end > size_of_tag + 8)
+        {
+            return Err(Error::from(ErrorKind::InvalidData));
+        }
+        offsets.push(offset);
+    }
+
+    for offset in offsets {
+        io.seek(SeekFrom::Start((base_offset + offset) as u64))?;
+        read_element(io, offset)?;
+    }
+
+    Ok(())
+}
+
+/// Writes a table of `count` offset and size pairs, then each element with `write_element`, and finally fills the
+/// table in with offsets relative to `base_offset`.
+pub(super) fn write_position_table(
+    io: &mut dyn IOHandler,
+    count: usize,
+    base_offset: usize,
+    mut write_element: impl FnMut(&mut dyn IOHandler, usize) -> Result<()>,
+) -> Result<()> {
+    // Keep starting position of the directory, to be filled latter
+    let directory_pos = io.tell()?;
+    for _ in 0..count {
+        io.write_u32(0)?; // Offset
+        io.write_u32(0)?; // Size
+    }
+
+    let mut positions = Vec::with_capacity(count);
+    for i in 0..count {
+        let before = io.tell()?;
+        write_element(io, i)?;
+        positions.push((before - base_offset, io.tell()? - before));
+    }
+
+    let current_pos = io.tell()?;
+    io.seek(SeekFrom::Start(directory_pos as u64))?;
+    for (offset, size) in positions {
+        io.write_u32(offset as u32)?;
+        io.write_u32(size as u32)?;
+    }
+    io.seek(SeekFrom::Start(current_pos as u64))
+}
+
+/// Writes `value` with `handler`, preceded by its type base, and reads it back. Returns the value read, the number of
+/// items the reader reported, and the data written, type base included.
+#[cfg(test)]
+fn round_trip(handler: &TypeHandler, value: &TagValue) -> Result<(TagValue, usize, Vec<u8>)> {
+    use crate::{
+        io::{FileMem, FileMemReadOnly},
+        state::Context,
+    };
+
+    let mut context = Context::new(None);
+
+    let mut mem = FileMem::new(Vec::new());
+    mem.write_type_base(handler.signature)?;
+    (handler.write)(&mut context, handler, &mut mem, value, 1)?;
+    let data = mem.cursor.into_inner();
+
+    let mut io = FileMemReadOnly::new(data.as_slice());
+    io.read_type_base()?;
+    let mut count = 0;
+    let value = (handler.read)(&mut context, handler, &mut io, &mut count, data.len() - 8)?;
+
+    Ok((value, count, data))
+}
+
+#[cfg(test)]
+mod test {
+    use test_case::test_case;
+
+    use crate::{
+        plugins::{get_tag_descriptor, TagDescriptor, TagListItem},
+        state::Context,
+        types::{
+            signatures::{tag, tag_type},
+            Signature, TagValue, CIEXYZ,
+        },
+    };
+
+    use super::SUPPORTED_TAGS;
+
+    #[test]
+    fn test_supported_tags_are_unique() {
+        for (i, item) in SUPPORTED_TAGS.iter().enumerate() {
+            assert!(
+                SUPPORTED_TAGS[i + 1..]
+                    .iter()
+                    .all(|other| other.signature != item.signature),
+                "{:?} is described more than once",
+                item.signature
+            );
+        }
+    }
+
+    #[test]
+    fn test_built_in_descriptors_are_found_without_plugins() {
+        let context = Context::new(None);
+
+        let chad = get_tag_descriptor(&context, tag::CHROMATIC_ADAPTATION).unwrap();
+        let a2b0 = get_tag_descriptor(&context, tag::A_TO_B0).unwrap();
+
+        assert_eq!(chad.get_element_count(), 9);
+        assert_eq!(chad.get_supported_types(), &[tag_type::S15_FIXED16_ARRAY]);
+        assert!(a2b0.is_type_supported(tag_type::LUTA_TO_B));
+        assert!(!a2b0.is_type_supported(tag_type::LUTB_TO_A));
+        assert!(get_tag_descriptor(&context, Signature::new(b"priv")).is_none());
+    }
+
+    #[test]
+    fn test_plugin_descriptors_extend_built_in_ones() {
+        let mut context = Context::new(None);
+        for signature in [tag::MEDIA_WHITE_POINT, Signature::new(b"priv")] {
+            context.tags_plugin.tags.push(TagListItem {
+                signature,
+                descriptor: TagDescriptor::new(1, vec![tag_type::TEXT], None),
+            });
+        }
+
+        let wtpt = get_tag_descriptor(&context, tag::MEDIA_WHITE_POINT).unwrap();
+        let private = get_tag_descriptor(&context, Signature::new(b"priv")).unwrap();
+        let bkpt = get_tag_descriptor(&context, tag::MEDIA_BLACK_POINT).unwrap();
+
+        assert_eq!(wtpt.get_supported_types(), &[tag_type::TEXT]);
+        assert_eq!(private.get_supported_types(), &[tag_type::TEXT]);
+        assert!(bkpt.is_type_supported(tag_type::XYZ));
+    }
+
+    #[test_case(tag::PROFILE_DESCRIPTION, 2.1, tag_type::TEXT_DESCRIPTION; "desc_v2")]
+    #[test_case(tag::PROFILE_DESCRIPTION, 4.3, tag_type::MULTI_LOCALIZED_UNICODE; "desc_v4")]
+    #[test_case(tag::COPYRIGHT, 2.1, tag_type::TEXT; "cprt_v2")]
+    #[test_case(tag::COPYRIGHT, 4.3, tag_type::MULTI_LOCALIZED_UNICODE; "cprt_v4")]
+    #[test_case(tag::RED_COLORANT, 2.1, tag_type::XYZ; "rxyz_v2")]
+    #[test_case(tag::MEDIA_WHITE_POINT, 4.3, tag_type::XYZ; "wtpt_v4")]
+    fn test_built_in_descriptors_decide_type_by_version(
+        sig: Signature,
+        version: f64,
+        expected: Signature,
+    ) {
+        let context = Context::new(None);
+        let descriptor = get_tag_descriptor(&context, sig).unwrap();
+
+        let r#type = descriptor
+            .decide_type(version, &TagValue::XYZ(CIEXYZ::default()))
+            .unwrap();
+
+        assert_eq!(r#type, expected);
+        assert!(descriptor.is_type_supported(r#type));
+    }
+}
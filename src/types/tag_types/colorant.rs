@@ -0,0 +1,226 @@
+//! `chrm` (chromaticity) and `clro` (colorantOrder) tag types, ICC.1:2022 clauses 10.2 and 10.3.
+
+use std::io::{Error, ErrorKind, Result};
+
+use crate::{
+    io::IOHandler,
+    plugins::TypeHandler,
+    state::{Context, ErrorCode},
+    types::{Chromaticity, TagValue, MAX_CHANNELS},
+};
+
+pub(crate) fn read_chromaticity(
+    _context: &mut Context,
+    _handler: &TypeHandler,
+    io: &mut dyn IOHandler,
+    num_items: &mut usize,
+    size_of_tag: usize,
+) -> Result<TagValue> {
+    let mut channels = io.read_u16()? as usize;
+
+    // Let's recover from a bug introduced in early versions of lcms1
+    if channels == 0 && size_of_tag == 32 {
+        io.read_u16()?;
+        channels = io.read_u16()? as usize;
+    }
+
+    let colorant_type = io.read_u16()?;
+
+    if channels == 0 || channels > MAX_CHANNELS || 4 + channels * 8 > size_of_tag {
+        return Err(Error::from(ErrorKind::InvalidData));
+    }
+
+    let mut xy = Vec::with_capacity(channels);
+    for _ in 0..channels {
+        let x = io.read_s15f16()?;
+        let y = io.read_s15f16()?;
+        xy.push((x, y));
+    }
+
+    *num_items = 1;
+    Ok(TagValue::Chromaticity(Chromaticity::new(
+        colorant_type.into(),
+        &xy,
+    )))
+}
+
+pub(crate) fn write_chromaticity(
+    _context: &mut Context,
+    _handler: &TypeHandler,
+    io: &mut dyn IOHandler,
+    value: &TagValue,
+    _num_items: usize,
+) -> Result<()> {
+    let chromaticity = match value {
+        TagValue::Chromaticity(chromaticity) => chromaticity,
+        _ => return Err(Error::from(ErrorKind::InvalidInput)),
+    };
+
+    io.write_u16(chromaticity.get_channels().len() as u16)?;
+    io.write_u16(chromaticity.get_colorant_type().into())?;
+
+    for (x, y) in chromaticity.get_channels() {
+        io.write_s15f16(*x)?;
+        io.write_s15f16(*y)?;
+    }
+
+    Ok(())
+}
+
+pub(crate) fn read_colorant_order(
+    context: &mut Context,
+    _handler: &TypeHandler,
+    io: &mut dyn IOHandler,
+    num_items: &mut usize,
+    _size_of_tag: usize,
+) -> Result<TagValue> {
+    let count = io.read_u32()? as usize;
+    if count > MAX_CHANNELS {
+        context.signal_error(ErrorCode::Range, format!("Too many colorants '{}'", count));
+        return Err(Error::from(ErrorKind::InvalidData));
+    }
+
+    let mut order = vec![0u8; count];
+    io.read(&mut order)?;
+
+    *num_items = 1;
+    Ok(TagValue::ColorantOrder(order))
+}
+
+pub(crate) fn write_colorant_order(
+    context: &mut Context,
+    _handler: &TypeHandler,
+    io: &mut dyn IOHandler,
+    value: &TagValue,
+    _num_items: usize,
+) -> Result<()> {
+    let order = match value {
+        TagValue::ColorantOrder(order) => order,
+        _ => return Err(Error::from(ErrorKind::InvalidInput)),
+    };
+
+    if order.len() > MAX_CHANNELS {
+        context.signal_error(
+            ErrorCode::Range,
+            format!("Too many colorants '{}'", order.len()),
+        );
+        return Err(Error::from(ErrorKind::InvalidInput));
+    }
+
+    io.write_u32(order.len() as u32)?;
+    io.write(order)
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::{self, ErrorKind};
+
+    use crate::{
+        io::FileMemReadOnly,
+        plugins::TypeHandler,
+        state::Context,
+        types::{signatures, tag_types::round_trip, Chromaticity, ColorantType, Profile, TagValue},
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_chromaticity_round_trips() -> io::Result<()> {
+        let chromaticity = Chromaticity::new(
+            ColorantType::EbuTech3213,
+            &[(0.64, 0.33), (0.29, 0.6), (0.15, 0.06)],
+        );
+
+        let (value, _, data) = round_trip(
+            &TypeHandler::new(
+                signatures::tag_type::CHROMATICITY,
+                read_chromaticity,
+                write_chromaticity,
+            ),
+            &TagValue::Chromaticity(chromaticity.clone()),
+        )?;
+
+        assert_eq!(data.len(), 8 + 4 + 3 * 8);
+        match value {
+            TagValue::Chromaticity(read) => {
+                assert_eq!(read.get_colorant_type(), ColorantType::EbuTech3213);
+                for (read, expected) in read.get_channels().iter().zip(chromaticity.get_channels())
+                {
+                    assert!((read.0 - expected.0).abs() < 1.0 / 65536.0);
+                    assert!((read.1 - expected.1).abs() < 1.0 / 65536.0);
+                }
+            }
+            value => panic!("unexpected tag value {:?}", value),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_chromaticity_recovers_from_lcms1_bug() -> io::Result<()> {
+        let mut context = Context::new(None);
+        let handler = TypeHandler::new(
+            signatures::tag_type::CHROMATICITY,
+            read_chromaticity,
+            write_chromaticity,
+        );
+
+        // The channel count came after 4 zero bytes
+        let mut data = vec![0, 0, 0, 0, 0, 3, 0, 1];
+        data.extend([0, 0, 0x80, 0].repeat(6));
+
+        let mut io = FileMemReadOnly::new(data.as_slice());
+        let mut count = 0;
+        match read_chromaticity(&mut context, &handler, &mut io, &mut count, 32)? {
+            TagValue::Chromaticity(read) => {
+                assert_eq!(read.get_colorant_type(), ColorantType::ItuRBt709);
+                assert_eq!(read.get_channels(), [(0.5, 0.5); 3]);
+            }
+            value => panic!("unexpected tag value {:?}", value),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_colorant_order_round_trips() -> io::Result<()> {
+        let handler = TypeHandler::new(
+            signatures::tag_type::COLORANT_ORDER,
+            read_colorant_order,
+            write_colorant_order,
+        );
+        let (value, _, data) = round_trip(&handler, &TagValue::ColorantOrder(vec![3, 0, 1, 2]))?;
+
+        assert_eq!(data.len(), 8 + 4 + 4);
+        match value {
+            TagValue::ColorantOrder(order) => assert_eq!(order, [3, 0, 1, 2]),
+            value => panic!("unexpected tag value {:?}", value),
+        }
+
+        let err = round_trip(&handler, &TagValue::ColorantOrder(vec![0; 17])).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_profile_saves_chromaticity() -> io::Result<()> {
+        let mut context = Context::new(None);
+        let mut profile = Profile::new();
+        let chromaticity = Chromaticity::new(ColorantType::P22, &[(0.5, 0.25); 3]);
+        profile.write_tag_thr(
+            &mut context,
+            signatures::tag::CHROMATICITY,
+            TagValue::Chromaticity(chromaticity.clone()),
+        )?;
+        let saved = profile.save_to_mem_thr(&mut context)?;
+        let mut reopened = Profile::open_from_mem_thr(&mut context, &saved)?;
+
+        match reopened.read_tag_thr(&mut context, signatures::tag::CHROMATICITY)? {
+            TagValue::Chromaticity(read) => assert_eq!(*read, chromaticity),
+            value => panic!("unexpected tag value {:?}", value),
+        }
+
+        Ok(())
+    }
+}
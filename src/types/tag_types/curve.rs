@@ -0,0 +1,312 @@
+//! `curv` and `para` tag types, ICC.1:2022 clauses 10.6 and 10.18.
+
+use std::io::{Error, ErrorKind, Result};
+
+use crate::{
+    io::{f64_to_u8f8, u8f8_to_f64, IOHandler},
+    plugins::TypeHandler,
+    state::{Context, ErrorCode},
+    types::{TagValue, ToneCurve},
+};
+
+/// The number of parameters of each `para` function type.
+const PARAMS_BY_TYPE: [usize; 5] = [1, 3, 4, 5, 7];
+
+pub(crate) fn read_curve(
+    context: &mut Context,
+    _handler: &TypeHandler,
+    io: &mut dyn IOHandler,
+    num_items: &mut usize,
+    _size_of_tag: usize,
+) -> Result<TagValue> {
+    let count = io.read_u32()?;
+
+    let curve = match count {
+        // Linear.
+        0 => ToneCurve::build_gamma(context, 1.0),
+        // Specified as the exponent of gamma function
+        1 => {
+            let gamma = u8f8_to_f64(io.read_u16()?);
+            ToneCurve::build_gamma(context, gamma)
+        }
+        // This is to prevent bad guys for doing bad things
+        0x8000.. => None,
+        // Curve
+        _ => {
+            let mut table = vec![0u16; count as usize];
+            io.read_u16_array(&mut table)?;
+            ToneCurve::build_tabulated_16(context, &table)
+        }
+    };
+
+    match curve {
+        Some(curve) => {
+            *num_items = 1;
+            Ok(TagValue::Curve(curve))
+        }
+        None => Err(Error::from(ErrorKind::InvalidData)),
+    }
+}
+
+pub(crate) fn write_curve(
+    _context: &mut Context,
+    _handler: &TypeHandler,
+    io: &mut dyn IOHandler,
+    value: &TagValue,
+    _num_items: usize,
+) -> Result<()> {
+    let curve = match value {
+        TagValue::Curve(curve) => curve,
+        _ => return Err(Error::from(ErrorKind::InvalidInput)),
+    };
+
+    if curve.get_segments().len() == 1 && curve.get_parametric_type() == 1 {
+        // Single gamma, preserve number
+        io.write_u32(1)?;
+        return io.write_u16(f64_to_u8f8(curve.get_segments()[0].params[0]));
+    }
+
+    let table = curve.get_table16();
+    io.write_u32(table.len() as u32)?;
+    io.write_u16_array(table)
+}
+
+pub(crate) fn read_parametric_curve(
+    context: &mut Context,
+    _handler: &TypeHandler,
+    io: &mut dyn IOHandler,
+    num_items: &mut usize,
+    _size_of_tag: usize,
+) -> Result<TagValue> {
+    let r#type = io.read_u16()? as usize;
+    io.read_u16()?; // Reserved
+
+    if r#type >= PARAMS_BY_TYPE.len() {
+        context.signal_error(
+            ErrorCode::UnknownExtension,
+            format!("Unknown parametric curve type '{}'", r#type),
+        );
+        return Err(Error::from(ErrorKind::InvalidData));
+    }
+
+    let mut params = [0f64; 10];
+    for param in params.iter_mut().take(PARAMS_BY_TYPE[r#type]) {
+        *param = io.read_s15f16()?;
+    }
+
+    match ToneCurve::build_parametric(context, r#type as i32 + 1, &params) {
+        Some(curve) => {
+            *num_items = 1;
+            Ok(TagValue::Curve(curve))
+        }
+        None => Err(Error::from(ErrorKind::InvalidData)),
+    }
+}
+
+pub(crate) fn write_parametric_curve(
+    context: &mut Context,
+    _handler: &TypeHandler,
+    io: &mut dyn IOHandler,
+    value: &TagValue,
+    _num_items: usize,
+) -> Result<()> {
+    let curve = match value {
+        TagValue::Curve(curve) => curve,
+        _ => return Err(Error::from(ErrorKind::InvalidInput)),
+    };
+
+    let segments = curve.get_segments();
+    if segments.len() != 1 || segments[0].r#type < 1 {
+        context.signal_error(
+            ErrorCode::UnknownExtension,
+            "Multisegment or Inverted parametric curves cannot be written",
+        );
+        return Err(Error::from(ErrorKind::InvalidInput));
+    }
+
+    let segment = &segments[0];
+    if segment.r#type as usize > PARAMS_BY_TYPE.len() {
+        context.signal_error(ErrorCode::UnknownExtension, "Unsupported parametric curve");
+        return Err(Error::from(ErrorKind::InvalidInput));
+    }
+
+    io.write_u16((segment.r#type - 1) as u16)?;
+    io.write_u16(0)?; // Reserved
+
+    for param in segment
+        .params
+        .iter()
+        .take(PARAMS_BY_TYPE[segment.r#type as usize - 1])
+    {
+        io.write_s15f16(*param)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::{self, ErrorKind};
+
+    use test_case::test_case;
+
+    use crate::{
+        io::{FileMem, FileMemReadOnly},
+        plugins::{TagTypeReader, TagTypeWriter, TypeHandler},
+        state::Context,
+        types::{signatures, Profile, TagValue, ToneCurve},
+    };
+
+    use super::{read_curve, read_parametric_curve, write_curve, write_parametric_curve};
+
+    fn read(reader: TagTypeReader, data: &[u8]) -> io::Result<ToneCurve> {
+        let mut context = Context::new(None);
+        let handler = TypeHandler::new(signatures::tag_type::CURVE, reader, write_curve);
+        let mut io = FileMemReadOnly::new(data);
+        let mut count = 0;
+
+        match reader(&mut context, &handler, &mut io, &mut count, data.len())? {
+            TagValue::Curve(curve) => {
+                assert_eq!(count, 1);
+                Ok(curve)
+            }
+            value => panic!("unexpected tag value {:?}", value),
+        }
+    }
+
+    fn write(writer: TagTypeWriter, curve: ToneCurve) -> io::Result<Vec<u8>> {
+        let mut context = Context::new(None);
+        let handler = TypeHandler::new(signatures::tag_type::CURVE, read_curve, writer);
+        let mut io = FileMem::new(Vec::new());
+
+        writer(&mut context, &handler, &mut io, &TagValue::Curve(curve), 1)?;
+
+        Ok(io.cursor.into_inner())
+    }
+
+    #[test_case(&[0, 0, 0, 0], 1.0; "identity")]
+    #[test_case(&[0, 0, 0, 1, 0x02, 0x33], 2.19921875; "gamma")]
+    fn test_read_curve_gamma(data: &[u8], gamma: f64) -> io::Result<()> {
+        let curve = read(read_curve, data)?;
+
+        assert_eq!(curve.get_parametric_type(), 1);
+        assert_eq!(curve.get_params().unwrap()[0], gamma);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_curve_table() -> io::Result<()> {
+        let curve = read(read_curve, &[0, 0, 0, 3, 0, 0, 0x40, 0, 0xff, 0xff])?;
+
+        assert_eq!(curve.get_table16(), &[0, 0x4000, 0xffff]);
+        assert_eq!(curve.eval_u16(0x7fff), 0x4000);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_curve_rejects_huge_tables() {
+        let err = read(read_curve, &[0, 0, 0x80, 0]).unwrap_err();
+
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_write_curve_preserves_gamma() -> io::Result<()> {
+        let mut context = Context::new(None);
+        let curve = ToneCurve::build_gamma(&mut context, 2.2).unwrap();
+
+        let data = write(write_curve, curve)?;
+
+        assert_eq!(data, [0, 0, 0, 1, 0x02, 0x33]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_curve_round_trips_table() -> io::Result<()> {
+        let mut context = Context::new(None);
+        let table = [0u16, 0x1000, 0x5000, 0xffff];
+        let curve = ToneCurve::build_tabulated_16(&mut context, &table).unwrap();
+
+        let data = write(write_curve, curve)?;
+
+        assert_eq!(data.len(), 4 + 2 * table.len());
+        assert_eq!(read(read_curve, &data)?.get_table16(), table);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parametric_curve_round_trips() -> io::Result<()> {
+        let mut context = Context::new(None);
+        let params = [2.4, 1.0 / 1.055, 0.055 / 1.055, 1.0 / 12.92, 0.04045];
+        let curve = ToneCurve::build_parametric(&mut context, 4, &params).unwrap();
+
+        let data = write(write_parametric_curve, curve.clone())?;
+        let read_back = read(read_parametric_curve, &data)?;
+
+        assert_eq!(data.len(), 4 + 4 * params.len());
+        assert_eq!(&data[0..4], &[0, 3, 0, 0]);
+        assert_eq!(read_back.get_parametric_type(), 4);
+        for x in [0.0, 0.01, 0.2, 0.5, 1.0] {
+            assert!((read_back.eval_f32(x) - curve.eval_f32(x)).abs() < 1e-4);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_parametric_curve_rejects_unknown_types() {
+        let err = read(read_parametric_curve, &[0, 5, 0, 0, 0, 1, 0, 0]).unwrap_err();
+
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_write_parametric_curve_rejects_tables() {
+        let mut context = Context::new(None);
+        let curve = ToneCurve::build_tabulated_16(&mut context, &[0, 0xffff]).unwrap();
+
+        let err = write(write_parametric_curve, curve).unwrap_err();
+
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    }
+
+    #[test_case(2.1, b"curv"; "v2")]
+    #[test_case(4.3, b"para"; "v4")]
+    fn test_trc_tags_round_trip_through_profile(
+        version: f64,
+        expected: &[u8; 4],
+    ) -> io::Result<()> {
+        let mut context = Context::new(None);
+        let params = [2.4, 1.0 / 1.055, 0.055 / 1.055, 1.0 / 12.92, 0.04045];
+        let curve = ToneCurve::build_parametric(&mut context, 4, &params).unwrap();
+        let mut profile = Profile::new();
+        profile.set_version(version);
+
+        profile.write_tag_thr(
+            &mut context,
+            signatures::tag::RED_TRC,
+            TagValue::Curve(curve.clone()),
+        )?;
+        let saved = profile.save_to_mem_thr(&mut context)?;
+        let mut reopened = Profile::open_from_mem_thr(&mut context, &saved)?;
+
+        assert_eq!(
+            &reopened.read_raw_tag(signatures::tag::RED_TRC)?[0..4],
+            expected
+        );
+        match reopened.read_tag_thr(&mut context, signatures::tag::RED_TRC)? {
+            TagValue::Curve(read_back) => {
+                for x in [0.0, 0.01, 0.2, 0.5, 1.0] {
+                    assert!((read_back.eval_f32(x) - curve.eval_f32(x)).abs() < 1e-4);
+                }
+            }
+            value => panic!("unexpected tag value {:?}", value),
+        }
+
+        Ok(())
+    }
+}
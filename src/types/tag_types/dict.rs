@@ -0,0 +1,360 @@
+//! `dict` (dictType) tag type, ICC.1:2022 clause 10.7.
+
+use std::io::{Error, ErrorKind, Result, SeekFrom};
+
+use crate::{
+    io::IOHandler,
+    plugins::TypeHandler,
+    state::{Context, ErrorCode},
+    types::{signatures::tag_type, Dict, Mlu, TagValue},
+};
+
+use super::text;
+
+/// Record size with the name and value only.
+const RECORD_SIZE_VALUE: usize = 16;
+/// Record size adding the display name.
+const RECORD_SIZE_DISPLAY_NAME: usize = 24;
+/// Record size adding the display value.
+const RECORD_SIZE_DISPLAY_VALUE: usize = 32;
+
+/// The offset and size of each element of a record, relative to the start of the tag. Missing elements have an
+/// offset of 0.
+type Record = [(usize, usize); 4];
+
+fn read_wide_string(
+    io: &mut dyn IOHandler,
+    base_offset: usize,
+    (offset, size): (usize, usize),
+) -> Result<String> {
+    if offset == 0 {
+        return Ok(String::new());
+    }
+
+    io.seek(SeekFrom::Start((base_offset + offset) as u64))?;
+    let mut units = vec![0u16; size / 2];
+    io.read_u16_array(&mut units)?;
+
+    Ok(String::from_utf16_lossy(&units))
+}
+
+fn read_embedded_mlu(
+    context: &mut Context,
+    handler: &TypeHandler,
+    io: &mut dyn IOHandler,
+    base_offset: usize,
+    (offset, size): (usize, usize),
+) -> Result<Option<Mlu>> {
+    if offset == 0 {
+        return Ok(None);
+    }
+
+    io.seek(SeekFrom::Start((base_offset + offset) as u64))?;
+    let base_type = io.read_type_base()?;
+    if base_type != tag_type::MULTI_LOCALIZED_UNICODE {
+        context.signal_error(
+            ErrorCode::UnknownExtension,
+            format!("Unknown display text type '{:?}' in dictionary", base_type),
+        );
+        return Err(Error::from(ErrorKind::InvalidData));
+    }
+
+    let mut count = 0;
+    match text::read_mlu(context, handler, io, &mut count, size.saturating_sub(8))? {
+        TagValue::Mlu(mlu) => Ok(Some(mlu)),
+        _ => Err(Error::from(ErrorKind::InvalidData)),
+    }
+}
+
+pub(crate) fn read_dict(
+    context: &mut Context,
+    handler: &TypeHandler,
+    io: &mut dyn IOHandler,
+    num_items: &mut usize,
+    size_of_tag: usize,
+) -> Result<TagValue> {
+    // Offsets are relative to the type base
+    let base_offset = io.tell()? - 8;
+
+    let count = io.read_u32()? as usize;
+    let record_size = io.read_u32()? as usize;
+
+    if ![
+        RECORD_SIZE_VALUE,
+        RECORD_SIZE_DISPLAY_NAME,
+        RECORD_SIZE_DISPLAY_VALUE,
+    ]
+    .contains(&record_size)
+    {
+        context.signal_error(
+            ErrorCode::UnknownExtension,
+            format!("Unknown record length in dictionary '{}'", record_size),
+        );
+        return Err(Error::from(ErrorKind::InvalidData));
+    }
+
+    if count > size_of_tag.saturating_sub(8) / record_size {
+        return Err(Error::from(ErrorKind::InvalidData));
+    }
+
+    let mut records = Vec::with_capacity(count);
+    for _ in 0..count {
+        let mut record: Record = [(0, 0); 4];
+        for element in record.iter_mut().take(record_size / 8) {
+            let offset = io.read_u32()? as usize;
+            let size = io.read_u32()? as usize;
+
+            // Leave 8 bytes for the type base in front of the data
+            if offset != 0
+                && offset
+                    .checked_add(size)
+                    .is_none_or(|end| end > size_of_tag + 8)
+            {
+                return Err(Error::from(ErrorKind::InvalidData));
+            }
+            *element = (offset, size);
+        }
+        records.push(record);
+    }
+
+    let mut dict = Dict::new();
+    for [name, value, display_name, display_value] in records {
+        let name = read_wide_string(io, base_offset, name)?;
+        let value = read_wide_string(io, base_offset, value)?;
+        let display_name = read_embedded_mlu(context, handler, io, base_offset, display_name)?;
+        let display_value = read_embedded_mlu(context, handler, io, base_offset, display_value)?;
+
+        dict.add_entry(&name, &value, display_name.as_ref(), display_value.as_ref());
+    }
+
+    *num_items = 1;
+    Ok(TagValue::Dict(dict))
+}
+
+/// Writes `display` with its own type base, returning its offset and size relative to `base_offset`, or zeros when
+/// there is nothing to write.
+fn write_embedded_mlu(
+    context: &mut Context,
+    handler: &TypeHandler,
+    io: &mut dyn IOHandler,
+    base_offset: usize,
+    display: Option<&Mlu>,
+) -> Result<(usize, usize)> {
+    let Some(mlu) = display else {
+        return Ok((0, 0));
+    };
+
+    let before = io.tell()?;
+    io.write_type_base(tag_type::MULTI_LOCALIZED_UNICODE)?;
+    text::write_mlu(context, handler, io, &TagValue::Mlu(mlu.clone()), 1)?;
+
+    Ok((before - base_offset, io.tell()? - before))
+}
+
+/// Writes the smallest record size holding every display name and value of the dictionary.
+pub(crate) fn write_dict(
+    context: &mut Context,
+    handler: &TypeHandler,
+    io: &mut dyn IOHandler,
+    value: &TagValue,
+    _num_items: usize,
+) -> Result<()> {
+    let dict = match value {
+        TagValue::Dict(dict) => dict,
+        _ => return Err(Error::from(ErrorKind::InvalidInput)),
+    };
+    let entries = dict.get_entries();
+
+    let base_offset = io.tell()? - 8;
+
+    let record_size = if entries.iter().any(|e| e.get_display_value().is_some()) {
+        RECORD_SIZE_DISPLAY_VALUE
+    } else if entries.iter().any(|e| e.get_display_name().is_some()) {
+        RECORD_SIZE_DISPLAY_NAME
+    } else {
+        RECORD_SIZE_VALUE
+    };
+
+    io.write_u32(entries.len() as u32)?;
+    io.write_u32(record_size as u32)?;
+
+    // Keep starting position of the directory, to be filled latter
+    let directory_pos = io.tell()?;
+    for _ in 0..entries.len() * record_size / 4 {
+        io.write_u32(0)?;
+    }
+
+    let mut records = Vec::with_capacity(entries.len());
+    for entry in entries {
+        let mut record: Record = [(0, 0); 4];
+
+        for (element, string) in record.iter_mut().zip([entry.get_name(), entry.get_value()]) {
+            let before = io.tell()?;
+            io.write_u16_array(&string.encode_utf16().collect::<Vec<_>>())?;
+            *element = (before - base_offset, io.tell()? - before);
+        }
+        record[2] =
+            write_embedded_mlu(context, handler, io, base_offset, entry.get_display_name())?;
+        record[3] =
+            write_embedded_mlu(context, handler, io, base_offset, entry.get_display_value())?;
+
+        records.push(record);
+    }
+
+    let current_pos = io.tell()?;
+    io.seek(SeekFrom::Start(directory_pos as u64))?;
+    for record in records {
+        for (offset, size) in record.into_iter().take(record_size / 8) {
+            io.write_u32(offset as u32)?;
+            io.write_u32(size as u32)?;
+        }
+    }
+    io.seek(SeekFrom::Start(current_pos as u64))
+}
+
+#[cfg(test)]
+mod test {
+    use std::io;
+
+    use crate::{
+        io::{FileMem, FileMemReadOnly},
+        plugins::TypeHandler,
+        state::Context,
+        types::{signatures, Dict, Mlu, Profile, TagValue},
+    };
+
+    use super::*;
+
+    /// Writes `dict` after a type base, as offsets are relative to it, and returns the whole tag.
+    fn write(dict: &Dict) -> io::Result<Vec<u8>> {
+        let mut context = Context::new(None);
+        let handler = TypeHandler::new(tag_type::DICT, read_dict, write_dict);
+
+        let mut mem = FileMem::new(Vec::new());
+        mem.write_type_base(tag_type::DICT)?;
+        write_dict(
+            &mut context,
+            &handler,
+            &mut mem,
+            &TagValue::Dict(dict.clone()),
+            1,
+        )?;
+        Ok(mem.cursor.into_inner())
+    }
+
+    fn read(tag: &[u8]) -> io::Result<Dict> {
+        let mut context = Context::new(None);
+        let handler = TypeHandler::new(tag_type::DICT, read_dict, write_dict);
+
+        let mut io = FileMemReadOnly::new(tag);
+        io.read_type_base()?;
+        let mut count = 0;
+        match read_dict(&mut context, &handler, &mut io, &mut count, tag.len() - 8)? {
+            TagValue::Dict(dict) => Ok(dict),
+            value => panic!("unexpected tag value {:?}", value),
+        }
+    }
+
+    fn record_size(tag: &[u8]) -> u32 {
+        u32::from_be_bytes(tag[12..16].try_into().unwrap())
+    }
+
+    #[test]
+    fn test_names_and_values_round_trip() -> io::Result<()> {
+        let mut dict = Dict::new();
+        dict.add_entry("Source", "Scanner \u{1F5A8}", None, None);
+        dict.add_entry("Empty", "", None, None);
+
+        let tag = write(&dict)?;
+        assert_eq!(record_size(&tag), 16);
+        assert_eq!(read(&tag)?, dict);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_display_names_round_trip() -> io::Result<()> {
+        let mut display = Mlu::new();
+        display.set(['e', 'n'], ['U', 'S'], "Print run");
+        display.set(['d', 'e'], ['D', 'E'], "Auflage");
+
+        let mut dict = Dict::new();
+        dict.add_entry("Run", "42", Some(&display), None);
+        dict.add_entry("Source", "Scanner", None, None);
+
+        let tag = write(&dict)?;
+        assert_eq!(record_size(&tag), 24);
+        assert_eq!(read(&tag)?, dict);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_display_values_round_trip() -> io::Result<()> {
+        let mut dict = Dict::new();
+        dict.add_entry("Run", "42", None, Some(&Mlu::from_text("forty-two")));
+
+        let tag = write(&dict)?;
+        assert_eq!(record_size(&tag), 32);
+
+        let read = read(&tag)?;
+        assert_eq!(read, dict);
+        assert!(read.get(0).unwrap().get_display_name().is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_missing_value_reads_empty() -> io::Result<()> {
+        let mut tag = b"dict\0\0\0\0".to_vec();
+        tag.extend_from_slice(&[0, 0, 0, 1, 0, 0, 0, 16]);
+        tag.extend_from_slice(&[0, 0, 0, 32, 0, 0, 0, 4, 0, 0, 0, 0, 0, 0, 0, 0]);
+        tag.extend_from_slice(&[0, b'I', 0, b'D']);
+
+        let dict = read(&tag)?;
+        assert_eq!(dict.get_value("ID"), Some(""));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_unknown_record_size_fails() {
+        let mut tag = b"dict\0\0\0\0".to_vec();
+        tag.extend_from_slice(&[0, 0, 0, 1, 0, 0, 0, 20]);
+        tag.extend_from_slice(&[0; 20]);
+
+        assert!(read(&tag).is_err());
+    }
+
+    #[test]
+    fn test_out_of_bounds_offset_fails() {
+        let mut tag = b"dict\0\0\0\0".to_vec();
+        tag.extend_from_slice(&[0, 0, 0, 1, 0, 0, 0, 16]);
+        tag.extend_from_slice(&[0, 0, 0, 32, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+
+        assert!(read(&tag).is_err());
+    }
+
+    #[test]
+    fn test_metadata_round_trips_through_profile() -> io::Result<()> {
+        let mut context = Context::new(None);
+        let mut dict = Dict::new();
+        dict.add_entry("asset.origin", "render-farm", None, None);
+        dict.add_entry("asset.revision", "7", None, None);
+
+        let mut profile = Profile::new();
+        assert_eq!(
+            profile.metadata_thr(&mut context).unwrap_err().kind(),
+            io::ErrorKind::NotFound
+        );
+        profile.write_tag_thr(&mut context, signatures::tag::META, TagValue::Dict(dict))?;
+        let saved = profile.save_to_mem_thr(&mut context)?;
+        let mut reopened = Profile::open_from_mem_thr(&mut context, &saved)?;
+
+        let metadata = reopened.metadata_thr(&mut context)?;
+        assert_eq!(metadata.get_count(), 2);
+        assert_eq!(metadata.get_value("asset.revision"), Some("7"));
+
+        Ok(())
+    }
+}
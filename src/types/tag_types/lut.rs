@@ -0,0 +1,1889 @@
+//! `mft1` (lut8), `mft2` (lut16), `mAB ` (lutAtoB) and `mBA ` (lutBtoA) tag types, ICC.1:2022 clauses 10.10 to 10.13.
+
+use std::io::{Error, ErrorKind, Result, SeekFrom};
+
+use crate::{
+    io::IOHandler,
+    plugins::TypeHandler,
+    quick_saturate_word,
+    state::{Context, ErrorCode},
+    types::{
+        signatures::{stage, tag_type},
+        Pipeline, Signature, Stage, StageClutData, StageData, StageLoc, StageMatrixData,
+        StageToneCurveData, Tab, TagValue, ToneCurve, MAX_CHANNELS,
+    },
+};
+
+use super::{curve, decide_curve_type};
+
+const IDENTITY_MATRIX: [f64; 9] = [1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0];
+
+pub(super) fn from_8_to_16(value: u8) -> u16 {
+    ((value as u16) << 8) | value as u16
+}
+
+fn from_16_to_8(value: u16) -> u8 {
+    ((value as u32 * 65281 + 8388608) >> 24) as u8
+}
+
+/// Returns the number of entries of a CLUT of `grid_points` in each of `inputs` dimensions with `outputs` values per
+/// node, 0 if there is no CLUT, or `None` on overflow.
+fn clut_entries(outputs: usize, grid_points: usize, inputs: usize) -> Option<usize> {
+    if grid_points == 0 || outputs == 0 {
+        return Some(0);
+    }
+
+    (0..inputs)
+        .try_fold(1usize, |size, _| size.checked_mul(grid_points))
+        .and_then(|size| size.checked_mul(outputs))
+}
+
+fn is_identity(matrix: &[f64]) -> bool {
+    matrix
+        .iter()
+        .zip(IDENTITY_MATRIX.iter())
+        .all(|(a, b)| (a - b).abs() < 1.0 / 65535.0)
+}
+
+fn insert(context: &mut Context, lut: &mut Pipeline, stage: Option<Stage>) -> Result<()> {
+    let stage = stage.ok_or_else(|| Error::from(ErrorKind::InvalidData))?;
+
+    if lut.insert_stage(context, StageLoc::AtEnd, stage) {
+        Ok(())
+    } else {
+        Err(Error::from(ErrorKind::InvalidData))
+    }
+}
+
+/// Reads the channel counts, the number of CLUT grid points and the matrix shared by lut8 and lut16, returning an
+/// empty pipeline holding the matrix if it is needed.
+fn read_lut_header(
+    context: &mut Context,
+    io: &mut dyn IOHandler,
+) -> Result<(Pipeline, usize, usize, usize)> {
+    let input_channels = io.read_u8()? as usize;
+    let output_channels = io.read_u8()? as usize;
+    let clut_points = io.read_u8()? as usize;
+    io.read_u8()?; // Padding
+
+    // 0 for no CLUT and then 2 at least
+    if clut_points == 1 {
+        return Err(Error::from(ErrorKind::InvalidData));
+    }
+
+    if input_channels == 0
+        || input_channels > MAX_CHANNELS
+        || output_channels == 0
+        || output_channels > MAX_CHANNELS
+    {
+        return Err(Error::from(ErrorKind::InvalidData));
+    }
+
+    let mut lut = Pipeline::new(context, input_channels as u32, output_channels as u32)
+        .ok_or_else(|| Error::from(ErrorKind::InvalidData))?;
+
+    let mut matrix = [0f64; 9];
+    for value in matrix.iter_mut() {
+        *value = io.read_s15f16()?;
+    }
+
+    // Only operates if not identity...
+    if input_channels == 3 && !is_identity(&matrix) {
+        let stage = Stage::new_matrix(context, 3, 3, &matrix, None);
+        insert(context, &mut lut, stage)?;
+    }
+
+    Ok((lut, input_channels, output_channels, clut_points))
+}
+
+/// Reads one 16 bit table of `entries` entries per channel as a set of curves. No entries means no curves.
+fn read_16bit_tables(
+    context: &mut Context,
+    io: &mut dyn IOHandler,
+    lut: &mut Pipeline,
+    channels: usize,
+    entries: usize,
+) -> Result<()> {
+    // Maybe an empty table? (this is a lcms extension)
+    if entries == 0 {
+        return Ok(());
+    }
+
+    // Check for malicious profiles
+    if entries < 2 {
+        return Err(Error::from(ErrorKind::InvalidData));
+    }
+
+    let mut curves = Vec::with_capacity(channels);
+    let mut table = vec![0u16; entries];
+    for _ in 0..channels {
+        io.read_u16_array(&mut table)?;
+        curves.push(
+            ToneCurve::build_tabulated_16(context, &table)
+                .ok_or_else(|| Error::from(ErrorKind::InvalidData))?,
+        );
+    }
+
+    let stage = Stage::new_tone_curves(context, channels as u32, Some(&curves));
+    insert(context, lut, stage)
+}
+
+/// Reads one 256 entry 8 bit table per channel as a set of curves.
+fn read_8bit_tables(
+    context: &mut Context,
+    io: &mut dyn IOHandler,
+    lut: &mut Pipeline,
+    channels: usize,
+) -> Result<()> {
+    let mut curves = Vec::with_capacity(channels);
+    let mut temp = [0u8; 256];
+    for _ in 0..channels {
+        io.read(&mut temp)?;
+
+        let table = temp.map(from_8_to_16);
+        curves.push(
+            ToneCurve::build_tabulated_16(context, &table)
+                .ok_or_else(|| Error::from(ErrorKind::InvalidData))?,
+        );
+    }
+
+    let stage = Stage::new_tone_curves(context, channels as u32, Some(&curves));
+    insert(context, lut, stage)
+}
+
+pub(crate) fn read_lut8(
+    context: &mut Context,
+    _handler: &TypeHandler,
+    io: &mut dyn IOHandler,
+    num_items: &mut usize,
+    size_of_tag: usize,
+) -> Result<TagValue> {
+    let (mut lut, input_channels, output_channels, clut_points) = read_lut_header(context, io)?;
+
+    // Get input tables
+    read_8bit_tables(context, io, &mut lut, input_channels)?;
+
+    // Get 3D CLUT. Check the overflow....
+    let tab_size = clut_entries(output_channels, clut_points, input_channels)
+        .filter(|size| *size <= size_of_tag)
+        .ok_or_else(|| Error::from(ErrorKind::InvalidData))?;
+    if tab_size > 0 {
+        let mut temp = vec![0u8; tab_size];
+        io.read(&mut temp)?;
+
+        let table = temp.into_iter().map(from_8_to_16).collect::<Vec<_>>();
+        let stage = Stage::new_clut_16bit(
+            context,
+            clut_points as u32,
+            input_channels as u32,
+            output_channels as u32,
+            Some(&table),
+        );
+        insert(context, &mut lut, stage)?;
+    }
+
+    // Get output tables
+    read_8bit_tables(context, io, &mut lut, output_channels)?;
+
+    lut.set_save_as_8_bits(true);
+
+    *num_items = 1;
+    Ok(TagValue::Pipeline(lut))
+}
+
+pub(crate) fn read_lut16(
+    context: &mut Context,
+    _handler: &TypeHandler,
+    io: &mut dyn IOHandler,
+    num_items: &mut usize,
+    size_of_tag: usize,
+) -> Result<TagValue> {
+    let (mut lut, input_channels, output_channels, clut_points) = read_lut_header(context, io)?;
+
+    let input_entries = io.read_u16()? as usize;
+    let output_entries = io.read_u16()? as usize;
+
+    if input_entries > 0x7FFF || output_entries > 0x7FFF {
+        return Err(Error::from(ErrorKind::InvalidData));
+    }
+
+    // Get input tables
+    read_16bit_tables(context, io, &mut lut, input_channels, input_entries)?;
+
+    // Get 3D CLUT
+    let tab_size = clut_entries(output_channels, clut_points, input_channels)
+        .filter(|size| *size <= size_of_tag / 2)
+        .ok_or_else(|| Error::from(ErrorKind::InvalidData))?;
+    if tab_size > 0 {
+        let mut table = vec![0u16; tab_size];
+        io.read_u16_array(&mut table)?;
+
+        let stage = Stage::new_clut_16bit(
+            context,
+            clut_points as u32,
+            input_channels as u32,
+            output_channels as u32,
+            Some(&table),
+        );
+        insert(context, &mut lut, stage)?;
+    }
+
+    // Get output tables
+    read_16bit_tables(context, io, &mut lut, output_channels, output_entries)?;
+
+    *num_items = 1;
+    Ok(TagValue::Pipeline(lut))
+}
+
+/// The parts of a pipeline that lut8 and lut16 can hold: matrix, input curves, CLUT and output curves, in that order.
+struct LutParts<'a> {
+    matrix: Option<&'a StageMatrixData>,
+    pre: Option<&'a StageToneCurveData>,
+    clut: Option<&'a StageClutData>,
+    post: Option<&'a StageToneCurveData>,
+    clut_points: usize,
+}
+
+/// Disassembles `lut` into its lut8 or lut16 parts, signaling an error if it has any other layout.
+fn disassemble<'a>(context: &mut Context, lut: &'a Pipeline, name: &str) -> Result<LutParts<'a>> {
+    let mut stages = lut.get_stages().iter().peekable();
+    let mut parts = LutParts {
+        matrix: None,
+        pre: None,
+        clut: None,
+        post: None,
+        clut_points: 0,
+    };
+
+    if let Some(StageData::Matrix(matrix)) = stages.peek().map(|stage| stage.get_data()) {
+        let stage = stages.next().unwrap();
+        if stage.get_input_channels() != 3
+            || stage.get_output_channels() != 3
+            || matrix.get_offset().is_some()
+        {
+            context.signal_error(
+                ErrorCode::UnknownExtension,
+                format!("Only 3x3 matrices without offset can be saved as {}", name),
+            );
+            return Err(Error::from(ErrorKind::InvalidInput));
+        }
+        parts.matrix = Some(matrix);
+    }
+    if let Some(StageData::ToneCurves(curves)) = stages.peek().map(|stage| stage.get_data()) {
+        stages.next();
+        parts.pre = Some(curves);
+    }
+    if let Some(StageData::CLut(clut)) = stages.peek().map(|stage| stage.get_data()) {
+        stages.next();
+        parts.clut = Some(clut);
+    }
+    if let Some(StageData::ToneCurves(curves)) = stages.peek().map(|stage| stage.get_data()) {
+        stages.next();
+        parts.post = Some(curves);
+    }
+
+    // That should be all
+    if stages.next().is_some() {
+        context.signal_error(
+            ErrorCode::UnknownExtension,
+            format!("LUT is not suitable to be saved as {}", name),
+        );
+        return Err(Error::from(ErrorKind::InvalidInput));
+    }
+
+    if let Some(clut) = parts.clut {
+        // Only allows same CLUT points in all dimensions
+        let grid_points = clut.get_grid_points();
+        if grid_points.iter().any(|points| *points != grid_points[0]) || grid_points[0] > 255 {
+            context.signal_error(
+                ErrorCode::UnknownExtension,
+                format!(
+                    "LUT with different samples per dimension not suitable to be saved as {}",
+                    name
+                ),
+            );
+            return Err(Error::from(ErrorKind::InvalidInput));
+        }
+        parts.clut_points = grid_points[0] as usize;
+    }
+
+    Ok(parts)
+}
+
+fn write_lut_header(io: &mut dyn IOHandler, lut: &Pipeline, parts: &LutParts) -> Result<()> {
+    io.write_u8(lut.get_input_channels() as u8)?;
+    io.write_u8(lut.get_output_channels() as u8)?;
+    io.write_u8(parts.clut_points as u8)?;
+    io.write_u8(0)?; // Padding
+
+    let matrix = parts
+        .matrix
+        .map_or(IDENTITY_MATRIX.as_slice(), |matrix| matrix.get_matrix());
+    for value in matrix {
+        io.write_s15f16(*value)?;
+    }
+
+    Ok(())
+}
+
+/// Returns the 16 bit values of a CLUT, converting floating point ones.
+fn clut_table16(clut: &StageClutData) -> Vec<u16> {
+    match clut.get_table() {
+        Tab::U16(tab) => tab.to_vec(),
+        Tab::F32(tab) => tab
+            .iter()
+            .map(|v| quick_saturate_word(*v as f64 * 65535.0))
+            .collect(),
+    }
+}
+
+/// Writes 256 entry 8 bit tables for `channels` curves, or identities without curves.
+fn write_8bit_tables(
+    context: &mut Context,
+    io: &mut dyn IOHandler,
+    channels: usize,
+    tables: Option<&StageToneCurveData>,
+) -> Result<()> {
+    let identity = (0..=255u8).collect::<Vec<_>>();
+
+    let curves = match tables {
+        Some(tables) => tables.get_curves(),
+        None => {
+            for _ in 0..channels {
+                io.write(&identity)?;
+            }
+            return Ok(());
+        }
+    };
+
+    for curve in curves {
+        let table = curve.get_table16();
+
+        // Usual case of identity curves
+        if table == [0, 0xffff] {
+            io.write(&identity)?;
+        } else if table.len() != 256 {
+            context.signal_error(
+                ErrorCode::Range,
+                "LUT8 needs 256 entries on prelinearization",
+            );
+            return Err(Error::from(ErrorKind::InvalidInput));
+        } else {
+            let values = table.iter().map(|v| from_16_to_8(*v)).collect::<Vec<_>>();
+            io.write(&values)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns the number of entries of the 16 bit tables of `tables`, which must be the same for all curves.
+fn entries_16bit(context: &mut Context, tables: Option<&StageToneCurveData>) -> Result<usize> {
+    let curves = match tables {
+        Some(tables) => tables.get_curves(),
+        None => return Ok(2),
+    };
+
+    let entries = curves[0].get_table16().len();
+    if entries > 0xffff || curves.iter().any(|c| c.get_table16().len() != entries) {
+        context.signal_error(
+            ErrorCode::Range,
+            "LUT16 needs the same number of entries on all curves",
+        );
+        return Err(Error::from(ErrorKind::InvalidInput));
+    }
+
+    Ok(entries)
+}
+
+/// Writes the 16 bit tables of `channels` curves, or identities without curves.
+fn write_16bit_tables(
+    io: &mut dyn IOHandler,
+    channels: usize,
+    tables: Option<&StageToneCurveData>,
+) -> Result<()> {
+    match tables {
+        Some(tables) => {
+            for curve in tables.get_curves() {
+                io.write_u16_array(curve.get_table16())?;
+            }
+        }
+        None => {
+            for _ in 0..channels {
+                io.write_u16_array(&[0, 0xffff])?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+pub(crate) fn write_lut8(
+    context: &mut Context,
+    _handler: &TypeHandler,
+    io: &mut dyn IOHandler,
+    value: &TagValue,
+    _num_items: usize,
+) -> Result<()> {
+    let lut = match value {
+        TagValue::Pipeline(lut) => lut,
+        _ => return Err(Error::from(ErrorKind::InvalidInput)),
+    };
+
+    // Disassemble the LUT into components.
+    let parts = disassemble(context, lut, "LUT8")?;
+    let input_channels = lut.get_input_channels() as usize;
+    let output_channels = lut.get_output_channels() as usize;
+
+    write_lut_header(io, lut, &parts)?;
+
+    // Prelinearization
+    write_8bit_tables(context, io, input_channels, parts.pre)?;
+
+    // The 3D CLUT.
+    if let Some(clut) = parts.clut {
+        let values = clut_table16(clut)
+            .into_iter()
+            .map(from_16_to_8)
+            .collect::<Vec<_>>();
+        io.write(&values)?;
+    }
+
+    // The postlinearization table
+    write_8bit_tables(context, io, output_channels, parts.post)
+}
+
+pub(crate) fn write_lut16(
+    context: &mut Context,
+    _handler: &TypeHandler,
+    io: &mut dyn IOHandler,
+    value: &TagValue,
+    _num_items: usize,
+) -> Result<()> {
+    let lut = match value {
+        TagValue::Pipeline(lut) => lut,
+        _ => return Err(Error::from(ErrorKind::InvalidInput)),
+    };
+
+    // Disassemble the LUT into components.
+    let parts = disassemble(context, lut, "LUT16")?;
+    let input_channels = lut.get_input_channels() as usize;
+    let output_channels = lut.get_output_channels() as usize;
+
+    let input_entries = entries_16bit(context, parts.pre)?;
+    let output_entries = entries_16bit(context, parts.post)?;
+
+    write_lut_header(io, lut, &parts)?;
+    io.write_u16(input_entries as u16)?;
+    io.write_u16(output_entries as u16)?;
+
+    // The prelinearization table
+    write_16bit_tables(io, input_channels, parts.pre)?;
+
+    // The 3D CLUT.
+    if let Some(clut) = parts.clut {
+        io.write_u16_array(&clut_table16(clut))?;
+    }
+
+    // The postlinearization table
+    write_16bit_tables(io, output_channels, parts.post)
+}
+
+/// Scale from the V4 16 bit Lab encoding to the V2 one, where L* = 100 is `0xff00` rather than `0xffff`.
+const LAB_V4_TO_V2_SCALE: f64 = 65280.0 / 65535.0;
+
+/// Number of entries of the input curves the Lab encoding conversion is folded into, as they are not linear.
+const LAB_INPUT_ENTRIES: usize = 4096;
+
+/// Samples `curves` (or identities without curves) into tables of `entries` entries, scaling their input by `in_scale`
+/// and their output by `out_scale`.
+fn scale_curves(
+    context: &mut Context,
+    channels: u32,
+    curves: Option<&[ToneCurve]>,
+    entries: usize,
+    in_scale: f64,
+    out_scale: f64,
+) -> Result<Stage> {
+    let mut scaled = Vec::with_capacity(channels as usize);
+    for i in 0..channels as usize {
+        let table = (0..entries)
+            .map(|j| {
+                let x = (j as f64 / (entries - 1) as f64 * in_scale).min(1.0);
+                let y = match curves {
+                    Some(curves) => curves[i].eval_f32(x as f32) as f64,
+                    None => x,
+                };
+                quick_saturate_word(y * out_scale * 65535.0)
+            })
+            .collect::<Vec<_>>();
+
+        let curve = ToneCurve::build_tabulated_16(context, &table)
+            .ok_or_else(|| Error::from(ErrorKind::InvalidInput))?;
+        scaled.push(curve);
+    }
+
+    Stage::new_tone_curves(context, channels, Some(&scaled))
+        .ok_or_else(|| Error::from(ErrorKind::InvalidInput))
+}
+
+fn insert_at(
+    context: &mut Context,
+    lut: &mut Pipeline,
+    location: StageLoc,
+    stage: Stage,
+) -> Result<()> {
+    if lut.insert_stage(context, location, stage) {
+        Ok(())
+    } else {
+        Err(Error::from(ErrorKind::InvalidInput))
+    }
+}
+
+/// Converts `lut`, whose Lab input and/or output use the V4 16 bit encoding, to the V2 encoding lut16 holds Lab in.
+///
+/// This is the reverse of what [`Profile::read_lut_tag`](crate::types::Profile::read_lut_tag) does: the Lab stages it
+/// adds are removed, and otherwise the conversion is folded into the first matrix or curves of the pipeline and into
+/// its last curves, as lut16 can't hold any other stage there.
+pub(crate) fn encode_lab_v2(
+    context: &mut Context,
+    lut: &Pipeline,
+    lab_in: bool,
+    lab_out: bool,
+) -> Result<Pipeline> {
+    let mut lut = lut.clone();
+
+    if lab_in {
+        let scale = 1.0 / LAB_V4_TO_V2_SCALE;
+        let channels = lut.get_input_channels();
+
+        match lut.remove_stage(StageLoc::AtBegin) {
+            Some(stage) if stage.get_implements() == stage::LAB_V4_TO_V2 => {}
+            Some(stage) => {
+                let replacement = match stage.get_data() {
+                    StageData::Matrix(matrix) => {
+                        let scaled = matrix
+                            .get_matrix()
+                            .iter()
+                            .map(|v| v * scale)
+                            .collect::<Vec<_>>();
+                        Stage::new_matrix(
+                            context,
+                            stage.get_output_channels(),
+                            stage.get_input_channels(),
+                            &scaled,
+                            matrix.get_offset(),
+                        )
+                        .ok_or_else(|| Error::from(ErrorKind::InvalidInput))?
+                    }
+                    StageData::ToneCurves(curves) => {
+                        let curves = curves.get_curves();
+                        let entries = curves
+                            .iter()
+                            .map(|curve| curve.get_table16().len())
+                            .fold(LAB_INPUT_ENTRIES, usize::max);
+                        scale_curves(context, channels, Some(curves), entries, scale, 1.0)?
+                    }
+                    _ => {
+                        insert_at(context, &mut lut, StageLoc::AtBegin, stage)?;
+                        scale_curves(context, channels, None, LAB_INPUT_ENTRIES, scale, 1.0)?
+                    }
+                };
+                insert_at(context, &mut lut, StageLoc::AtBegin, replacement)?;
+            }
+            None => {
+                let stage = scale_curves(context, channels, None, LAB_INPUT_ENTRIES, scale, 1.0)?;
+                insert_at(context, &mut lut, StageLoc::AtBegin, stage)?;
+            }
+        }
+    }
+
+    if lab_out {
+        let channels = lut.get_output_channels();
+
+        match lut.remove_stage(StageLoc::AtEnd) {
+            Some(stage) if stage.get_implements() == stage::LAB_V2_TO_V4 => {}
+            Some(stage) => {
+                let replacement = match stage.get_data() {
+                    StageData::ToneCurves(curves) => {
+                        let curves = curves.get_curves();
+                        let entries = curves
+                            .iter()
+                            .map(|curve| curve.get_table16().len())
+                            .fold(2, usize::max);
+                        scale_curves(
+                            context,
+                            channels,
+                            Some(curves),
+                            entries,
+                            1.0,
+                            LAB_V4_TO_V2_SCALE,
+                        )?
+                    }
+                    _ => {
+                        insert_at(context, &mut lut, StageLoc::AtEnd, stage)?;
+                        scale_curves(context, channels, None, 2, 1.0, LAB_V4_TO_V2_SCALE)?
+                    }
+                };
+                insert_at(context, &mut lut, StageLoc::AtEnd, replacement)?;
+            }
+            None => {
+                let stage = scale_curves(context, channels, None, 2, 1.0, LAB_V4_TO_V2_SCALE)?;
+                insert_at(context, &mut lut, StageLoc::AtEnd, stage)?;
+            }
+        }
+    }
+
+    Ok(lut)
+}
+
+/// Reads one embedded `curv` or `para` curve per channel at `offset`, as a set of curves.
+fn read_set_of_curves(
+    context: &mut Context,
+    handler: &TypeHandler,
+    io: &mut dyn IOHandler,
+    offset: usize,
+    channels: usize,
+) -> Result<Stage> {
+    if channels > MAX_CHANNELS {
+        return Err(Error::from(ErrorKind::InvalidData));
+    }
+
+    io.seek(SeekFrom::Start(offset as u64))?;
+
+    let mut curves = Vec::with_capacity(channels);
+    for _ in 0..channels {
+        let base_type = io.read_type_base()?;
+        let read = match base_type {
+            tag_type::CURVE => curve::read_curve,
+            tag_type::PARAMETRIC_CURVE => curve::read_parametric_curve,
+            _ => {
+                context.signal_error(
+                    ErrorCode::UnknownExtension,
+                    format!("Unknown curve type '{:?}'", base_type),
+                );
+                return Err(Error::from(ErrorKind::InvalidData));
+            }
+        };
+
+        let mut count = 0;
+        match read(context, handler, io, &mut count, 0)? {
+            TagValue::Curve(curve) => curves.push(curve),
+            _ => return Err(Error::from(ErrorKind::InvalidData)),
+        }
+        io.read_alignment()?;
+    }
+
+    Stage::new_tone_curves(context, channels as u32, Some(&curves))
+        .ok_or_else(|| Error::from(ErrorKind::InvalidData))
+}
+
+/// Reads the 3x3 matrix and the offset at `offset`.
+fn read_matrix(context: &mut Context, io: &mut dyn IOHandler, offset: usize) -> Result<Stage> {
+    io.seek(SeekFrom::Start(offset as u64))?;
+
+    let mut matrix = [0f64; 9];
+    for value in matrix.iter_mut() {
+        *value = io.read_s15f16()?;
+    }
+
+    let mut offsets = [0f64; 3];
+    for value in offsets.iter_mut() {
+        *value = io.read_s15f16()?;
+    }
+
+    Stage::new_matrix(context, 3, 3, &matrix, Some(&offsets))
+        .ok_or_else(|| Error::from(ErrorKind::InvalidData))
+}
+
+/// Reads the CLUT at `offset`, which has its own number of grid points per dimension and 8 or 16 bit precision.
+fn read_clut(
+    context: &mut Context,
+    io: &mut dyn IOHandler,
+    offset: usize,
+    input_channels: usize,
+    output_channels: usize,
+    size_of_tag: usize,
+) -> Result<Stage> {
+    io.seek(SeekFrom::Start(offset as u64))?;
+
+    let mut grid_points8 = [0u8; MAX_CHANNELS];
+    io.read(&mut grid_points8)?;
+
+    // Impossible value, 0 for no CLUT and then 2 at least
+    if grid_points8.contains(&1) {
+        return Err(Error::from(ErrorKind::InvalidData));
+    }
+    let grid_points = grid_points8.map(|points| points as u32);
+
+    let precision = io.read_u8()?;
+    let mut padding = [0u8; 3];
+    io.read(&mut padding)?;
+
+    let entries = grid_points[..input_channels.min(MAX_CHANNELS)]
+        .iter()
+        .try_fold(output_channels, |size, points| {
+            size.checked_mul(*points as usize)
+        })
+        .filter(|size| {
+            size.checked_mul(precision.max(1) as usize)
+                .is_some_and(|bytes| bytes <= size_of_tag)
+        })
+        .ok_or_else(|| Error::from(ErrorKind::InvalidData))?;
+
+    // Precision can be 1 or 2 bytes
+    let table = match precision {
+        1 => {
+            let mut temp = vec![0u8; entries];
+            io.read(&mut temp)?;
+            temp.into_iter().map(from_8_to_16).collect::<Vec<_>>()
+        }
+        2 => {
+            let mut table = vec![0u16; entries];
+            io.read_u16_array(&mut table)?;
+            table
+        }
+        _ => {
+            context.signal_error(
+                ErrorCode::UnknownExtension,
+                format!("Unknown precision of '{}'", precision),
+            );
+            return Err(Error::from(ErrorKind::InvalidData));
+        }
+    };
+
+    Stage::new_clut_16bit_granular(
+        context,
+        &grid_points,
+        input_channels as u32,
+        output_channels as u32,
+        Some(&table),
+    )
+    .ok_or_else(|| Error::from(ErrorKind::InvalidData))
+}
+
+/// Reads the channel counts and the offsets of the B curves, matrix, M curves, CLUT and A curves shared by lutAtoB and
+/// lutBtoA, returning an empty pipeline.
+fn read_lut_ab_header(
+    context: &mut Context,
+    io: &mut dyn IOHandler,
+) -> Result<(Pipeline, usize, usize, [usize; 5])> {
+    let input_channels = io.read_u8()? as usize;
+    let output_channels = io.read_u8()? as usize;
+    io.read_u16()?; // Padding
+
+    let mut offsets = [0usize; 5];
+    for offset in offsets.iter_mut() {
+        *offset = io.read_u32()? as usize;
+    }
+
+    if input_channels == 0
+        || input_channels >= MAX_CHANNELS
+        || output_channels == 0
+        || output_channels >= MAX_CHANNELS
+    {
+        return Err(Error::from(ErrorKind::InvalidData));
+    }
+
+    let lut = Pipeline::new(context, input_channels as u32, output_channels as u32)
+        .ok_or_else(|| Error::from(ErrorKind::InvalidData))?;
+
+    Ok((lut, input_channels, output_channels, offsets))
+}
+
+pub(crate) fn read_lut_a_to_b(
+    context: &mut Context,
+    handler: &TypeHandler,
+    io: &mut dyn IOHandler,
+    num_items: &mut usize,
+    size_of_tag: usize,
+) -> Result<TagValue> {
+    // Offsets are relative to the start of the tag, including its type base
+    let base_offset = io.tell()? - 8;
+
+    let (mut lut, input_channels, output_channels, offsets) = read_lut_ab_header(context, io)?;
+    let [offset_b, offset_mat, offset_m, offset_c, offset_a] = offsets;
+
+    if offset_a != 0 {
+        let stage =
+            read_set_of_curves(context, handler, io, base_offset + offset_a, input_channels)?;
+        insert(context, &mut lut, Some(stage))?;
+    }
+    if offset_c != 0 {
+        let stage = read_clut(
+            context,
+            io,
+            base_offset + offset_c,
+            input_channels,
+            output_channels,
+            size_of_tag,
+        )?;
+        insert(context, &mut lut, Some(stage))?;
+    }
+    if offset_m != 0 {
+        let stage = read_set_of_curves(
+            context,
+            handler,
+            io,
+            base_offset + offset_m,
+            output_channels,
+        )?;
+        insert(context, &mut lut, Some(stage))?;
+    }
+    if offset_mat != 0 {
+        let stage = read_matrix(context, io, base_offset + offset_mat)?;
+        insert(context, &mut lut, Some(stage))?;
+    }
+    if offset_b != 0 {
+        let stage = read_set_of_curves(
+            context,
+            handler,
+            io,
+            base_offset + offset_b,
+            output_channels,
+        )?;
+        insert(context, &mut lut, Some(stage))?;
+    }
+
+    *num_items = 1;
+    Ok(TagValue::Pipeline(lut))
+}
+
+pub(crate) fn read_lut_b_to_a(
+    context: &mut Context,
+    handler: &TypeHandler,
+    io: &mut dyn IOHandler,
+    num_items: &mut usize,
+    size_of_tag: usize,
+) -> Result<TagValue> {
+    // Offsets are relative to the start of the tag, including its type base
+    let base_offset = io.tell()? - 8;
+
+    let (mut lut, input_channels, output_channels, offsets) = read_lut_ab_header(context, io)?;
+    let [offset_b, offset_mat, offset_m, offset_c, offset_a] = offsets;
+
+    if offset_b != 0 {
+        let stage =
+            read_set_of_curves(context, handler, io, base_offset + offset_b, input_channels)?;
+        insert(context, &mut lut, Some(stage))?;
+    }
+    if offset_mat != 0 {
+        let stage = read_matrix(context, io, base_offset + offset_mat)?;
+        insert(context, &mut lut, Some(stage))?;
+    }
+    if offset_m != 0 {
+        let stage =
+            read_set_of_curves(context, handler, io, base_offset + offset_m, input_channels)?;
+        insert(context, &mut lut, Some(stage))?;
+    }
+    if offset_c != 0 {
+        let stage = read_clut(
+            context,
+            io,
+            base_offset + offset_c,
+            input_channels,
+            output_channels,
+            size_of_tag,
+        )?;
+        insert(context, &mut lut, Some(stage))?;
+    }
+    if offset_a != 0 {
+        let stage = read_set_of_curves(
+            context,
+            handler,
+            io,
+            base_offset + offset_a,
+            output_channels,
+        )?;
+        insert(context, &mut lut, Some(stage))?;
+    }
+
+    *num_items = 1;
+    Ok(TagValue::Pipeline(lut))
+}
+
+/// Returns the stages of `lut` if their types are exactly `types`.
+fn check_and_retrieve_stages<'a>(lut: &'a Pipeline, types: &[Signature]) -> Option<Vec<&'a Stage>> {
+    let stages = lut.get_stages();
+
+    if stages.len() != types.len()
+        || stages
+            .iter()
+            .zip(types.iter())
+            .any(|(stage, r#type)| stage.get_type() != *r#type)
+    {
+        return None;
+    }
+
+    Some(stages.iter().collect())
+}
+
+/// Writes each curve of `stage` as an embedded `para` curve, or as `curv` when it can't be expressed as one.
+fn write_set_of_curves(
+    context: &mut Context,
+    handler: &TypeHandler,
+    io: &mut dyn IOHandler,
+    stage: &Stage,
+) -> Result<()> {
+    let curves = match stage.get_data() {
+        StageData::ToneCurves(data) => data.get_curves(),
+        _ => return Err(Error::from(ErrorKind::InvalidInput)),
+    };
+
+    for curve in curves {
+        let value = TagValue::Curve(curve.clone());
+
+        // If this is a table-based curve, use curve type even on V4
+        let r#type = decide_curve_type(4.0, &value);
+        io.write_type_base(r#type)?;
+        if r#type == tag_type::PARAMETRIC_CURVE {
+            curve::write_parametric_curve(context, handler, io, &value, 1)?;
+        } else {
+            curve::write_curve(context, handler, io, &value, 1)?;
+        }
+
+        io.write_alignment()?;
+    }
+
+    Ok(())
+}
+
+fn write_matrix(context: &mut Context, io: &mut dyn IOHandler, stage: &Stage) -> Result<()> {
+    let matrix = match stage.get_data() {
+        StageData::Matrix(matrix)
+            if stage.get_input_channels() == 3 && stage.get_output_channels() == 3 =>
+        {
+            matrix
+        }
+        _ => {
+            context.signal_error(
+                ErrorCode::NotSuitable,
+                "Only 3x3 matrices can be saved in LutAToB or LutBToA",
+            );
+            return Err(Error::from(ErrorKind::InvalidInput));
+        }
+    };
+
+    for value in matrix.get_matrix() {
+        io.write_s15f16(*value)?;
+    }
+
+    match matrix.get_offset() {
+        Some(offset) => {
+            for value in offset {
+                io.write_s15f16(*value)?;
+            }
+        }
+        None => {
+            for _ in 0..3 {
+                io.write_s15f16(0.0)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn write_clut(
+    context: &mut Context,
+    io: &mut dyn IOHandler,
+    precision: u8,
+    stage: &Stage,
+) -> Result<()> {
+    let clut = match stage.get_data() {
+        StageData::CLut(clut) => clut,
+        _ => return Err(Error::from(ErrorKind::InvalidInput)),
+    };
+
+    if clut.get_grid_points().iter().any(|points| *points > 255) {
+        context.signal_error(
+            ErrorCode::NotSuitable,
+            "Cannot save CLUT of more than 255 grid points per dimension",
+        );
+        return Err(Error::from(ErrorKind::InvalidInput));
+    }
+
+    let mut grid_points = [0u8; MAX_CHANNELS];
+    for (dst, src) in grid_points.iter_mut().zip(clut.get_grid_points()) {
+        *dst = *src as u8;
+    }
+    io.write(&grid_points)?;
+
+    io.write_u8(precision)?;
+    io.write(&[0u8; 3])?;
+
+    // Precision can be 1 or 2 bytes
+    let table = clut_table16(clut);
+    if precision == 1 {
+        let values = table.into_iter().map(from_16_to_8).collect::<Vec<_>>();
+        io.write(&values)?;
+    } else {
+        io.write_u16_array(&table)?;
+    }
+
+    io.write_alignment()
+}
+
+/// The stages of a lutAtoB or lutBtoA, which are written in this same order whatever the direction.
+#[derive(Default)]
+struct LutAbParts<'a> {
+    a: Option<&'a Stage>,
+    clut: Option<&'a Stage>,
+    m: Option<&'a Stage>,
+    matrix: Option<&'a Stage>,
+    b: Option<&'a Stage>,
+}
+
+/// Writes the parts of a lutAtoB or lutBtoA, and then fills the directory of offsets in.
+fn write_lut_ab(
+    context: &mut Context,
+    handler: &TypeHandler,
+    io: &mut dyn IOHandler,
+    lut: &Pipeline,
+    parts: LutAbParts,
+) -> Result<()> {
+    // Get the base for all offsets
+    let base_offset = io.tell()? - 8;
+
+    io.write_u8(lut.get_input_channels() as u8)?;
+    io.write_u8(lut.get_output_channels() as u8)?;
+    io.write_u16(0)?; // Padding
+
+    // Keep directory to be filled latter
+    let directory_pos = io.tell()?;
+    for _ in 0..5 {
+        io.write_u32(0)?;
+    }
+
+    let mut offsets = [0u32; 5];
+    let [offset_b, offset_mat, offset_m, offset_c, offset_a] = &mut offsets;
+
+    if let Some(a) = parts.a {
+        *offset_a = (io.tell()? - base_offset) as u32;
+        write_set_of_curves(context, handler, io, a)?;
+    }
+    if let Some(clut) = parts.clut {
+        *offset_c = (io.tell()? - base_offset) as u32;
+        let precision = if lut.get_save_as_8_bits() { 1 } else { 2 };
+        write_clut(context, io, precision, clut)?;
+    }
+    if let Some(m) = parts.m {
+        *offset_m = (io.tell()? - base_offset) as u32;
+        write_set_of_curves(context, handler, io, m)?;
+    }
+    if let Some(matrix) = parts.matrix {
+        *offset_mat = (io.tell()? - base_offset) as u32;
+        write_matrix(context, io, matrix)?;
+    }
+    if let Some(b) = parts.b {
+        *offset_b = (io.tell()? - base_offset) as u32;
+        write_set_of_curves(context, handler, io, b)?;
+    }
+
+    let current_pos = io.tell()?;
+    io.seek(SeekFrom::Start(directory_pos as u64))?;
+    for offset in offsets {
+        io.write_u32(offset)?;
+    }
+    io.seek(SeekFrom::Start(current_pos as u64))
+}
+
+pub(crate) fn write_lut_a_to_b(
+    context: &mut Context,
+    handler: &TypeHandler,
+    io: &mut dyn IOHandler,
+    value: &TagValue,
+    _num_items: usize,
+) -> Result<()> {
+    let lut = match value {
+        TagValue::Pipeline(lut) => lut,
+        _ => return Err(Error::from(ErrorKind::InvalidInput)),
+    };
+
+    let (curves, matrix, clut) = (
+        stage::CURVE_SET_ELEM_TYPE,
+        stage::MATRIX_ELEM_TYPE,
+        stage::C_LUT_ELEM_TYPE,
+    );
+    let parts = if lut.get_stages().is_empty() {
+        LutAbParts::default()
+    } else if let Some([b]) = check_and_retrieve_stages(lut, &[curves]).as_deref() {
+        LutAbParts {
+            b: Some(*b),
+            ..Default::default()
+        }
+    } else if let Some([m, mat, b]) =
+        check_and_retrieve_stages(lut, &[curves, matrix, curves]).as_deref()
+    {
+        LutAbParts {
+            m: Some(*m),
+            matrix: Some(*mat),
+            b: Some(*b),
+            ..Default::default()
+        }
+    } else if let Some([a, c, b]) =
+        check_and_retrieve_stages(lut, &[curves, clut, curves]).as_deref()
+    {
+        LutAbParts {
+            a: Some(*a),
+            clut: Some(*c),
+            b: Some(*b),
+            ..Default::default()
+        }
+    } else if let Some([a, c, m, mat, b]) =
+        check_and_retrieve_stages(lut, &[curves, clut, curves, matrix, curves]).as_deref()
+    {
+        LutAbParts {
+            a: Some(*a),
+            clut: Some(*c),
+            m: Some(*m),
+            matrix: Some(*mat),
+            b: Some(*b),
+        }
+    } else {
+        context.signal_error(
+            ErrorCode::NotSuitable,
+            "LUT is not suitable to be saved as LutAToB",
+        );
+        return Err(Error::from(ErrorKind::InvalidInput));
+    };
+
+    write_lut_ab(context, handler, io, lut, parts)
+}
+
+pub(crate) fn write_lut_b_to_a(
+    context: &mut Context,
+    handler: &TypeHandler,
+    io: &mut dyn IOHandler,
+    value: &TagValue,
+    _num_items: usize,
+) -> Result<()> {
+    let lut = match value {
+        TagValue::Pipeline(lut) => lut,
+        _ => return Err(Error::from(ErrorKind::InvalidInput)),
+    };
+
+    let (curves, matrix, clut) = (
+        stage::CURVE_SET_ELEM_TYPE,
+        stage::MATRIX_ELEM_TYPE,
+        stage::C_LUT_ELEM_TYPE,
+    );
+    let parts = if lut.get_stages().is_empty() {
+        LutAbParts::default()
+    } else if let Some([b]) = check_and_retrieve_stages(lut, &[curves]).as_deref() {
+        LutAbParts {
+            b: Some(*b),
+            ..Default::default()
+        }
+    } else if let Some([b, mat, m]) =
+        check_and_retrieve_stages(lut, &[curves, matrix, curves]).as_deref()
+    {
+        LutAbParts {
+            m: Some(*m),
+            matrix: Some(*mat),
+            b: Some(*b),
+            ..Default::default()
+        }
+    } else if let Some([b, c, a]) =
+        check_and_retrieve_stages(lut, &[curves, clut, curves]).as_deref()
+    {
+        LutAbParts {
+            a: Some(*a),
+            clut: Some(*c),
+            b: Some(*b),
+            ..Default::default()
+        }
+    } else if let Some([b, mat, m, c, a]) =
+        check_and_retrieve_stages(lut, &[curves, matrix, curves, clut, curves]).as_deref()
+    {
+        LutAbParts {
+            a: Some(*a),
+            clut: Some(*c),
+            m: Some(*m),
+            matrix: Some(*mat),
+            b: Some(*b),
+        }
+    } else {
+        context.signal_error(
+            ErrorCode::NotSuitable,
+            "LUT is not suitable to be saved as LutBToA",
+        );
+        return Err(Error::from(ErrorKind::InvalidInput));
+    };
+
+    write_lut_ab(context, handler, io, lut, parts)
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::{self, ErrorKind};
+
+    use crate::{
+        io::{AccessMode, FileMem, FileMemReadOnly},
+        plugins::{TagTypeReader, TagTypeWriter, TypeHandler},
+        state::Context,
+        testing::get_test_resource_path,
+        types::{signatures, Pipeline, Profile, Stage, StageData, StageLoc, TagValue, ToneCurve},
+    };
+
+    use super::*;
+
+    fn round_trip(
+        reader: TagTypeReader,
+        writer: TagTypeWriter,
+        lut: Pipeline,
+    ) -> io::Result<(Pipeline, usize)> {
+        let mut context = Context::new(None);
+        let handler = TypeHandler::new(signatures::tag_type::LUT16, reader, writer);
+
+        let mut mem = FileMem::new(Vec::new());
+        writer(
+            &mut context,
+            &handler,
+            &mut mem,
+            &TagValue::Pipeline(lut),
+            1,
+        )?;
+        let data = mem.cursor.into_inner();
+
+        let mut io = FileMemReadOnly::new(data.as_slice());
+        let mut count = 0;
+        match reader(&mut context, &handler, &mut io, &mut count, data.len())? {
+            TagValue::Pipeline(lut) => Ok((lut, data.len())),
+            value => panic!("unexpected tag value {:?}", value),
+        }
+    }
+
+    /// Builds matrix, curves, a 2 point CLUT inverting its 3 inputs, and curves.
+    fn test_lut(context: &mut Context) -> Pipeline {
+        let mut lut = Pipeline::new(context, 3, 3).unwrap();
+        let matrix = [0.5, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0];
+        let table = (0..256u32).map(|i| (i * 257) as u16).collect::<Vec<_>>();
+        let curve = ToneCurve::build_tabulated_16(context, &table).unwrap();
+        let mut clut = Vec::new();
+        for node in 0..8 {
+            for channel in 0..3 {
+                let bit = (node >> (2 - channel)) & 1;
+                clut.push(if bit == 0 { 0xffff } else { 0 });
+            }
+        }
+
+        let stages = [
+            Stage::new_matrix(context, 3, 3, &matrix, None).unwrap(),
+            Stage::new_tone_curves(
+                context,
+                3,
+                Some(&[curve.clone(), curve.clone(), curve.clone()]),
+            )
+            .unwrap(),
+            Stage::new_clut_16bit(context, 2, 3, 3, Some(&clut)).unwrap(),
+            Stage::new_tone_curves(context, 3, None).unwrap(),
+        ];
+        for stage in stages {
+            assert!(lut.insert_stage(context, StageLoc::AtEnd, stage));
+        }
+
+        lut
+    }
+
+    /// Like [`round_trip`], but with the type base in front, as lutAtoB and lutBtoA offsets count from it.
+    fn round_trip_ab(
+        reader: TagTypeReader,
+        writer: TagTypeWriter,
+        lut: Pipeline,
+    ) -> io::Result<(Pipeline, usize)> {
+        let mut context = Context::new(None);
+        let handler = TypeHandler::new(signatures::tag_type::LUTA_TO_B, reader, writer);
+
+        let mut mem = FileMem::new(Vec::new());
+        mem.write_type_base(signatures::tag_type::LUTA_TO_B)?;
+        writer(
+            &mut context,
+            &handler,
+            &mut mem,
+            &TagValue::Pipeline(lut),
+            1,
+        )?;
+        let data = mem.cursor.into_inner();
+
+        let mut io = FileMemReadOnly::new(data.as_slice());
+        io.read_type_base()?;
+        let mut count = 0;
+        match reader(&mut context, &handler, &mut io, &mut count, data.len() - 8)? {
+            TagValue::Pipeline(lut) => Ok((lut, data.len())),
+            value => panic!("unexpected tag value {:?}", value),
+        }
+    }
+
+    /// Builds A curves, a 3x2x4 grid CLUT, M curves, a matrix with offset and B curves.
+    fn test_lut_ab(context: &mut Context) -> Pipeline {
+        let mut lut = Pipeline::new(context, 3, 3).unwrap();
+        let matrix = [0.5, 0.25, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0];
+        let offset = [0.125, 0.0, 0.0];
+        let curve = ToneCurve::build_gamma(context, 2.2).unwrap();
+        let table = (0..24u32).map(|i| (i * 2849) as u16).collect::<Vec<_>>();
+        let table = table
+            .iter()
+            .flat_map(|v| [*v, 0xffff - *v, *v / 2])
+            .collect::<Vec<_>>();
+
+        let stages = [
+            Stage::new_tone_curves(context, 3, Some(&[curve.clone(), curve.clone(), curve]))
+                .unwrap(),
+            Stage::new_clut_16bit_granular(context, &[3, 2, 4], 3, 3, Some(&table)).unwrap(),
+            Stage::new_tone_curves(context, 3, None).unwrap(),
+            Stage::new_matrix(context, 3, 3, &matrix, Some(&offset)).unwrap(),
+            Stage::new_tone_curves(context, 3, None).unwrap(),
+        ];
+        for stage in stages {
+            assert!(lut.insert_stage(context, StageLoc::AtEnd, stage));
+        }
+
+        lut
+    }
+
+    fn assert_same_eval(
+        context: &mut Context,
+        expected: &Pipeline,
+        got: &Pipeline,
+        tolerance: i32,
+    ) {
+        let (mut e, mut g) = ([0u16; 3], [0u16; 3]);
+        for input in [[0u16, 0, 0], [0xffff, 0x8000, 0x1234], [0x4000, 0xffff, 0]] {
+            expected.eval_u16(context, &input, &mut e);
+            got.eval_u16(context, &input, &mut g);
+            for (a, b) in e.iter().zip(g.iter()) {
+                assert!(
+                    (*a as i32 - *b as i32).abs() <= tolerance,
+                    "{:?} != {:?}",
+                    e,
+                    g
+                );
+            }
+        }
+    }
+
+    fn stage_types(lut: &Pipeline) -> Vec<&'static str> {
+        lut.get_stages()
+            .iter()
+            .map(|stage| match stage.get_data() {
+                StageData::Matrix(_) => "matrix",
+                StageData::ToneCurves(_) => "curves",
+                StageData::CLut(_) => "clut",
+                StageData::None => "none",
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_lut16_round_trips() -> io::Result<()> {
+        let mut context = Context::new(None);
+        let lut = test_lut(&mut context);
+
+        let (read, size) = round_trip(read_lut16, write_lut16, lut.clone())?;
+
+        // Header and matrix, curves of 256 and 2 entries, and the CLUT
+        assert_eq!(size, 4 + 36 + 4 + 3 * 256 * 2 + 8 * 3 * 2 + 3 * 2 * 2);
+        assert_eq!(stage_types(&read), ["matrix", "curves", "clut", "curves"]);
+        assert!(!read.get_save_as_8_bits());
+
+        let (mut expected, mut got) = ([0u16; 3], [0u16; 3]);
+        for input in [[0u16, 0, 0], [0xffff, 0x8000, 0x1234], [0x4000, 0xffff, 0]] {
+            lut.eval_u16(&mut context, &input, &mut expected);
+            read.eval_u16(&mut context, &input, &mut got);
+            assert_eq!(got, expected);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_lut8_round_trips() -> io::Result<()> {
+        let mut context = Context::new(None);
+        let lut = test_lut(&mut context);
+
+        let (read, size) = round_trip(read_lut8, write_lut8, lut.clone())?;
+
+        assert_eq!(size, 4 + 36 + 3 * 256 + 8 * 3 + 3 * 256);
+        assert_eq!(stage_types(&read), ["matrix", "curves", "clut", "curves"]);
+        assert!(read.get_save_as_8_bits());
+
+        let (mut expected, mut got) = ([0u16; 3], [0u16; 3]);
+        lut.eval_u16(&mut context, &[0xffff, 0x8080, 0], &mut expected);
+        read.eval_u16(&mut context, &[0xffff, 0x8080, 0], &mut got);
+        for (e, g) in expected.iter().zip(got.iter()) {
+            assert!(
+                (*e as i32 - *g as i32).abs() <= 0x101,
+                "{:?} != {:?}",
+                expected,
+                got
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_lut16_without_clut_or_curves() -> io::Result<()> {
+        let mut context = Context::new(None);
+        let mut lut = Pipeline::new(&mut context, 1, 1).unwrap();
+        let curve = ToneCurve::build_gamma(&mut context, 1.0).unwrap();
+        let stage = Stage::new_tone_curves(&mut context, 1, Some(&[curve])).unwrap();
+        lut.insert_stage(&mut context, StageLoc::AtEnd, stage);
+
+        let (read, _) = round_trip(read_lut16, write_lut16, lut)?;
+
+        // The missing output curves are written as identities
+        assert_eq!(stage_types(&read), ["curves", "curves"]);
+        assert_eq!(read.get_input_channels(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_lut_writers_reject_unsuitable_pipelines() {
+        let mut context = Context::new(None);
+        let mut lut = test_lut(&mut context);
+        let stage = Stage::new_tone_curves(&mut context, 3, None).unwrap();
+        lut.insert_stage(&mut context, StageLoc::AtEnd, stage);
+
+        let err = round_trip(read_lut16, write_lut16, lut).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+
+        let mut lut = Pipeline::new(&mut context, 2, 1).unwrap();
+        let stage = Stage::new_clut_16bit_granular(&mut context, &[2, 3], 2, 1, None).unwrap();
+        lut.insert_stage(&mut context, StageLoc::AtEnd, stage);
+
+        let err = round_trip(read_lut8, write_lut8, lut).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn test_lut16_rejects_malformed_tags() {
+        let mut context = Context::new(None);
+        let handler = TypeHandler::new(signatures::tag_type::LUT16, read_lut16, write_lut16);
+        let header = |inputs: u8, outputs: u8, points: u8| {
+            let mut data = vec![inputs, outputs, points, 0];
+            for value in IDENTITY_MATRIX {
+                data.extend(((value * 65536.0) as i32).to_be_bytes());
+            }
+            // No curves, so only the CLUT is left to check
+            data.extend([0, 0, 0, 0]);
+            data
+        };
+
+        for data in [
+            header(0, 3, 2),
+            header(3, 17, 2),
+            header(3, 3, 1),
+            header(15, 15, 255),
+        ] {
+            let mut io = FileMemReadOnly::new(data.as_slice());
+            let mut count = 0;
+            let result = read_lut16(&mut context, &handler, &mut io, &mut count, data.len());
+
+            assert_eq!(result.unwrap_err().kind(), ErrorKind::InvalidData);
+        }
+    }
+
+    #[test]
+    fn test_read_lut16_from_profile() -> io::Result<()> {
+        let mut context = Context::new(None);
+        let mut profile = Profile::open_from_file_thr(
+            &mut context,
+            get_test_resource_path("sRGB_v4_ICC_preference.icc"),
+            AccessMode::Read,
+        )?;
+        let mut lut = Pipeline::new(&mut context, 3, 3).unwrap();
+        let stage = Stage::new_tone_curves(&mut context, 3, None).unwrap();
+        lut.insert_stage(&mut context, StageLoc::AtEnd, stage);
+
+        // The test profile only has V4 LUTs, so write a V2 one into a copy
+        profile.set_version(2.1);
+        profile.write_tag_thr(
+            &mut context,
+            signatures::tag::A_TO_B0,
+            TagValue::Pipeline(lut),
+        )?;
+        let saved = profile.save_to_mem_thr(&mut context)?;
+        let mut reopened = Profile::open_from_mem_thr(&mut context, &saved)?;
+
+        assert_eq!(
+            &reopened.read_raw_tag(signatures::tag::A_TO_B0)?[..4],
+            b"mft2"
+        );
+        match reopened.read_tag_thr(&mut context, signatures::tag::A_TO_B0)? {
+            TagValue::Pipeline(lut) => assert_eq!(stage_types(lut), ["curves", "curves"]),
+            value => panic!("unexpected tag value {:?}", value),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_tag_saves_8_bit_luts_as_lut8_in_v4() -> io::Result<()> {
+        let mut context = Context::new(None);
+        let mut profile = Profile::new();
+        profile.set_version(4.3);
+
+        let mut lut = test_lut(&mut context);
+        lut.set_save_as_8_bits(true);
+        profile.write_tag_thr(
+            &mut context,
+            signatures::tag::A_TO_B0,
+            TagValue::Pipeline(lut),
+        )?;
+        let saved = profile.save_to_mem_thr(&mut context)?;
+        let mut reopened = Profile::open_from_mem_thr(&mut context, &saved)?;
+
+        assert_eq!(
+            &reopened.read_raw_tag_thr(&mut context, signatures::tag::A_TO_B0)?[..4],
+            b"mft1"
+        );
+        match reopened.read_tag_thr(&mut context, signatures::tag::A_TO_B0)? {
+            TagValue::Pipeline(lut) => assert!(lut.get_save_as_8_bits()),
+            value => panic!("unexpected tag value {:?}", value),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_lut_tag_converts_v2_lab() -> io::Result<()> {
+        let mut context = Context::new(None);
+        let mut profile = Profile::new();
+        profile.set_version(2.1);
+        profile.set_color_space(signatures::color_space::RGB);
+        profile.set_pcs(signatures::color_space::LAB);
+
+        let mut lut = Pipeline::new(&mut context, 3, 3).unwrap();
+        let stage = Stage::new_tone_curves(&mut context, 3, None).unwrap();
+        lut.insert_stage(&mut context, StageLoc::AtEnd, stage);
+        profile.write_lut_tag_thr(&mut context, signatures::tag::A_TO_B0, &lut)?;
+        profile.write_lut_tag_thr(&mut context, signatures::tag::B_TO_A0, &lut)?;
+        let saved = profile.save_to_mem_thr(&mut context)?;
+        let mut reopened = Profile::open_from_mem_thr(&mut context, &saved)?;
+
+        let a2b = reopened.read_lut_tag_thr(&mut context, signatures::tag::A_TO_B0)?;
+        let b2a = reopened.read_lut_tag_thr(&mut context, signatures::tag::B_TO_A0)?;
+
+        assert_eq!(stage_types(&a2b), ["curves", "curves", "matrix"]);
+        assert_eq!(
+            a2b.get_stages()[2].get_implements(),
+            signatures::stage::LAB_V2_TO_V4
+        );
+        assert_eq!(stage_types(&b2a), ["matrix", "curves", "curves"]);
+        assert_eq!(
+            b2a.get_stages()[0].get_implements(),
+            signatures::stage::LAB_V4_TO_V2
+        );
+
+        // The identity was stored as L* = 100 in the V2 encoding, and comes out as L* = 100 in the V4 one
+        let mut out = [0u16; 3];
+        match reopened.read_tag_thr(&mut context, signatures::tag::A_TO_B0)? {
+            TagValue::Pipeline(stored) => {
+                stored.eval_u16(&mut context, &[0xffff, 0x8080, 0x8080], &mut out);
+                assert_eq!(out, [0xff00, 0x8000, 0x8000]);
+            }
+            value => panic!("unexpected tag value {:?}", value),
+        }
+        a2b.eval_u16(&mut context, &[0xffff, 0x8080, 0x8080], &mut out);
+        assert_eq!(out, [0xffff, 0x8080, 0x8080]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_lut_tag_round_trips_v2_lab() -> io::Result<()> {
+        let mut context = Context::new(None);
+        let mut profile = Profile::new();
+        profile.set_version(2.1);
+        profile.set_color_space(signatures::color_space::LAB);
+        profile.set_pcs(signatures::color_space::LAB);
+
+        let table = (0..27 * 3)
+            .map(|i| ((i * 797) % 65536) as u16)
+            .collect::<Vec<_>>();
+        let mut lut = Pipeline::new(&mut context, 3, 3).unwrap();
+        let stage = Stage::new_clut_16bit(&mut context, 3, 3, 3, Some(&table));
+        insert(&mut context, &mut lut, stage)?;
+        profile.write_tag_thr(
+            &mut context,
+            signatures::tag::A_TO_B0,
+            TagValue::Pipeline(lut),
+        )?;
+        let saved = profile.save_to_mem_thr(&mut context)?;
+
+        let mut first = Profile::open_from_mem_thr(&mut context, &saved)?;
+        let read = first.read_lut_tag_thr(&mut context, signatures::tag::A_TO_B0)?;
+        assert_eq!(
+            read.get_stages()[0].get_implements(),
+            signatures::stage::LAB_V4_TO_V2
+        );
+
+        // What was read must be writable as is, and read back the same
+        first.write_lut_tag_thr(&mut context, signatures::tag::A_TO_B0, &read)?;
+        let resaved = first.save_to_mem_thr(&mut context)?;
+        let mut second = Profile::open_from_mem_thr(&mut context, &resaved)?;
+        let reread = second.read_lut_tag_thr(&mut context, signatures::tag::A_TO_B0)?;
+
+        assert_eq!(stage_types(&reread), stage_types(&read));
+        assert_eq!(
+            second.read_raw_tag_thr(&mut context, signatures::tag::A_TO_B0)?,
+            first.read_raw_tag_thr(&mut context, signatures::tag::A_TO_B0)?
+        );
+        for input in [
+            [0, 0x8080, 0x8080],
+            [0xffff, 0, 0xffff],
+            [0x1234, 0xabcd, 0x5678],
+        ] {
+            let (mut expected, mut out) = ([0u16; 3], [0u16; 3]);
+            read.eval_u16(&mut context, &input, &mut expected);
+            reread.eval_u16(&mut context, &input, &mut out);
+            assert_eq!(out, expected);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_tag_round_trips_v2_lab_lut16() -> io::Result<()> {
+        let mut context = Context::new(None);
+        let mut profile = Profile::new();
+        profile.set_version(2.1);
+        profile.set_color_space(signatures::color_space::LAB);
+        profile.set_pcs(signatures::color_space::LAB);
+
+        let table = (0..27 * 3)
+            .map(|i| ((i * 797) % 65536) as u16)
+            .collect::<Vec<_>>();
+        let mut lut = Pipeline::new(&mut context, 3, 3).unwrap();
+        let stage = Stage::new_clut_16bit(&mut context, 3, 3, 3, Some(&table));
+        insert(&mut context, &mut lut, stage)?;
+        profile.write_tag_thr(
+            &mut context,
+            signatures::tag::A_TO_B0,
+            TagValue::Pipeline(lut),
+        )?;
+        let saved = profile.save_to_mem_thr(&mut context)?;
+
+        // read_tag() and write_tag() keep the stored V2 encoding, so the values must not change
+        let mut first = Profile::open_from_mem_thr(&mut context, &saved)?;
+        let read = match first.read_tag_thr(&mut context, signatures::tag::A_TO_B0)? {
+            TagValue::Pipeline(lut) => lut.clone(),
+            value => panic!("unexpected tag value {:?}", value),
+        };
+        first.write_tag_thr(
+            &mut context,
+            signatures::tag::A_TO_B0,
+            TagValue::Pipeline(read.clone()),
+        )?;
+        let resaved = first.save_to_mem_thr(&mut context)?;
+        let mut second = Profile::open_from_mem_thr(&mut context, &resaved)?;
+
+        assert_eq!(
+            second.read_raw_tag_thr(&mut context, signatures::tag::A_TO_B0)?,
+            Profile::open_from_mem_thr(&mut context, &saved)?
+                .read_raw_tag_thr(&mut context, signatures::tag::A_TO_B0)?
+        );
+        let reread = match second.read_tag_thr(&mut context, signatures::tag::A_TO_B0)? {
+            TagValue::Pipeline(lut) => lut,
+            value => panic!("unexpected tag value {:?}", value),
+        };
+        for input in [
+            [0, 0x8080, 0x8080],
+            [0xff00, 0, 0xffff],
+            [0x1234, 0xabcd, 0x5678],
+        ] {
+            let (mut expected, mut out) = ([0u16; 3], [0u16; 3]);
+            read.eval_u16(&mut context, &input, &mut expected);
+            reread.eval_u16(&mut context, &input, &mut out);
+            assert_eq!(out, expected);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_lut_tag_keeps_xyz_luts() -> io::Result<()> {
+        let mut context = Context::new(None);
+        let mut profile = Profile::new();
+        profile.set_version(2.1);
+        profile.set_pcs(signatures::color_space::XYZ);
+
+        let mut lut = Pipeline::new(&mut context, 3, 3).unwrap();
+        let stage = Stage::new_tone_curves(&mut context, 3, None).unwrap();
+        lut.insert_stage(&mut context, StageLoc::AtEnd, stage);
+        profile.write_tag_thr(
+            &mut context,
+            signatures::tag::A_TO_B0,
+            TagValue::Pipeline(lut),
+        )?;
+
+        let lut = profile.read_lut_tag_thr(&mut context, signatures::tag::A_TO_B0)?;
+
+        assert_eq!(stage_types(&lut), ["curves"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_lut_a_to_b_round_trips() -> io::Result<()> {
+        let mut context = Context::new(None);
+        let lut = test_lut_ab(&mut context);
+
+        let (read, _) = round_trip_ab(read_lut_a_to_b, write_lut_a_to_b, lut.clone())?;
+
+        assert_eq!(
+            stage_types(&read),
+            ["curves", "clut", "curves", "matrix", "curves"]
+        );
+        match read.get_stages()[1].get_data() {
+            StageData::CLut(clut) => assert_eq!(clut.get_grid_points()[..3], [3, 2, 4]),
+            data => panic!("unexpected stage data {:?}", data),
+        }
+        match read.get_stages()[3].get_data() {
+            StageData::Matrix(matrix) => {
+                assert_eq!(matrix.get_offset(), Some(&[0.125, 0.0, 0.0][..]))
+            }
+            data => panic!("unexpected stage data {:?}", data),
+        }
+        assert_same_eval(&mut context, &lut, &read, 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_lut_a_to_b_round_trips_8_bit_clut() -> io::Result<()> {
+        let mut context = Context::new(None);
+        let mut lut = test_lut_ab(&mut context);
+        let (_, size16) = round_trip_ab(read_lut_a_to_b, write_lut_a_to_b, lut.clone())?;
+        lut.set_save_as_8_bits(true);
+
+        let (read, size8) = round_trip_ab(read_lut_a_to_b, write_lut_a_to_b, lut.clone())?;
+
+        assert_eq!(size16 - size8, 3 * 2 * 4 * 3);
+        assert_same_eval(&mut context, &lut, &read, 0x101);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_clut_rejects_oversized_grids() {
+        let mut context = Context::new(None);
+
+        // 255^8 entries still fit in a usize, but not once multiplied by the 2 byte precision
+        let mut data = [0u8; 20];
+        data[..8].fill(255);
+        data[16] = 2;
+
+        let mut io = FileMemReadOnly::new(data.as_slice());
+        let err = read_clut(&mut context, &mut io, 0, 8, 1, data.len()).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_lut_b_to_a_round_trips() -> io::Result<()> {
+        let mut context = Context::new(None);
+        let mut lut = Pipeline::new(&mut context, 3, 3).unwrap();
+        let matrix = [0.5, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0];
+        let curve = ToneCurve::build_gamma(&mut context, 1.8).unwrap();
+        let stages = [
+            Stage::new_tone_curves(
+                &mut context,
+                3,
+                Some(&[curve.clone(), curve.clone(), curve]),
+            )
+            .unwrap(),
+            Stage::new_matrix(&mut context, 3, 3, &matrix, None).unwrap(),
+            Stage::new_tone_curves(&mut context, 3, None).unwrap(),
+        ];
+        for stage in stages {
+            lut.insert_stage(&mut context, StageLoc::AtEnd, stage);
+        }
+
+        let (read, _) = round_trip_ab(read_lut_b_to_a, write_lut_b_to_a, lut.clone())?;
+
+        assert_eq!(stage_types(&read), ["curves", "matrix", "curves"]);
+        assert_same_eval(&mut context, &lut, &read, 1);
+
+        // An empty pipeline is written as a lutBtoA without any parts
+        let (read, _) = round_trip_ab(
+            read_lut_b_to_a,
+            write_lut_b_to_a,
+            Pipeline::new(&mut context, 3, 3).unwrap(),
+        )?;
+        assert!(read.get_stages().is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_lut_ab_writers_reject_unsuitable_pipelines() {
+        let mut context = Context::new(None);
+
+        // lutAtoB ends with the matrix and the B curves, which lutBtoA can't
+        let lut = test_lut_ab(&mut context);
+        let err = round_trip_ab(read_lut_b_to_a, write_lut_b_to_a, lut).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+
+        let lut = test_lut(&mut context);
+        let err = round_trip_ab(read_lut_a_to_b, write_lut_a_to_b, lut).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn test_read_lut_ab_from_profile() -> io::Result<()> {
+        let mut context = Context::new(None);
+        let mut profile = Profile::open_from_file_thr(
+            &mut context,
+            get_test_resource_path("sRGB_v4_ICC_preference.icc"),
+            AccessMode::Read,
+        )?;
+
+        let a2b = match profile.read_tag_thr(&mut context, signatures::tag::A_TO_B0)? {
+            TagValue::Pipeline(lut) => lut.clone(),
+            value => panic!("unexpected tag value {:?}", value),
+        };
+        let b2a = match profile.read_tag_thr(&mut context, signatures::tag::B_TO_A0)? {
+            TagValue::Pipeline(lut) => lut.clone(),
+            value => panic!("unexpected tag value {:?}", value),
+        };
+
+        assert_eq!(
+            stage_types(&a2b),
+            ["curves", "clut", "curves", "matrix", "curves"]
+        );
+        assert_eq!(
+            stage_types(&b2a),
+            ["curves", "matrix", "curves", "clut", "curves"]
+        );
+        assert_eq!(a2b.get_input_channels(), 3);
+        assert_eq!(b2a.get_output_channels(), 3);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_v4_profile_saves_lut_as_a_to_b() -> io::Result<()> {
+        let mut context = Context::new(None);
+        let mut profile = Profile::new();
+        profile.set_version(4.3);
+        let lut = test_lut_ab(&mut context);
+        profile.write_tag_thr(
+            &mut context,
+            signatures::tag::A_TO_B0,
+            TagValue::Pipeline(lut.clone()),
+        )?;
+        let saved = profile.save_to_mem_thr(&mut context)?;
+        let mut reopened = Profile::open_from_mem_thr(&mut context, &saved)?;
+
+        assert_eq!(
+            &reopened.read_raw_tag(signatures::tag::A_TO_B0)?[..4],
+            b"mAB "
+        );
+        let read = reopened.read_lut_tag_thr(&mut context, signatures::tag::A_TO_B0)?;
+        assert_same_eval(&mut context, &lut, &read, 1);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,219 @@
+//! `meas` (measurement) and `view` (viewingConditions) tag types, ICC.1:2022 clauses 10.14 and 10.30.
+
+use std::io::{Error, ErrorKind, Result};
+
+use crate::{
+    io::IOHandler,
+    plugins::TypeHandler,
+    state::Context,
+    types::{Measurement, TagValue, ViewingConditions},
+};
+
+pub(crate) fn read_measurement(
+    _context: &mut Context,
+    _handler: &TypeHandler,
+    io: &mut dyn IOHandler,
+    num_items: &mut usize,
+    _size_of_tag: usize,
+) -> Result<TagValue> {
+    let observer = io.read_u32()?.into();
+    let backing = io.read_xyz()?;
+    let geometry = io.read_u32()?.into();
+    // The flare is a u16Fixed16Number
+    let flare = io.read_u32()? as f64 / 65536.0;
+    let illuminant = io.read_u32()?.into();
+
+    *num_items = 1;
+    Ok(TagValue::Measurement(Measurement {
+        observer,
+        backing,
+        geometry,
+        flare,
+        illuminant,
+    }))
+}
+
+pub(crate) fn write_measurement(
+    _context: &mut Context,
+    _handler: &TypeHandler,
+    io: &mut dyn IOHandler,
+    value: &TagValue,
+    _num_items: usize,
+) -> Result<()> {
+    let measurement = match value {
+        TagValue::Measurement(measurement) => measurement,
+        _ => return Err(Error::from(ErrorKind::InvalidInput)),
+    };
+
+    io.write_u32(measurement.observer.into())?;
+    io.write_xyz(measurement.backing)?;
+    io.write_u32(measurement.geometry.into())?;
+    io.write_u32((measurement.flare * 65536.0 + 0.5).floor() as u32)?;
+    io.write_u32(measurement.illuminant.into())
+}
+
+pub(crate) fn read_viewing_conditions(
+    _context: &mut Context,
+    _handler: &TypeHandler,
+    io: &mut dyn IOHandler,
+    num_items: &mut usize,
+    _size_of_tag: usize,
+) -> Result<TagValue> {
+    let illuminant = io.read_xyz()?;
+    let surround = io.read_xyz()?;
+    let illuminant_type = io.read_u32()?.into();
+
+    *num_items = 1;
+    Ok(TagValue::ViewingConditions(ViewingConditions {
+        illuminant,
+        surround,
+        illuminant_type,
+    }))
+}
+
+pub(crate) fn write_viewing_conditions(
+    _context: &mut Context,
+    _handler: &TypeHandler,
+    io: &mut dyn IOHandler,
+    value: &TagValue,
+    _num_items: usize,
+) -> Result<()> {
+    let conditions = match value {
+        TagValue::ViewingConditions(conditions) => conditions,
+        _ => return Err(Error::from(ErrorKind::InvalidInput)),
+    };
+
+    io.write_xyz(conditions.illuminant)?;
+    io.write_xyz(conditions.surround)?;
+    io.write_u32(conditions.illuminant_type.into())
+}
+
+#[cfg(test)]
+mod test {
+    use std::io;
+
+    use crate::{
+        io::FileMemReadOnly,
+        plugins::TypeHandler,
+        state::Context,
+        types::{
+            signatures, tag_types::round_trip, Measurement, MeasurementGeometry, Profile,
+            StandardIlluminant, StandardObserver, TagValue, ViewingConditions, CIEXYZ,
+        },
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_measurement_round_trips() -> io::Result<()> {
+        let measurement = Measurement {
+            observer: StandardObserver::Cie1931,
+            backing: CIEXYZ {
+                X: 0.5,
+                Y: 0.25,
+                Z: 0.125,
+            },
+            geometry: MeasurementGeometry::ZeroDiffuse,
+            flare: 0.01,
+            illuminant: StandardIlluminant::Other(42),
+        };
+
+        let (value, _, data) = round_trip(
+            &TypeHandler::new(
+                signatures::tag_type::MEASUREMENT,
+                read_measurement,
+                write_measurement,
+            ),
+            &TagValue::Measurement(measurement),
+        )?;
+
+        assert_eq!(data.len(), 8 + 28);
+        match value {
+            TagValue::Measurement(read) => {
+                assert_eq!(read.observer, measurement.observer);
+                assert_eq!(read.backing, measurement.backing);
+                assert_eq!(read.geometry, measurement.geometry);
+                assert!((read.flare - measurement.flare).abs() < 1.0 / 65536.0);
+                assert_eq!(read.illuminant, StandardIlluminant::Other(42));
+            }
+            value => panic!("unexpected tag value {:?}", value),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_measurement_flare_is_unsigned() -> io::Result<()> {
+        let mut context = Context::new(None);
+        let handler = TypeHandler::new(
+            signatures::tag_type::MEASUREMENT,
+            read_measurement,
+            write_measurement,
+        );
+
+        let mut data = [0u8; 28];
+        data[20..24].copy_from_slice(&0x8000_8000u32.to_be_bytes());
+
+        let mut io = FileMemReadOnly::new(data.as_slice());
+        let mut count = 0;
+        match read_measurement(&mut context, &handler, &mut io, &mut count, data.len())? {
+            TagValue::Measurement(read) => assert_eq!(read.flare, 32768.5),
+            value => panic!("unexpected tag value {:?}", value),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_viewing_conditions_round_trip_through_profile() -> io::Result<()> {
+        let mut context = Context::new(None);
+        let conditions = ViewingConditions {
+            illuminant: CIEXYZ {
+                X: 19.6445,
+                Y: 20.3718,
+                Z: 16.8089,
+            },
+            surround: CIEXYZ {
+                X: 3.9289,
+                Y: 4.0744,
+                Z: 3.3618,
+            },
+            illuminant_type: StandardIlluminant::D50,
+        };
+        let mut profile = Profile::new();
+        profile.write_tag_thr(
+            &mut context,
+            signatures::tag::VIEWING_CONDITIONS,
+            TagValue::ViewingConditions(conditions),
+        )?;
+        let saved = profile.save_to_mem_thr(&mut context)?;
+        let mut reopened = Profile::open_from_mem_thr(&mut context, &saved)?;
+
+        assert_eq!(
+            reopened
+                .read_raw_tag(signatures::tag::VIEWING_CONDITIONS)?
+                .len(),
+            8 + 28
+        );
+        match reopened.read_tag_thr(&mut context, signatures::tag::VIEWING_CONDITIONS)? {
+            TagValue::ViewingConditions(read) => {
+                assert_eq!(read.illuminant_type, StandardIlluminant::D50);
+                assert!((read.illuminant.Y - conditions.illuminant.Y).abs() < 1.0 / 65536.0);
+                assert!((read.surround.Z - conditions.surround.Z).abs() < 1.0 / 65536.0);
+            }
+            value => panic!("unexpected tag value {:?}", value),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_enums_keep_unknown_codes() {
+        for code in [0, 1, 2, 9, u32::MAX] {
+            assert_eq!(u32::from(StandardObserver::from(code)), code);
+            assert_eq!(u32::from(MeasurementGeometry::from(code)), code);
+            assert_eq!(u32::from(StandardIlluminant::from(code)), code);
+        }
+        assert_eq!(StandardIlluminant::from(7), StandardIlluminant::E);
+    }
+}
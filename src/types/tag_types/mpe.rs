@@ -0,0 +1,912 @@
+//! `mpet` (multiProcessElements) tag type and its `cvst`, `matf`, `clut`, `bACS` and `eACS` elements, ICC.1:2022
+//! clauses 10.15 and 11.
+//!
+//! Elements are handled by [`TypeHandler`]s too, registered in the multi process element plugin chunk or among the
+//! built-in ones. An element reader returns a [`TagValue::Pipeline`] holding the stages it read, which is empty for
+//! the elements that are ignored, and an element writer receives a [`TagValue::Pipeline`] holding the single stage to
+//! write.
+
+use std::io::{Error, ErrorKind, Result};
+
+use crate::{
+    io::IOHandler,
+    plugins::{
+        get_mpe_type_handler, TypeHandler, MAX_INPUT_DIMENTIONS, MAX_NODES_IN_CURVE, MINUS_INF,
+        PLUS_INF,
+    },
+    state::{Context, ErrorCode},
+    types::{
+        signatures::curve_segment, CurveSegment, Pipeline, Stage, StageData, StageLoc, Tab,
+        TagValue, ToneCurve, MAX_CHANNELS,
+    },
+};
+
+use super::{read_position_table, write_position_table};
+
+/// The number of parameters of each `parf` function type, which are the parametric curves 6, 7 and 8.
+const PARAMS_BY_TYPE: [usize; 3] = [4, 5, 5];
+
+/// Reads the input and output channels of an element.
+fn read_element_channels(io: &mut dyn IOHandler) -> Result<(usize, usize)> {
+    let input_channels = io.read_u16()? as usize;
+    let output_channels = io.read_u16()? as usize;
+
+    if input_channels == 0
+        || input_channels >= MAX_CHANNELS
+        || output_channels == 0
+        || output_channels >= MAX_CHANNELS
+    {
+        return Err(Error::from(ErrorKind::InvalidData));
+    }
+
+    Ok((input_channels, output_channels))
+}
+
+/// Wraps the stage read by an element reader into the pipeline element readers return.
+fn element_value(context: &mut Context, stage: Option<Stage>) -> Result<TagValue> {
+    let stage = stage.ok_or_else(|| Error::from(ErrorKind::InvalidData))?;
+    let mut lut = Pipeline::new(
+        context,
+        stage.get_input_channels(),
+        stage.get_output_channels(),
+    )
+    .ok_or_else(|| Error::from(ErrorKind::InvalidData))?;
+
+    if !lut.insert_stage(context, StageLoc::AtEnd, stage) {
+        return Err(Error::from(ErrorKind::InvalidData));
+    }
+
+    Ok(TagValue::Pipeline(lut))
+}
+
+/// Returns the single stage an element writer is given.
+fn element_stage(value: &TagValue) -> Result<&Stage> {
+    match value {
+        TagValue::Pipeline(lut) if lut.get_stages().len() == 1 => Ok(&lut.get_stages()[0]),
+        _ => Err(Error::from(ErrorKind::InvalidInput)),
+    }
+}
+
+/// Reads a `curf` segmented curve.
+fn read_segmented_curve(context: &mut Context, io: &mut dyn IOHandler) -> Result<ToneCurve> {
+    let signature = io.read_type_base()?;
+    if signature != curve_segment::SEGMENTED {
+        context.signal_error(
+            ErrorCode::UnknownExtension,
+            format!("Unknown curve type '{:?}'", signature),
+        );
+        return Err(Error::from(ErrorKind::InvalidData));
+    }
+
+    let count = io.read_u16()? as usize;
+    io.read_u16()?; // Padding
+
+    if count == 0 {
+        return Err(Error::from(ErrorKind::InvalidData));
+    }
+
+    let mut segments = Vec::with_capacity(count);
+    let mut x0 = MINUS_INF as f32;
+    for _ in 1..count {
+        let x1 = io.read_f32()?;
+        segments.push(CurveSegment {
+            x0,
+            x1,
+            r#type: 0,
+            params: [0.0; 10],
+            sampled_points: Vec::new(),
+        });
+        x0 = x1;
+    }
+    segments.push(CurveSegment {
+        x0,
+        x1: PLUS_INF as f32,
+        r#type: 0,
+        params: [0.0; 10],
+        sampled_points: Vec::new(),
+    });
+
+    for i in 0..count {
+        let signature = io.read_type_base()?;
+
+        match signature {
+            curve_segment::FORMULA => {
+                let r#type = io.read_u16()? as usize;
+                io.read_u16()?; // Padding
+
+                let params = match PARAMS_BY_TYPE.get(r#type) {
+                    Some(params) => *params,
+                    None => {
+                        context.signal_error(
+                            ErrorCode::UnknownExtension,
+                            format!("Unknown formula type {}", r#type),
+                        );
+                        return Err(Error::from(ErrorKind::InvalidData));
+                    }
+                };
+
+                segments[i].r#type = r#type as i32 + 6;
+                for j in 0..params {
+                    segments[i].params[j] = io.read_f32()? as f64;
+                }
+            }
+            curve_segment::SAMPLED => {
+                let points = io.read_u32()? as usize;
+                if points > MAX_NODES_IN_CURVE {
+                    return Err(Error::from(ErrorKind::InvalidData));
+                }
+
+                // The first point is implicit, it is the value of the previous segment at the breakpoint
+                let first = if i == 0 {
+                    0.0
+                } else {
+                    ToneCurve::build_segmented(context, &segments[..i])
+                        .ok_or_else(|| Error::from(ErrorKind::InvalidData))?
+                        .eval_f32(segments[i].x0)
+                };
+
+                let mut sampled_points = Vec::with_capacity(points + 1);
+                sampled_points.push(first);
+                for _ in 0..points {
+                    sampled_points.push(io.read_f32()?);
+                }
+                segments[i].sampled_points = sampled_points;
+            }
+            _ => {
+                context.signal_error(
+                    ErrorCode::UnknownExtension,
+                    format!("Unknown curve element type '{:?}' found.", signature),
+                );
+                return Err(Error::from(ErrorKind::InvalidData));
+            }
+        }
+    }
+
+    ToneCurve::build_segmented(context, &segments)
+        .ok_or_else(|| Error::from(ErrorKind::InvalidData))
+}
+
+/// Writes `curve` as a `curf` segmented curve. Only sampled segments and parametric segments of types 6 to 8 can be
+/// written.
+fn write_segmented_curve(
+    context: &mut Context,
+    io: &mut dyn IOHandler,
+    curve: &ToneCurve,
+) -> Result<()> {
+    let segments = curve.get_segments();
+
+    if segments.is_empty()
+        || segments
+            .iter()
+            .any(|segment| segment.r#type != 0 && !(6..=8).contains(&segment.r#type))
+    {
+        context.signal_error(
+            ErrorCode::NotSuitable,
+            "Only sampled segments and formula segments of types 6 to 8 can be saved in segmented curves",
+        );
+        return Err(Error::from(ErrorKind::InvalidInput));
+    }
+
+    io.write_type_base(curve_segment::SEGMENTED)?;
+    io.write_u16(segments.len() as u16)?;
+    io.write_u16(0)?; // Padding
+
+    // Write the break-points
+    for segment in &segments[..segments.len() - 1] {
+        io.write_f32(segment.x1)?;
+    }
+
+    for segment in segments {
+        if segment.r#type == 0 {
+            io.write_type_base(curve_segment::SAMPLED)?;
+
+            // The first point is implicit in the previous segment
+            let points = segment.sampled_points.get(1..).unwrap_or_default();
+            io.write_u32(points.len() as u32)?;
+            for point in points {
+                io.write_f32(*point)?;
+            }
+        } else {
+            let r#type = segment.r#type as usize - 6;
+
+            io.write_type_base(curve_segment::FORMULA)?;
+            io.write_u16(r#type as u16)?;
+            io.write_u16(0)?; // Padding
+
+            for param in &segment.params[..PARAMS_BY_TYPE[r#type]] {
+                io.write_f32(*param as f32)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+pub(crate) fn read_mpe_curve(
+    context: &mut Context,
+    _handler: &TypeHandler,
+    io: &mut dyn IOHandler,
+    num_items: &mut usize,
+    size_of_tag: usize,
+) -> Result<TagValue> {
+    let base_offset = io.tell()? - 8;
+
+    let (input_channels, output_channels) = read_element_channels(io)?;
+    if input_channels != output_channels {
+        return Err(Error::from(ErrorKind::InvalidData));
+    }
+
+    let mut curves = Vec::with_capacity(input_channels);
+    read_position_table(io, input_channels, base_offset, size_of_tag, |io, _| {
+        curves.push(read_segmented_curve(context, io)?);
+        Ok(())
+    })?;
+
+    *num_items = 1;
+    let stage = Stage::new_tone_curves(context, input_channels as u32, Some(&curves));
+    element_value(context, stage)
+}
+
+pub(crate) fn write_mpe_curve(
+    context: &mut Context,
+    _handler: &TypeHandler,
+    io: &mut dyn IOHandler,
+    value: &TagValue,
+    _num_items: usize,
+) -> Result<()> {
+    let stage = element_stage(value)?;
+    let curves = match stage.get_data() {
+        StageData::ToneCurves(data) => data.get_curves(),
+        _ => return Err(Error::from(ErrorKind::InvalidInput)),
+    };
+
+    let base_offset = io.tell()? - 8;
+
+    io.write_u16(stage.get_input_channels() as u16)?;
+    io.write_u16(stage.get_output_channels() as u16)?;
+
+    write_position_table(io, curves.len(), base_offset, |io, i| {
+        write_segmented_curve(context, io, &curves[i])
+    })
+}
+
+pub(crate) fn read_mpe_matrix(
+    context: &mut Context,
+    _handler: &TypeHandler,
+    io: &mut dyn IOHandler,
+    num_items: &mut usize,
+    size_of_tag: usize,
+) -> Result<TagValue> {
+    let (input_channels, output_channels) = read_element_channels(io)?;
+
+    let elements = input_channels * output_channels;
+    if (elements + output_channels) * 4 > size_of_tag {
+        return Err(Error::from(ErrorKind::InvalidData));
+    }
+
+    let mut matrix = Vec::with_capacity(elements);
+    for _ in 0..elements {
+        matrix.push(io.read_f32()? as f64);
+    }
+
+    let mut offsets = Vec::with_capacity(output_channels);
+    for _ in 0..output_channels {
+        offsets.push(io.read_f32()? as f64);
+    }
+
+    *num_items = 1;
+    let stage = Stage::new_matrix(
+        context,
+        output_channels as u32,
+        input_channels as u32,
+        &matrix,
+        Some(&offsets),
+    );
+    element_value(context, stage)
+}
+
+pub(crate) fn write_mpe_matrix(
+    _context: &mut Context,
+    _handler: &TypeHandler,
+    io: &mut dyn IOHandler,
+    value: &TagValue,
+    _num_items: usize,
+) -> Result<()> {
+    let stage = element_stage(value)?;
+    let matrix = match stage.get_data() {
+        StageData::Matrix(matrix) => matrix,
+        _ => return Err(Error::from(ErrorKind::InvalidInput)),
+    };
+
+    io.write_u16(stage.get_input_channels() as u16)?;
+    io.write_u16(stage.get_output_channels() as u16)?;
+
+    for value in matrix.get_matrix() {
+        io.write_f32(*value as f32)?;
+    }
+
+    match matrix.get_offset() {
+        Some(offset) => {
+            for value in offset {
+                io.write_f32(*value as f32)?;
+            }
+        }
+        None => {
+            for _ in 0..stage.get_output_channels() {
+                io.write_f32(0.0)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+pub(crate) fn read_mpe_clut(
+    context: &mut Context,
+    _handler: &TypeHandler,
+    io: &mut dyn IOHandler,
+    num_items: &mut usize,
+    size_of_tag: usize,
+) -> Result<TagValue> {
+    let (input_channels, output_channels) = read_element_channels(io)?;
+    if input_channels > MAX_INPUT_DIMENTIONS {
+        return Err(Error::from(ErrorKind::InvalidData));
+    }
+
+    let mut grid_points8 = [0u8; MAX_CHANNELS];
+    io.read(&mut grid_points8)?;
+
+    // Impossible value, 0 for no CLUT and then 2 at least
+    if grid_points8[..input_channels].contains(&1) {
+        return Err(Error::from(ErrorKind::InvalidData));
+    }
+    let grid_points = grid_points8.map(|points| points as u32);
+
+    let entries = grid_points[..input_channels]
+        .iter()
+        .try_fold(output_channels, |size, points| {
+            size.checked_mul(*points as usize)
+        })
+        .filter(|size| {
+            size.checked_mul(4)
+                .is_some_and(|bytes| bytes <= size_of_tag)
+        })
+        .ok_or_else(|| Error::from(ErrorKind::InvalidData))?;
+
+    let mut table = Vec::with_capacity(entries);
+    for _ in 0..entries {
+        table.push(io.read_f32()?);
+    }
+
+    *num_items = 1;
+    let stage = Stage::new_clut_float_granular(
+        context,
+        &grid_points,
+        input_channels as u32,
+        output_channels as u32,
+        Some(&table),
+    );
+    element_value(context, stage)
+}
+
+pub(crate) fn write_mpe_clut(
+    context: &mut Context,
+    _handler: &TypeHandler,
+    io: &mut dyn IOHandler,
+    value: &TagValue,
+    _num_items: usize,
+) -> Result<()> {
+    let stage = element_stage(value)?;
+    let clut = match stage.get_data() {
+        StageData::CLut(clut) => clut,
+        _ => return Err(Error::from(ErrorKind::InvalidInput)),
+    };
+
+    // Only floats are supported in MPE
+    let table = match clut.get_table() {
+        Tab::F32(table) => table,
+        Tab::U16(_) => {
+            context.signal_error(
+                ErrorCode::NotSuitable,
+                "Only floating point CLUTs can be saved as multi process elements",
+            );
+            return Err(Error::from(ErrorKind::InvalidInput));
+        }
+    };
+
+    if clut.get_grid_points().iter().any(|points| *points > 255) {
+        context.signal_error(
+            ErrorCode::NotSuitable,
+            "Cannot save CLUT of more than 255 grid points per dimension",
+        );
+        return Err(Error::from(ErrorKind::InvalidInput));
+    }
+
+    io.write_u16(stage.get_input_channels() as u16)?;
+    io.write_u16(stage.get_output_channels() as u16)?;
+
+    let mut grid_points = [0u8; MAX_CHANNELS];
+    for (dst, src) in grid_points.iter_mut().zip(clut.get_grid_points()) {
+        *dst = *src as u8;
+    }
+    io.write(&grid_points)?;
+
+    for value in table.iter() {
+        io.write_f32(*value)?;
+    }
+
+    Ok(())
+}
+
+/// Skips a `bACS` or `eACS` element, which are ignored.
+pub(crate) fn read_mpe_acs(
+    context: &mut Context,
+    _handler: &TypeHandler,
+    io: &mut dyn IOHandler,
+    num_items: &mut usize,
+    _size_of_tag: usize,
+) -> Result<TagValue> {
+    let (channels, _) = read_element_channels(io)?;
+
+    *num_items = 1;
+    Pipeline::new(context, channels as u32, channels as u32)
+        .map(TagValue::Pipeline)
+        .ok_or_else(|| Error::from(ErrorKind::InvalidData))
+}
+
+/// `bACS` and `eACS` elements are never written, as no stage is read from them.
+pub(crate) fn write_mpe_acs(
+    _context: &mut Context,
+    _handler: &TypeHandler,
+    _io: &mut dyn IOHandler,
+    _value: &TagValue,
+    _num_items: usize,
+) -> Result<()> {
+    Err(Error::from(ErrorKind::InvalidInput))
+}
+
+pub(crate) fn read_mpe(
+    context: &mut Context,
+    _handler: &TypeHandler,
+    io: &mut dyn IOHandler,
+    num_items: &mut usize,
+    size_of_tag: usize,
+) -> Result<TagValue> {
+    let base_offset = io.tell()? - 8;
+
+    let input_channels = io.read_u16()? as usize;
+    let output_channels = io.read_u16()? as usize;
+
+    if input_channels == 0
+        || input_channels >= MAX_CHANNELS
+        || output_channels == 0
+        || output_channels >= MAX_CHANNELS
+    {
+        return Err(Error::from(ErrorKind::InvalidData));
+    }
+
+    let mut lut = Pipeline::new(context, input_channels as u32, output_channels as u32)
+        .ok_or_else(|| Error::from(ErrorKind::InvalidData))?;
+
+    let count = io.read_u32()? as usize;
+    read_position_table(io, count, base_offset, size_of_tag, |io, offset| {
+        let signature = io.read_type_base()?;
+        let handler = match get_mpe_type_handler(context, signature) {
+            Some(handler) => handler,
+            None => {
+                context.signal_error(
+                    ErrorCode::UnknownExtension,
+                    format!("Unknown MPE type '{:?}' found.", signature),
+                );
+                return Err(Error::from(ErrorKind::InvalidData));
+            }
+        };
+
+        let mut items = 0;
+        // The element data follows its own type base
+        let size = size_of_tag.saturating_sub(offset);
+        let element = match (handler.read)(context, &handler, io, &mut items, size)? {
+            TagValue::Pipeline(element) => element,
+            _ => return Err(Error::from(ErrorKind::InvalidData)),
+        };
+
+        for stage in element.get_stages().iter().cloned() {
+            if !lut.insert_stage(context, StageLoc::AtEnd, stage) {
+                return Err(Error::from(ErrorKind::InvalidData));
+            }
+        }
+
+        Ok(())
+    })?;
+
+    // Check channel count. Elements are chained by insert_stage(), so only both ends are left
+    let first_input_channels = lut
+        .get_stages()
+        .first()
+        .map_or(input_channels as u32, |stage| stage.get_input_channels());
+    if first_input_channels != input_channels as u32 {
+        return Err(Error::from(ErrorKind::InvalidData));
+    }
+    let last_output_channels = lut
+        .get_stages()
+        .last()
+        .map_or(input_channels as u32, |stage| stage.get_output_channels());
+    if last_output_channels != output_channels as u32 {
+        return Err(Error::from(ErrorKind::InvalidData));
+    }
+
+    *num_items = 1;
+    Ok(TagValue::Pipeline(lut))
+}
+
+pub(crate) fn write_mpe(
+    context: &mut Context,
+    _handler: &TypeHandler,
+    io: &mut dyn IOHandler,
+    value: &TagValue,
+    _num_items: usize,
+) -> Result<()> {
+    let lut = match value {
+        TagValue::Pipeline(lut) => lut,
+        _ => return Err(Error::from(ErrorKind::InvalidInput)),
+    };
+
+    let base_offset = io.tell()? - 8;
+    let stages = lut.get_stages();
+
+    io.write_u16(lut.get_input_channels() as u16)?;
+    io.write_u16(lut.get_output_channels() as u16)?;
+    io.write_u32(stages.len() as u32)?;
+
+    write_position_table(io, stages.len(), base_offset, |io, i| {
+        let stage = &stages[i];
+        let handler = match get_mpe_type_handler(context, stage.get_type()) {
+            Some(handler) => handler,
+            None => {
+                context.signal_error(
+                    ErrorCode::UnknownExtension,
+                    format!("Found unknown MPE type '{:?}'", stage.get_type()),
+                );
+                return Err(Error::from(ErrorKind::InvalidInput));
+            }
+        };
+
+        let mut element = Pipeline::new(
+            context,
+            stage.get_input_channels(),
+            stage.get_output_channels(),
+        )
+        .ok_or_else(|| Error::from(ErrorKind::InvalidInput))?;
+        element.insert_stage(context, StageLoc::AtEnd, stage.clone());
+
+        io.write_type_base(stage.get_type())?;
+        (handler.write)(context, &handler, io, &TagValue::Pipeline(element), 1)?;
+        io.write_alignment()
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::{self, ErrorKind};
+
+    use crate::{
+        io::{FileMem, FileMemReadOnly, IOHandler},
+        plugins::{TypeHandler, MINUS_INF, PLUS_INF},
+        state::Context,
+        types::{
+            signatures, CurveSegment, Pipeline, Profile, Stage, StageData, StageLoc, TagValue,
+            ToneCurve,
+        },
+    };
+
+    use super::*;
+
+    fn round_trip(context: &mut Context, lut: Pipeline) -> io::Result<Pipeline> {
+        let handler = TypeHandler::new(
+            signatures::tag_type::MULTI_PROCESS_ELEMENT,
+            read_mpe,
+            write_mpe,
+        );
+
+        let mut mem = FileMem::new(Vec::new());
+        mem.write_type_base(signatures::tag_type::MULTI_PROCESS_ELEMENT)?;
+        write_mpe(context, &handler, &mut mem, &TagValue::Pipeline(lut), 1)?;
+        let data = mem.cursor.into_inner();
+
+        read(context, &data)
+    }
+
+    fn read(context: &mut Context, data: &[u8]) -> io::Result<Pipeline> {
+        let handler = TypeHandler::new(
+            signatures::tag_type::MULTI_PROCESS_ELEMENT,
+            read_mpe,
+            write_mpe,
+        );
+
+        let mut io = FileMemReadOnly::new(data);
+        io.read_type_base()?;
+        let mut count = 0;
+        match read_mpe(context, &handler, &mut io, &mut count, data.len() - 8)? {
+            TagValue::Pipeline(lut) => Ok(lut),
+            value => panic!("unexpected tag value {:?}", value),
+        }
+    }
+
+    /// Builds a curve made of a sampled segment up to 0.5 and a `Y = (0.5 * X + 0.25) ^ 2 + 0` formula after it.
+    fn segmented_curve(context: &mut Context) -> ToneCurve {
+        let segments = [
+            CurveSegment {
+                x0: MINUS_INF as f32,
+                x1: 0.5,
+                r#type: 0,
+                params: [0.0; 10],
+                sampled_points: vec![0.0, 0.1, 0.2, 0.25],
+            },
+            CurveSegment {
+                x0: 0.5,
+                x1: PLUS_INF as f32,
+                r#type: 6,
+                params: [2.0, 0.5, 0.25, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0],
+                sampled_points: Vec::new(),
+            },
+        ];
+
+        ToneCurve::build_segmented(context, &segments).unwrap()
+    }
+
+    fn test_lut(context: &mut Context) -> Pipeline {
+        let mut lut = Pipeline::new(context, 3, 2).unwrap();
+        let curve = segmented_curve(context);
+        let matrix = [0.5, 0.25, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0];
+        let offset = [0.125, 0.0, 0.0];
+        let table = (0..2 * 3 * 2 * 2)
+            .map(|i| i as f32 / 23.0)
+            .collect::<Vec<_>>();
+
+        let stages = [
+            Stage::new_tone_curves(context, 3, Some(&[curve.clone(), curve.clone(), curve]))
+                .unwrap(),
+            Stage::new_matrix(context, 3, 3, &matrix, Some(&offset)).unwrap(),
+            Stage::new_clut_float_granular(context, &[2, 3, 2], 3, 2, Some(&table)).unwrap(),
+        ];
+        for stage in stages {
+            assert!(lut.insert_stage(context, StageLoc::AtEnd, stage));
+        }
+
+        lut
+    }
+
+    #[test]
+    fn test_mpe_round_trips() -> io::Result<()> {
+        let mut context = Context::new(None);
+        let lut = test_lut(&mut context);
+
+        let read = round_trip(&mut context, lut.clone())?;
+
+        let types = read
+            .get_stages()
+            .iter()
+            .map(|stage| stage.get_type())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            types,
+            [
+                signatures::stage::CURVE_SET_ELEM_TYPE,
+                signatures::stage::MATRIX_ELEM_TYPE,
+                signatures::stage::C_LUT_ELEM_TYPE
+            ]
+        );
+        match read.get_stages()[0].get_data() {
+            StageData::ToneCurves(data) => {
+                let segments = data.get_curves()[0].get_segments();
+                assert_eq!(segments.len(), 2);
+                assert_eq!(segments[0].sampled_points, [0.0, 0.1, 0.2, 0.25]);
+                assert_eq!(segments[1].r#type, 6);
+                assert_eq!(segments[1].params[..4], [2.0, 0.5, 0.25, 0.0]);
+            }
+            data => panic!("unexpected stage data {:?}", data),
+        }
+
+        let (mut expected, mut got) = ([0f32; 2], [0f32; 2]);
+        for input in [[0.0, 0.0, 0.0], [1.0, 0.25, 0.75], [0.3, 0.6, 0.9]] {
+            lut.eval_float(&mut context, &input, &mut expected);
+            read.eval_float(&mut context, &input, &mut got);
+            assert_eq!(got, expected);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_mpe_skips_acs_elements() -> io::Result<()> {
+        let mut context = Context::new(None);
+
+        let mut data = b"mpet\0\0\0\0".to_vec();
+        data.extend([0, 1, 0, 1, 0, 0, 0, 2]);
+        data.extend([0, 0, 0, 32, 0, 0, 0, 12, 0, 0, 0, 44, 0, 0, 0, 20]);
+        data.extend(b"bACS\0\0\0\0");
+        data.extend([0, 1, 0, 1]);
+        data.extend(b"matf\0\0\0\0");
+        data.extend([0, 1, 0, 1]);
+        data.extend(2f32.to_be_bytes());
+        data.extend(0.5f32.to_be_bytes());
+
+        let lut = read(&mut context, &data)?;
+
+        assert_eq!(lut.get_stages().len(), 1);
+        let mut out = [0f32];
+        lut.eval_float(&mut context, &[0.25], &mut out);
+        assert_eq!(out, [1.0]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_mpe_elements_from_plugins_come_first() -> io::Result<()> {
+        fn read_acs_as_inverse(
+            context: &mut Context,
+            _handler: &TypeHandler,
+            io: &mut dyn IOHandler,
+            num_items: &mut usize,
+            _size_of_tag: usize,
+        ) -> io::Result<TagValue> {
+            let (channels, _) = read_element_channels(io)?;
+            let curve = ToneCurve::build_parametric(context, 6, &[1.0, -1.0, 1.0, 0.0]).unwrap();
+            let curves = vec![curve; channels];
+
+            *num_items = 1;
+            let stage = Stage::new_tone_curves(context, channels as u32, Some(&curves));
+            element_value(context, stage)
+        }
+
+        let mut context = Context::new(None);
+        context.mpe_types_plugin.tag_types.push(TypeHandler::new(
+            signatures::stage::B_ACS_ELEM_TYPE,
+            read_acs_as_inverse,
+            write_mpe_acs,
+        ));
+
+        let mut data = b"mpet\0\0\0\0".to_vec();
+        data.extend([0, 1, 0, 1, 0, 0, 0, 1]);
+        data.extend([0, 0, 0, 24, 0, 0, 0, 12]);
+        data.extend(b"bACS\0\0\0\0");
+        data.extend([0, 1, 0, 1]);
+
+        let lut = read(&mut context, &data)?;
+
+        assert_eq!(lut.get_stages().len(), 1);
+        let mut out = [0f32];
+        lut.eval_float(&mut context, &[0.25], &mut out);
+        assert_eq!(out, [0.75]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_mpe_writer_rejects_unsuitable_stages() {
+        let mut context = Context::new(None);
+
+        // Curves must be made of sampled and formula segments
+        let mut lut = Pipeline::new(&mut context, 1, 1).unwrap();
+        let curve = ToneCurve::build_gamma(&mut context, 2.2).unwrap();
+        let stage = Stage::new_tone_curves(&mut context, 1, Some(&[curve])).unwrap();
+        lut.insert_stage(&mut context, StageLoc::AtEnd, stage);
+
+        let err = round_trip(&mut context, lut).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+
+        // And CLUTs must hold floating point values
+        let mut lut = Pipeline::new(&mut context, 1, 1).unwrap();
+        let stage = Stage::new_clut_16bit(&mut context, 2, 1, 1, None).unwrap();
+        lut.insert_stage(&mut context, StageLoc::AtEnd, stage);
+
+        let err = round_trip(&mut context, lut).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn test_mpe_rejects_malformed_tags() {
+        let mut context = Context::new(None);
+
+        let header = |inputs: u8, outputs: u8, offset: u8| {
+            let mut data = b"mpet\0\0\0\0".to_vec();
+            data.extend([0, inputs, 0, outputs, 0, 0, 0, 1]);
+            data.extend([0, 0, 0, offset, 0, 0, 0, 12]);
+            data.extend(b"matf\0\0\0\0");
+            data.extend([0, 1, 0, 1]);
+            data
+        };
+
+        for data in [
+            header(0, 1, 24),
+            header(1, 16, 24),
+            // The element doesn't fit into the tag
+            header(1, 1, 200),
+            // The matrix values are missing
+            header(1, 1, 24),
+            // The output channels don't match
+            header(1, 2, 24),
+        ] {
+            let err = read(&mut context, &data).unwrap_err();
+            assert!(
+                matches!(
+                    err.kind(),
+                    ErrorKind::InvalidData | ErrorKind::UnexpectedEof
+                ),
+                "{:?}",
+                err
+            );
+        }
+    }
+
+    #[test]
+    fn test_mpe_rejects_mismatched_element_channels() {
+        let mut context = Context::new(None);
+
+        // A table of `channels` matf elements of one output each, the first taking `first_inputs` inputs
+        let tag = |inputs: u8, first_inputs: u8, channels: &[u8]| {
+            let mut data = b"mpet\0\0\0\0".to_vec();
+            data.extend([0, inputs, 0, 1, 0, 0, 0, channels.len() as u8]);
+
+            let mut offset = 16 + 8 * channels.len();
+            let mut elements = Vec::new();
+            for (i, element_inputs) in channels.iter().enumerate() {
+                let element_inputs = if i == 0 {
+                    first_inputs
+                } else {
+                    *element_inputs
+                };
+                let size = 12 + 4 * (element_inputs as usize + 1);
+                data.extend((offset as u32).to_be_bytes());
+                data.extend((size as u32).to_be_bytes());
+                offset += size;
+
+                elements.extend(b"matf\0\0\0\0");
+                elements.extend([0, element_inputs, 0, 1]);
+                for _ in 0..=element_inputs {
+                    elements.extend(1f32.to_be_bytes());
+                }
+            }
+            data.extend(elements);
+            data
+        };
+
+        assert!(read(&mut context, &tag(2, 2, &[2, 1])).is_ok());
+        for data in [
+            // The first element doesn't take the inputs of the tag
+            tag(2, 1, &[1]),
+            tag(2, 3, &[3, 1]),
+            // The second element doesn't take the output of the first one
+            tag(2, 2, &[2, 2]),
+        ] {
+            let err = read(&mut context, &data).unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::InvalidData);
+        }
+    }
+
+    #[test]
+    fn test_profile_saves_d_to_b_as_mpe() -> io::Result<()> {
+        let mut context = Context::new(None);
+        let mut profile = Profile::new();
+        profile.set_version(4.3);
+        let lut = test_lut(&mut context);
+        profile.write_tag_thr(
+            &mut context,
+            signatures::tag::D_TO_B0,
+            TagValue::Pipeline(lut),
+        )?;
+        let saved = profile.save_to_mem_thr(&mut context)?;
+        let mut reopened = Profile::open_from_mem_thr(&mut context, &saved)?;
+
+        assert_eq!(
+            &reopened.read_raw_tag(signatures::tag::D_TO_B0)?[..4],
+            b"mpet"
+        );
+        match reopened.read_tag_thr(&mut context, signatures::tag::D_TO_B0)? {
+            TagValue::Pipeline(lut) => assert_eq!(lut.get_stages().len(), 3),
+            value => panic!("unexpected tag value {:?}", value),
+        }
+
+        Ok(())
+    }
+}
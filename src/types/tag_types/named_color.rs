@@ -0,0 +1,346 @@
+//! `ncl2` (namedColor2) and `clrt` (colorantTable) tag types, ICC.1:2022 clauses 10.17 and 10.4.
+
+use std::io::{Error, ErrorKind, Result};
+
+use crate::{
+    io::IOHandler,
+    plugins::TypeHandler,
+    state::{Context, ErrorCode},
+    types::{NamedColorList, TagValue, MAX_CHANNELS},
+};
+
+use super::text::{decode_ascii, encode_ascii};
+
+/// Size of the prefix, the suffix and each root name, including the NUL terminator.
+const NAME_SIZE: usize = 32;
+
+fn read_name(io: &mut dyn IOHandler) -> Result<String> {
+    let mut name = [0u8; NAME_SIZE];
+    io.read(&mut name)?;
+    name[NAME_SIZE - 1] = 0;
+
+    Ok(decode_ascii(&name))
+}
+
+/// Writes `name` as NUL terminated ASCII, truncated to fit.
+fn write_name(io: &mut dyn IOHandler, name: &str) -> Result<()> {
+    let mut bytes = encode_ascii(name);
+    bytes.resize(NAME_SIZE, 0);
+    bytes[NAME_SIZE - 1] = 0;
+
+    io.write(&bytes)
+}
+
+pub(crate) fn read_named_color(
+    context: &mut Context,
+    _handler: &TypeHandler,
+    io: &mut dyn IOHandler,
+    num_items: &mut usize,
+    size_of_tag: usize,
+) -> Result<TagValue> {
+    let _vendor_flag = io.read_u32()?;
+    let count = io.read_u32()? as usize;
+    let device_coords = io.read_u32()? as usize;
+
+    let prefix = read_name(io)?;
+    let suffix = read_name(io)?;
+
+    if device_coords > MAX_CHANNELS {
+        context.signal_error(
+            ErrorCode::Range,
+            format!("Too many device coordinates '{}'", device_coords),
+        );
+        return Err(Error::from(ErrorKind::InvalidData));
+    }
+
+    // Each color is its root name, 3 PCS coordinates and the device coordinates
+    let color_size = NAME_SIZE + 2 * (3 + device_coords);
+    if count > size_of_tag.saturating_sub(12 + 2 * NAME_SIZE) / color_size {
+        return Err(Error::from(ErrorKind::InvalidData));
+    }
+
+    let mut list = NamedColorList::new(context, device_coords as u32, &prefix, &suffix)
+        .ok_or_else(|| Error::from(ErrorKind::InvalidData))?;
+
+    let mut colorant = vec![0u16; device_coords];
+    for _ in 0..count {
+        let root = read_name(io)?;
+
+        let mut pcs = [0u16; 3];
+        io.read_u16_array(&mut pcs)?;
+        io.read_u16_array(&mut colorant)?;
+
+        list.append(&root, pcs, Some(&colorant));
+    }
+
+    *num_items = 1;
+    Ok(TagValue::NamedColorList(list))
+}
+
+pub(crate) fn write_named_color(
+    _context: &mut Context,
+    _handler: &TypeHandler,
+    io: &mut dyn IOHandler,
+    value: &TagValue,
+    _num_items: usize,
+) -> Result<()> {
+    let list = match value {
+        TagValue::NamedColorList(list) => list,
+        _ => return Err(Error::from(ErrorKind::InvalidInput)),
+    };
+    let colorant_count = list.get_colorant_count() as usize;
+
+    io.write_u32(0)?; // Vendor flag
+    io.write_u32(list.get_count() as u32)?;
+    io.write_u32(colorant_count as u32)?;
+
+    write_name(io, list.get_prefix())?;
+    write_name(io, list.get_suffix())?;
+
+    for color in list.get_colors() {
+        write_name(io, color.get_name())?;
+        io.write_u16_array(&color.get_pcs())?;
+        io.write_u16_array(&color.get_device_colorant()[..colorant_count])?;
+    }
+
+    Ok(())
+}
+
+pub(crate) fn read_colorant_table(
+    context: &mut Context,
+    _handler: &TypeHandler,
+    io: &mut dyn IOHandler,
+    num_items: &mut usize,
+    _size_of_tag: usize,
+) -> Result<TagValue> {
+    let count = io.read_u32()? as usize;
+
+    if count > MAX_CHANNELS {
+        context.signal_error(ErrorCode::Range, format!("Too many colorants '{}'", count));
+        return Err(Error::from(ErrorKind::InvalidData));
+    }
+
+    // Colorants only have a name and PCS coordinates
+    let mut list = NamedColorList::new(context, 0, "", "")
+        .ok_or_else(|| Error::from(ErrorKind::InvalidData))?;
+
+    for _ in 0..count {
+        let name = read_name(io)?;
+
+        let mut pcs = [0u16; 3];
+        io.read_u16_array(&mut pcs)?;
+
+        list.append(&name, pcs, None);
+    }
+
+    *num_items = 1;
+    Ok(TagValue::NamedColorList(list))
+}
+
+pub(crate) fn write_colorant_table(
+    context: &mut Context,
+    _handler: &TypeHandler,
+    io: &mut dyn IOHandler,
+    value: &TagValue,
+    _num_items: usize,
+) -> Result<()> {
+    let list = match value {
+        TagValue::NamedColorList(list) => list,
+        _ => return Err(Error::from(ErrorKind::InvalidInput)),
+    };
+
+    if list.get_count() > MAX_CHANNELS {
+        context.signal_error(
+            ErrorCode::Range,
+            format!("Too many colorants '{}'", list.get_count()),
+        );
+        return Err(Error::from(ErrorKind::InvalidInput));
+    }
+
+    io.write_u32(list.get_count() as u32)?;
+
+    for color in list.get_colors() {
+        write_name(io, color.get_name())?;
+        io.write_u16_array(&color.get_pcs())?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::{self, ErrorKind};
+
+    use crate::{
+        io::{FileMem, FileMemReadOnly},
+        plugins::TypeHandler,
+        state::Context,
+        types::{signatures, NamedColorList, Profile, TagValue},
+    };
+
+    use super::*;
+
+    fn handler() -> TypeHandler {
+        TypeHandler::new(
+            signatures::tag_type::NAMED_COLOR2,
+            read_named_color,
+            write_named_color,
+        )
+    }
+
+    fn read(context: &mut Context, data: &[u8]) -> io::Result<NamedColorList> {
+        let mut io = FileMemReadOnly::new(data);
+        let mut count = 0;
+        match read_named_color(context, &handler(), &mut io, &mut count, data.len())? {
+            TagValue::NamedColorList(list) => Ok(list),
+            value => panic!("unexpected tag value {:?}", value),
+        }
+    }
+
+    #[test]
+    fn test_named_color_round_trips() -> io::Result<()> {
+        let mut context = Context::new(None);
+        let mut list = NamedColorList::new(&mut context, 2, "PANTONE ", " CV").unwrap();
+        list.append(
+            "Warm Red",
+            [0x8000, 0xc000, 0xa000],
+            Some(&[0xffff, 0x1234]),
+        );
+        list.append("Process Blue", [0x4000, 0x7000, 0x2000], Some(&[0, 0xffff]));
+
+        let mut mem = FileMem::new(Vec::new());
+        write_named_color(
+            &mut context,
+            &handler(),
+            &mut mem,
+            &TagValue::NamedColorList(list.clone()),
+            1,
+        )?;
+        let data = mem.cursor.into_inner();
+
+        assert_eq!(data.len(), 12 + 2 * 32 + 2 * (32 + 3 * 2 + 2 * 2));
+        assert_eq!(read(&mut context, &data)?, list);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_named_color_truncates_long_names() -> io::Result<()> {
+        let mut context = Context::new(None);
+        let mut list = NamedColorList::new(&mut context, 0, "", "").unwrap();
+        let name = "A name much longer than the 31 characters that fit";
+        list.append(name, [0; 3], None);
+
+        let mut mem = FileMem::new(Vec::new());
+        write_named_color(
+            &mut context,
+            &handler(),
+            &mut mem,
+            &TagValue::NamedColorList(list),
+            1,
+        )?;
+        let read = read(&mut context, &mem.cursor.into_inner())?;
+
+        assert_eq!(read.get(0).unwrap().get_name(), &name[..31]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_named_color_rejects_malformed_tags() {
+        let mut context = Context::new(None);
+
+        let header = |count: u8, device_coords: u8| {
+            let mut data = vec![0, 0, 0, 0, 0, 0, 0, count, 0, 0, 0, device_coords];
+            data.extend([0; 64]);
+            data
+        };
+
+        for data in [header(0, 17), header(1, 3)] {
+            let err = read(&mut context, &data).unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::InvalidData);
+        }
+    }
+
+    #[test]
+    fn test_profile_saves_named_colors() -> io::Result<()> {
+        let mut context = Context::new(None);
+        let mut list = NamedColorList::new(&mut context, 1, "", "").unwrap();
+        list.append("Spot", [1, 2, 3], Some(&[4]));
+
+        let mut profile = Profile::new();
+        profile.write_tag_thr(
+            &mut context,
+            signatures::tag::NAMED_COLOR2,
+            TagValue::NamedColorList(list.clone()),
+        )?;
+        let saved = profile.save_to_mem_thr(&mut context)?;
+        let mut reopened = Profile::open_from_mem_thr(&mut context, &saved)?;
+
+        assert_eq!(
+            &reopened.read_raw_tag(signatures::tag::NAMED_COLOR2)?[..4],
+            b"ncl2"
+        );
+        match reopened.read_tag_thr(&mut context, signatures::tag::NAMED_COLOR2)? {
+            TagValue::NamedColorList(read) => assert_eq!(*read, list),
+            value => panic!("unexpected tag value {:?}", value),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_colorant_table_round_trips() -> io::Result<()> {
+        let mut context = Context::new(None);
+        let mut list = NamedColorList::new(&mut context, 0, "", "").unwrap();
+        for (name, pcs) in [
+            ("Cyan", [0x8000, 0x6000, 0x5000]),
+            ("Magenta", [0x7000, 0xc000, 0x8000]),
+        ] {
+            list.append(name, pcs, None);
+        }
+        let handler = TypeHandler::new(
+            signatures::tag_type::COLORANT_TABLE,
+            read_colorant_table,
+            write_colorant_table,
+        );
+
+        let mut mem = FileMem::new(Vec::new());
+        write_colorant_table(
+            &mut context,
+            &handler,
+            &mut mem,
+            &TagValue::NamedColorList(list.clone()),
+            1,
+        )?;
+        let data = mem.cursor.into_inner();
+        assert_eq!(data.len(), 4 + 2 * (32 + 3 * 2));
+
+        let mut io = FileMemReadOnly::new(data.as_slice());
+        let mut count = 0;
+        match read_colorant_table(&mut context, &handler, &mut io, &mut count, data.len())? {
+            TagValue::NamedColorList(read) => assert_eq!(read, list),
+            value => panic!("unexpected tag value {:?}", value),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_colorant_table_rejects_too_many_colorants() {
+        let mut context = Context::new(None);
+        let handler = TypeHandler::new(
+            signatures::tag_type::COLORANT_TABLE,
+            read_colorant_table,
+            write_colorant_table,
+        );
+
+        let data = [0, 0, 0, 17];
+        let mut io = FileMemReadOnly::new(data.as_slice());
+        let mut count = 0;
+        let err = read_colorant_table(&mut context, &handler, &mut io, &mut count, data.len())
+            .unwrap_err();
+
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,531 @@
+//! `XYZ `, `dtim`, `sig `, `sf32`, `uf32`, `ui08`, `ui16`, `ui32` and `ui64` tag types, ICC.1:2022 clauses 10.8 and
+//! 10.20 to 10.31.
+
+use std::io::{Error, ErrorKind, Result};
+
+use crate::{
+    io::IOHandler,
+    plugins::TypeHandler,
+    state::Context,
+    types::{DateTimeNumber, Signature, TagValue},
+};
+
+pub(crate) fn read_xyz(
+    _context: &mut Context,
+    _handler: &TypeHandler,
+    io: &mut dyn IOHandler,
+    num_items: &mut usize,
+    _size_of_tag: usize,
+) -> Result<TagValue> {
+    let xyz = io.read_xyz()?;
+
+    *num_items = 1;
+    Ok(TagValue::XYZ(xyz))
+}
+
+pub(crate) fn write_xyz(
+    _context: &mut Context,
+    _handler: &TypeHandler,
+    io: &mut dyn IOHandler,
+    value: &TagValue,
+    _num_items: usize,
+) -> Result<()> {
+    match value {
+        TagValue::XYZ(xyz) => io.write_xyz(*xyz),
+        _ => Err(Error::from(ErrorKind::InvalidInput)),
+    }
+}
+
+pub(crate) fn read_date_time(
+    _context: &mut Context,
+    _handler: &TypeHandler,
+    io: &mut dyn IOHandler,
+    num_items: &mut usize,
+    _size_of_tag: usize,
+) -> Result<TagValue> {
+    let mut values = [0u16; 6];
+    io.read_u16_array(&mut values)?;
+    let [year, month, day, hours, minutes, seconds] = values;
+
+    *num_items = 1;
+    Ok(TagValue::DateTime(DateTimeNumber {
+        year,
+        month,
+        day,
+        hours,
+        minutes,
+        seconds,
+    }))
+}
+
+pub(crate) fn write_date_time(
+    _context: &mut Context,
+    _handler: &TypeHandler,
+    io: &mut dyn IOHandler,
+    value: &TagValue,
+    _num_items: usize,
+) -> Result<()> {
+    match value {
+        TagValue::DateTime(date) => io.write_u16_array(&[
+            date.year,
+            date.month,
+            date.day,
+            date.hours,
+            date.minutes,
+            date.seconds,
+        ]),
+        _ => Err(Error::from(ErrorKind::InvalidInput)),
+    }
+}
+
+pub(crate) fn read_signature(
+    _context: &mut Context,
+    _handler: &TypeHandler,
+    io: &mut dyn IOHandler,
+    num_items: &mut usize,
+    _size_of_tag: usize,
+) -> Result<TagValue> {
+    let sig = Signature::from(io.read_u32()?);
+
+    *num_items = 1;
+    Ok(TagValue::Signature(sig))
+}
+
+pub(crate) fn write_signature(
+    _context: &mut Context,
+    _handler: &TypeHandler,
+    io: &mut dyn IOHandler,
+    value: &TagValue,
+    _num_items: usize,
+) -> Result<()> {
+    match value {
+        TagValue::Signature(sig) => io.write_u32((*sig).into()),
+        _ => Err(Error::from(ErrorKind::InvalidInput)),
+    }
+}
+
+/// Reads as many `width` byte values as fit in `size_of_tag`.
+fn read_array<T>(
+    io: &mut dyn IOHandler,
+    num_items: &mut usize,
+    size_of_tag: usize,
+    width: usize,
+    read: fn(&mut dyn IOHandler) -> Result<T>,
+) -> Result<Vec<T>> {
+    let count = size_of_tag / width;
+
+    let mut values = Vec::with_capacity(count);
+    for _ in 0..count {
+        values.push(read(io)?);
+    }
+
+    *num_items = count;
+    Ok(values)
+}
+
+pub(crate) fn read_s15_fixed16_array(
+    _context: &mut Context,
+    _handler: &TypeHandler,
+    io: &mut dyn IOHandler,
+    num_items: &mut usize,
+    size_of_tag: usize,
+) -> Result<TagValue> {
+    let values = read_array(io, num_items, size_of_tag, 4, |io| io.read_s15f16())?;
+
+    Ok(TagValue::S15Fixed16Array(values))
+}
+
+pub(crate) fn write_s15_fixed16_array(
+    _context: &mut Context,
+    _handler: &TypeHandler,
+    io: &mut dyn IOHandler,
+    value: &TagValue,
+    _num_items: usize,
+) -> Result<()> {
+    let values = match value {
+        TagValue::S15Fixed16Array(values) => values,
+        _ => return Err(Error::from(ErrorKind::InvalidInput)),
+    };
+
+    for value in values {
+        io.write_s15f16(*value)?;
+    }
+
+    Ok(())
+}
+
+pub(crate) fn read_u16_fixed16_array(
+    _context: &mut Context,
+    _handler: &TypeHandler,
+    io: &mut dyn IOHandler,
+    num_items: &mut usize,
+    size_of_tag: usize,
+) -> Result<TagValue> {
+    let values = read_array(io, num_items, size_of_tag, 4, |io| {
+        Ok(io.read_u32()? as f64 / 65536.0)
+    })?;
+
+    Ok(TagValue::U16Fixed16Array(values))
+}
+
+pub(crate) fn write_u16_fixed16_array(
+    _context: &mut Context,
+    _handler: &TypeHandler,
+    io: &mut dyn IOHandler,
+    value: &TagValue,
+    _num_items: usize,
+) -> Result<()> {
+    let values = match value {
+        TagValue::U16Fixed16Array(values) => values,
+        _ => return Err(Error::from(ErrorKind::InvalidInput)),
+    };
+
+    for value in values {
+        io.write_u32((value * 65536.0 + 0.5).floor() as u32)?;
+    }
+
+    Ok(())
+}
+
+pub(crate) fn read_u8_array(
+    _context: &mut Context,
+    _handler: &TypeHandler,
+    io: &mut dyn IOHandler,
+    num_items: &mut usize,
+    size_of_tag: usize,
+) -> Result<TagValue> {
+    let values = read_array(io, num_items, size_of_tag, 1, |io| io.read_u8())?;
+
+    Ok(TagValue::U8Array(values))
+}
+
+pub(crate) fn write_u8_array(
+    _context: &mut Context,
+    _handler: &TypeHandler,
+    io: &mut dyn IOHandler,
+    value: &TagValue,
+    _num_items: usize,
+) -> Result<()> {
+    match value {
+        TagValue::U8Array(values) => io.write(values),
+        _ => Err(Error::from(ErrorKind::InvalidInput)),
+    }
+}
+
+pub(crate) fn read_u16_array(
+    _context: &mut Context,
+    _handler: &TypeHandler,
+    io: &mut dyn IOHandler,
+    num_items: &mut usize,
+    size_of_tag: usize,
+) -> Result<TagValue> {
+    let values = read_array(io, num_items, size_of_tag, 2, |io| io.read_u16())?;
+
+    Ok(TagValue::U16Array(values))
+}
+
+pub(crate) fn write_u16_array(
+    _context: &mut Context,
+    _handler: &TypeHandler,
+    io: &mut dyn IOHandler,
+    value: &TagValue,
+    _num_items: usize,
+) -> Result<()> {
+    match value {
+        TagValue::U16Array(values) => io.write_u16_array(values),
+        _ => Err(Error::from(ErrorKind::InvalidInput)),
+    }
+}
+
+pub(crate) fn read_u32_array(
+    _context: &mut Context,
+    _handler: &TypeHandler,
+    io: &mut dyn IOHandler,
+    num_items: &mut usize,
+    size_of_tag: usize,
+) -> Result<TagValue> {
+    let values = read_array(io, num_items, size_of_tag, 4, |io| io.read_u32())?;
+
+    Ok(TagValue::U32Array(values))
+}
+
+pub(crate) fn write_u32_array(
+    _context: &mut Context,
+    _handler: &TypeHandler,
+    io: &mut dyn IOHandler,
+    value: &TagValue,
+    _num_items: usize,
+) -> Result<()> {
+    let values = match value {
+        TagValue::U32Array(values) => values,
+        _ => return Err(Error::from(ErrorKind::InvalidInput)),
+    };
+
+    for value in values {
+        io.write_u32(*value)?;
+    }
+
+    Ok(())
+}
+
+pub(crate) fn read_u64_array(
+    _context: &mut Context,
+    _handler: &TypeHandler,
+    io: &mut dyn IOHandler,
+    num_items: &mut usize,
+    size_of_tag: usize,
+) -> Result<TagValue> {
+    let values = read_array(io, num_items, size_of_tag, 8, |io| io.read_u64())?;
+
+    Ok(TagValue::U64Array(values))
+}
+
+pub(crate) fn write_u64_array(
+    _context: &mut Context,
+    _handler: &TypeHandler,
+    io: &mut dyn IOHandler,
+    value: &TagValue,
+    _num_items: usize,
+) -> Result<()> {
+    let values = match value {
+        TagValue::U64Array(values) => values,
+        _ => return Err(Error::from(ErrorKind::InvalidInput)),
+    };
+
+    for value in values {
+        io.write_u64(*value)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::{self, ErrorKind};
+
+    use crate::{
+        io::{s15f16_to_f64, AccessMode},
+        plugins::TypeHandler,
+        state::Context,
+        testing::get_test_resource_path,
+        types::{
+            signatures::{self, tag_type},
+            tag_types::round_trip,
+            DateTimeNumber, Profile, Signature, TagValue, CIEXYZ,
+        },
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_read_numeric_tags_from_profile() -> io::Result<()> {
+        let mut context = Context::new(None);
+        let mut profile = Profile::open_from_file_thr(
+            &mut context,
+            get_test_resource_path("sRGB_v4_ICC_preference.icc"),
+            AccessMode::Read,
+        )?;
+
+        match profile.read_tag_thr(&mut context, signatures::tag::MEDIA_WHITE_POINT)? {
+            TagValue::XYZ(xyz) => assert_eq!(
+                *xyz,
+                CIEXYZ {
+                    X: s15f16_to_f64(0x0000F6D6),
+                    Y: s15f16_to_f64(0x00010000),
+                    Z: s15f16_to_f64(0x0000D32D),
+                }
+            ),
+            value => panic!("unexpected tag value {:?}", value),
+        }
+        match profile.read_tag_thr(&mut context, signatures::tag::CHROMATIC_ADAPTATION)? {
+            TagValue::S15Fixed16Array(values) => {
+                assert_eq!(values.len(), 9);
+                // Bradford adaptation from D65 to D50 is close to identity
+                assert!((values[0] - 1.0479).abs() < 1e-3);
+                assert!((values[4] - 1.0).abs() < 0.1);
+            }
+            value => panic!("unexpected tag value {:?}", value),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_xyz_round_trips() -> io::Result<()> {
+        let xyz = CIEXYZ {
+            X: 0.5,
+            Y: -1.25,
+            Z: 4096.125,
+        };
+
+        let (value, count, _) = round_trip(
+            &TypeHandler::new(tag_type::XYZ, read_xyz, write_xyz),
+            &TagValue::XYZ(xyz),
+        )?;
+
+        assert_eq!(count, 1);
+        assert!(matches!(value, TagValue::XYZ(read) if read == xyz));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_fixed16_arrays_round_trip() -> io::Result<()> {
+        let values = vec![0.0, 1.5, 255.25, 0.0009765625];
+
+        let (signed, signed_count, _) = round_trip(
+            &TypeHandler::new(
+                tag_type::S15_FIXED16_ARRAY,
+                read_s15_fixed16_array,
+                write_s15_fixed16_array,
+            ),
+            &TagValue::S15Fixed16Array([&[-2.5], values.as_slice()].concat()),
+        )?;
+        let (unsigned, unsigned_count, _) = round_trip(
+            &TypeHandler::new(
+                tag_type::U16_FIXED16_ARRAY,
+                read_u16_fixed16_array,
+                write_u16_fixed16_array,
+            ),
+            &TagValue::U16Fixed16Array(values.clone()),
+        )?;
+
+        assert_eq!(signed_count, 5);
+        assert_eq!(unsigned_count, 4);
+        assert!(
+            matches!(signed, TagValue::S15Fixed16Array(read) if read[0] == -2.5 && read[1..] == values)
+        );
+        assert!(matches!(unsigned, TagValue::U16Fixed16Array(read) if read == values));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_integer_arrays_round_trip() -> io::Result<()> {
+        let (u8s, u8_count, _) = round_trip(
+            &TypeHandler::new(tag_type::UINT8_ARRAY, read_u8_array, write_u8_array),
+            &TagValue::U8Array(vec![1, 2, 3]),
+        )?;
+        let (u16s, u16_count, _) = round_trip(
+            &TypeHandler::new(tag_type::UINT16_ARRAY, read_u16_array, write_u16_array),
+            &TagValue::U16Array(vec![0xffff, 0]),
+        )?;
+        let (u32s, u32_count, _) = round_trip(
+            &TypeHandler::new(tag_type::UINT32_ARRAY, read_u32_array, write_u32_array),
+            &TagValue::U32Array(vec![0xdead_beef]),
+        )?;
+        let (u64s, u64_count, _) = round_trip(
+            &TypeHandler::new(tag_type::UINT64_ARRAY, read_u64_array, write_u64_array),
+            &TagValue::U64Array(vec![u64::MAX, 42]),
+        )?;
+
+        assert_eq!((u8_count, u16_count, u32_count, u64_count), (3, 2, 1, 2));
+        assert!(matches!(u8s, TagValue::U8Array(read) if read == [1, 2, 3]));
+        assert!(matches!(u16s, TagValue::U16Array(read) if read == [0xffff, 0]));
+        assert!(matches!(u32s, TagValue::U32Array(read) if read == [0xdead_beef]));
+        assert!(matches!(u64s, TagValue::U64Array(read) if read == [u64::MAX, 42]));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_writers_reject_other_values() {
+        let err = round_trip(
+            &TypeHandler::new(tag_type::UINT8_ARRAY, read_u8_array, write_u8_array),
+            &TagValue::U16Array(vec![1]),
+        )
+        .unwrap_err();
+
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn test_chromatic_adaptation_requires_nine_values() -> io::Result<()> {
+        let mut context = Context::new(None);
+        let mut profile = Profile::new();
+        profile.set_version(4.3);
+
+        profile.write_tag_thr(
+            &mut context,
+            signatures::tag::CHROMATIC_ADAPTATION,
+            TagValue::S15Fixed16Array(vec![1.0; 8]),
+        )?;
+        profile.write_tag_thr(
+            &mut context,
+            signatures::tag::LUMINANCE,
+            TagValue::XYZ(CIEXYZ {
+                X: 0.0,
+                Y: 80.0,
+                Z: 0.0,
+            }),
+        )?;
+        let saved = profile.save_to_mem_thr(&mut context)?;
+        let mut reopened = Profile::open_from_mem_thr(&mut context, &saved)?;
+
+        let err = reopened
+            .read_tag_thr(&mut context, signatures::tag::CHROMATIC_ADAPTATION)
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        assert!(matches!(
+            reopened.read_tag_thr(&mut context, signatures::tag::LUMINANCE)?,
+            TagValue::XYZ(xyz) if xyz.Y == 80.0
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_date_time_round_trips() -> io::Result<()> {
+        let date = DateTimeNumber {
+            year: 2024,
+            month: 2,
+            day: 29,
+            hours: 23,
+            minutes: 59,
+            seconds: 30,
+        };
+
+        let (value, count, _) = round_trip(
+            &TypeHandler::new(tag_type::DATE_TIME, read_date_time, write_date_time),
+            &TagValue::DateTime(date),
+        )?;
+
+        assert_eq!(count, 1);
+        assert!(matches!(value, TagValue::DateTime(read) if read == date));
+        Ok(())
+    }
+
+    #[test]
+    fn test_technology_signature_round_trips() -> io::Result<()> {
+        let mut context = Context::new(None);
+        let mut profile = Profile::new();
+        profile.write_tag_thr(
+            &mut context,
+            signatures::tag::TECHNOLOGY,
+            TagValue::Signature(signatures::technology::DIGITAL_CINEMA_PROJECTOR),
+        )?;
+        let saved = profile.save_to_mem_thr(&mut context)?;
+        let mut reopened = Profile::open_from_mem_thr(&mut context, &saved)?;
+
+        assert_eq!(
+            reopened.read_raw_tag(signatures::tag::TECHNOLOGY)?,
+            b"sig \0\0\0\0dcpj"
+        );
+        let technology = match reopened.read_tag_thr(&mut context, signatures::tag::TECHNOLOGY)? {
+            TagValue::Signature(sig) => *sig,
+            value => panic!("unexpected tag value {:?}", value),
+        };
+        assert_eq!(
+            signatures::technology::get_description(technology),
+            Some("Digital cinema projector")
+        );
+
+        let (value, _, _) = round_trip(
+            &TypeHandler::new(tag_type::SIGNATURE, read_signature, write_signature),
+            &TagValue::Signature(Signature::from(b"abcd")),
+        )?;
+        assert!(matches!(value, TagValue::Signature(sig) if sig == Signature::from(b"abcd")));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,375 @@
+//! `pseq` (profileSequenceDesc) and `psid` (profileSequenceIdentifier) tag types, ICC.1:2022 clauses 10.22 and 10.23.
+
+use std::io::{Error, ErrorKind, Result};
+
+use crate::{
+    io::IOHandler,
+    plugins::TypeHandler,
+    state::{Context, ErrorCode},
+    types::{
+        signatures::tag_type, Mlu, ProfileID, Sequence, SequenceDescriptor, Signature, TagValue,
+    },
+};
+
+use super::{read_position_table, text, write_position_table};
+
+/// Reads a text embedded with its own type base, which may be `text`, `desc` or `mluc`. `size` is the space left in
+/// the tag from the type base on.
+fn read_embedded_text(
+    context: &mut Context,
+    handler: &TypeHandler,
+    io: &mut dyn IOHandler,
+    size: usize,
+) -> Result<Mlu> {
+    let base_type = io.read_type_base()?;
+    let read = match base_type {
+        tag_type::TEXT => text::read_text,
+        tag_type::TEXT_DESCRIPTION => text::read_text_description,
+        tag_type::MULTI_LOCALIZED_UNICODE => text::read_mlu,
+        _ => {
+            context.signal_error(
+                ErrorCode::UnknownExtension,
+                format!("Unknown embedded text type '{:?}'", base_type),
+            );
+            return Err(Error::from(ErrorKind::InvalidData));
+        }
+    };
+
+    let mut count = 0;
+    match read(context, handler, io, &mut count, size.saturating_sub(8))? {
+        TagValue::Mlu(mlu) => Ok(mlu),
+        _ => Err(Error::from(ErrorKind::InvalidData)),
+    }
+}
+
+/// Writes a text with its own type base, as `desc` in V2 profiles and as `mluc` in V4 profiles.
+fn write_embedded_text(
+    context: &mut Context,
+    handler: &TypeHandler,
+    io: &mut dyn IOHandler,
+    text: &Mlu,
+) -> Result<()> {
+    let value = TagValue::Mlu(text.clone());
+
+    if handler.get_icc_version() < 0x4000000 {
+        io.write_type_base(tag_type::TEXT_DESCRIPTION)?;
+        text::write_text_description(context, handler, io, &value, 1)
+    } else {
+        io.write_type_base(tag_type::MULTI_LOCALIZED_UNICODE)?;
+        text::write_mlu(context, handler, io, &value, 1)
+    }
+}
+
+pub(crate) fn read_profile_sequence_desc(
+    context: &mut Context,
+    handler: &TypeHandler,
+    io: &mut dyn IOHandler,
+    num_items: &mut usize,
+    size_of_tag: usize,
+) -> Result<TagValue> {
+    let start = io.tell()?;
+
+    let count = io.read_u32()? as usize;
+
+    // Each profile takes 20 bytes and two texts of at least 8 bytes each
+    if count > size_of_tag / (20 + 2 * 8) {
+        return Err(Error::from(ErrorKind::InvalidData));
+    }
+
+    let mut seq = Sequence::new();
+    for _ in 0..count {
+        let mut descriptor = SequenceDescriptor::new();
+
+        descriptor.set_device_mfg(Signature::from(io.read_u32()?));
+        descriptor.set_device_model(Signature::from(io.read_u32()?));
+        descriptor.set_attributes(io.read_u64()?);
+        descriptor.set_technology(Signature::from(io.read_u32()?));
+
+        let left = size_of_tag.saturating_sub(io.tell()? - start);
+        descriptor.set_manufacturer(read_embedded_text(context, handler, io, left)?);
+        let left = size_of_tag.saturating_sub(io.tell()? - start);
+        descriptor.set_model(read_embedded_text(context, handler, io, left)?);
+
+        seq.push(descriptor);
+    }
+
+    *num_items = 1;
+    Ok(TagValue::Sequence(seq))
+}
+
+pub(crate) fn write_profile_sequence_desc(
+    context: &mut Context,
+    handler: &TypeHandler,
+    io: &mut dyn IOHandler,
+    value: &TagValue,
+    _num_items: usize,
+) -> Result<()> {
+    let seq = match value {
+        TagValue::Sequence(seq) => seq,
+        _ => return Err(Error::from(ErrorKind::InvalidInput)),
+    };
+
+    io.write_u32(seq.get_count() as u32)?;
+
+    for descriptor in seq.get_descriptors() {
+        io.write_u32(descriptor.get_device_mfg().into())?;
+        io.write_u32(descriptor.get_device_model().into())?;
+        io.write_u64(descriptor.get_attributes())?;
+        io.write_u32(descriptor.get_technology().into())?;
+
+        write_embedded_text(context, handler, io, descriptor.get_manufacturer())?;
+        write_embedded_text(context, handler, io, descriptor.get_model())?;
+    }
+
+    Ok(())
+}
+
+pub(crate) fn read_profile_sequence_id(
+    context: &mut Context,
+    handler: &TypeHandler,
+    io: &mut dyn IOHandler,
+    num_items: &mut usize,
+    size_of_tag: usize,
+) -> Result<TagValue> {
+    let base_offset = io.tell()? - 8;
+
+    let count = io.read_u32()? as usize;
+
+    let mut seq = Sequence::new();
+    read_position_table(io, count, base_offset, size_of_tag, |io, offset| {
+        let mut descriptor = SequenceDescriptor::new();
+
+        let mut id = [0u8; 16];
+        io.read(&mut id)?;
+        descriptor.set_profile_id(ProfileID::from_bytes(id));
+
+        // The description follows the ID, up to the end of the tag
+        let left = (size_of_tag + 8).saturating_sub(offset + 16);
+        descriptor.set_description(read_embedded_text(context, handler, io, left)?);
+
+        seq.push(descriptor);
+        Ok(())
+    })?;
+
+    *num_items = 1;
+    Ok(TagValue::Sequence(seq))
+}
+
+pub(crate) fn write_profile_sequence_id(
+    context: &mut Context,
+    handler: &TypeHandler,
+    io: &mut dyn IOHandler,
+    value: &TagValue,
+    _num_items: usize,
+) -> Result<()> {
+    let seq = match value {
+        TagValue::Sequence(seq) => seq,
+        _ => return Err(Error::from(ErrorKind::InvalidInput)),
+    };
+
+    let base_offset = io.tell()? - 8;
+
+    io.write_u32(seq.get_count() as u32)?;
+
+    let descriptors = seq.get_descriptors();
+    write_position_table(io, descriptors.len(), base_offset, |io, i| {
+        io.write(&descriptors[i].get_profile_id().to_bytes())?;
+        write_embedded_text(context, handler, io, descriptors[i].get_description())?;
+        io.write_alignment()
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use std::io;
+
+    use crate::{
+        io::FileMemReadOnly,
+        types::{signatures, tag_types::round_trip, Profile},
+    };
+
+    use super::*;
+
+    fn test_seq() -> Sequence {
+        let mut seq = Sequence::new();
+        for (i, name) in ["Scanner", "Press"].into_iter().enumerate() {
+            let mut descriptor = SequenceDescriptor::new();
+            descriptor.set_device_mfg(Signature::from(b"ACME"));
+            descriptor.set_device_model(Signature::from(i as u32 + 1));
+            descriptor.set_attributes(0x1_0000_0002);
+            descriptor.set_technology(signatures::technology::OFFSET_LITHOGRAPHY);
+            descriptor.set_profile_id(ProfileID::from_bytes([i as u8 + 1; 16]));
+            descriptor.set_manufacturer(Mlu::from_text("ACME"));
+            descriptor.set_model(Mlu::from_text(name));
+            descriptor.set_description(Mlu::from_text(&format!("{} profile", name)));
+            seq.push(descriptor);
+        }
+
+        seq
+    }
+
+    #[test]
+    fn test_profile_sequence_desc_round_trips() -> io::Result<()> {
+        let seq = test_seq();
+
+        let mut handler = TypeHandler::new(
+            tag_type::PROFILE_SEQUENCE_DESC,
+            read_profile_sequence_desc,
+            write_profile_sequence_desc,
+        );
+
+        for (icc_version, text_type) in [(0x2100000, b"desc"), (0x4300000, b"mluc")] {
+            handler.icc_version = icc_version;
+            let (read, _, data) = round_trip(&handler, &TagValue::Sequence(seq.clone()))?;
+            let read = match read {
+                TagValue::Sequence(read) => read,
+                value => panic!("unexpected tag value {:?}", value),
+            };
+
+            assert_eq!(&data[12 + 20..12 + 24], text_type);
+            assert_eq!(read.get_count(), 2);
+            for (read, expected) in read.get_descriptors().iter().zip(seq.get_descriptors()) {
+                assert_eq!(read.get_device_mfg(), expected.get_device_mfg());
+                assert_eq!(read.get_device_model(), expected.get_device_model());
+                assert_eq!(read.get_attributes(), expected.get_attributes());
+                assert_eq!(read.get_technology(), expected.get_technology());
+                assert_eq!(
+                    read.get_model().get(Mlu::NO_LANGUAGE, Mlu::NO_COUNTRY),
+                    expected.get_model().get(Mlu::NO_LANGUAGE, Mlu::NO_COUNTRY)
+                );
+                // Only psid tags store IDs and descriptions
+                assert_eq!(read.get_profile_id(), ProfileID::from_bytes([0; 16]));
+                assert_eq!(read.get_description().get_translations_count(), 0);
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_profile_sequence_id_round_trips() -> io::Result<()> {
+        let seq = test_seq();
+
+        let mut handler = TypeHandler::new(
+            tag_type::PROFILE_SEQUENCE_ID,
+            read_profile_sequence_id,
+            write_profile_sequence_id,
+        );
+        handler.icc_version = 0x4300000;
+
+        let read = match round_trip(&handler, &TagValue::Sequence(seq.clone()))? {
+            (TagValue::Sequence(read), _, _) => read,
+            (value, _, _) => panic!("unexpected tag value {:?}", value),
+        };
+
+        assert_eq!(read.get_count(), 2);
+        for (read, expected) in read.get_descriptors().iter().zip(seq.get_descriptors()) {
+            assert_eq!(read.get_profile_id(), expected.get_profile_id());
+            assert_eq!(
+                read.get_description()
+                    .get(Mlu::NO_LANGUAGE, Mlu::NO_COUNTRY),
+                expected
+                    .get_description()
+                    .get(Mlu::NO_LANGUAGE, Mlu::NO_COUNTRY)
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_profile_sequence_desc_rejects_unknown_text_types() {
+        let mut context = Context::new(None);
+        let handler = TypeHandler::new(
+            tag_type::PROFILE_SEQUENCE_DESC,
+            read_profile_sequence_desc,
+            write_profile_sequence_desc,
+        );
+
+        let mut data = vec![0, 0, 0, 1];
+        data.extend([0; 20]);
+        data.extend(b"XYZ \0\0\0\0");
+        data.extend([0; 16]);
+
+        let mut io = FileMemReadOnly::new(data.as_slice());
+        let mut count = 0;
+        let err =
+            read_profile_sequence_desc(&mut context, &handler, &mut io, &mut count, data.len())
+                .unwrap_err();
+
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_read_profile_sequence_merges_ids() -> io::Result<()> {
+        let mut context = Context::new(None);
+        let mut profile = Profile::new();
+        profile.set_version(4.3);
+        profile.write_profile_sequence_thr(&mut context, &test_seq())?;
+        let saved = profile.save_to_mem_thr(&mut context)?;
+        let mut reopened = Profile::open_from_mem_thr(&mut context, &saved)?;
+
+        assert!(reopened
+            .read_raw_tag(signatures::tag::PROFILE_SEQUENCE_ID)
+            .is_ok());
+        let seq = reopened.read_profile_sequence_thr(&mut context)?;
+
+        let descriptor = seq.get(1).unwrap();
+        assert_eq!(descriptor.get_profile_id(), ProfileID::from_bytes([2; 16]));
+        assert_eq!(
+            descriptor
+                .get_model()
+                .get(Mlu::NO_LANGUAGE, Mlu::NO_COUNTRY),
+            Some("Press")
+        );
+        assert_eq!(
+            descriptor
+                .get_description()
+                .get(Mlu::NO_LANGUAGE, Mlu::NO_COUNTRY),
+            Some("Press profile")
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_profile_sequence_without_ids() -> io::Result<()> {
+        let mut context = Context::new(None);
+        let mut profile = Profile::new();
+        profile.set_version(2.1);
+
+        let missing = profile.read_profile_sequence_thr(&mut context).unwrap_err();
+        assert_eq!(missing.kind(), ErrorKind::NotFound);
+
+        profile.write_profile_sequence_thr(&mut context, &test_seq())?;
+        let saved = profile.save_to_mem_thr(&mut context)?;
+        let mut reopened = Profile::open_from_mem_thr(&mut context, &saved)?;
+
+        let err = reopened
+            .read_raw_tag(signatures::tag::PROFILE_SEQUENCE_ID)
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::NotFound);
+        let seq = reopened.read_profile_sequence_thr(&mut context)?;
+        assert_eq!(seq.get_count(), 2);
+        assert_eq!(
+            seq.get(0).unwrap().get_profile_id(),
+            ProfileID::from_bytes([0; 16])
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_merge_ids_needs_the_same_length() {
+        let desc = test_seq();
+        let mut ids = Sequence::new();
+        ids.push(SequenceDescriptor::new());
+
+        let merged = desc.clone().merge_ids(&ids);
+
+        assert_eq!(
+            merged.get(0).unwrap().get_profile_id(),
+            desc.get(0).unwrap().get_profile_id()
+        );
+    }
+}
@@ -0,0 +1,484 @@
+//! `mluc`, `desc` and `text` tag types, ICC.1:2022 clauses 10.15 and 10.24, and ICC.1:2001-04 clause 6.5.17.
+
+use std::io::{Error, ErrorKind, Result};
+
+use crate::{
+    io::IOHandler,
+    plugins::TypeHandler,
+    state::{Context, ErrorCode},
+    types::{
+        mlu::{code_to_u16, u16_to_code},
+        Mlu, TagValue,
+    },
+};
+
+/// Size of the type base, the record count and the record size preceding the `mluc` records.
+const MLU_HEADER_SIZE: usize = 16;
+/// Size of a single `mluc` record.
+const MLU_RECORD_SIZE: usize = 12;
+/// Size of the ScriptCode part of a `desc` tag: the code, the count and 67 bytes of text.
+const SCRIPT_CODE_SIZE: usize = 2 + 1 + 67;
+
+/// Decodes ASCII text up to its first NUL. Bytes beyond 7 bits are taken as Latin-1, as many profiles use them.
+pub(super) fn decode_ascii(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .take_while(|b| **b != 0)
+        .map(|b| *b as char)
+        .collect()
+}
+
+/// Encodes text as NUL terminated ASCII, replacing anything outside of 7 bits.
+pub(super) fn encode_ascii(text: &str) -> Vec<u8> {
+    text.chars()
+        .map(|c| {
+            if c.is_ascii() && c != '\0' {
+                c as u8
+            } else {
+                b'?'
+            }
+        })
+        .chain([0])
+        .collect()
+}
+
+/// Decodes UTF-16BE text, dropping an odd trailing byte and anything after the first NUL.
+fn decode_utf16(bytes: &[u8]) -> String {
+    let units = bytes
+        .chunks_exact(2)
+        .map(|unit| u16::from_be_bytes([unit[0], unit[1]]))
+        .take_while(|unit| *unit != 0)
+        .collect::<Vec<_>>();
+
+    String::from_utf16_lossy(&units)
+}
+
+fn read_bytes(io: &mut dyn IOHandler, len: usize) -> Result<Vec<u8>> {
+    let mut bytes = vec![0u8; len];
+    io.read(&mut bytes)?;
+
+    Ok(bytes)
+}
+
+pub(crate) fn read_mlu(
+    context: &mut Context,
+    _handler: &TypeHandler,
+    io: &mut dyn IOHandler,
+    num_items: &mut usize,
+    size_of_tag: usize,
+) -> Result<TagValue> {
+    let count = io.read_u32()? as usize;
+    let record_len = io.read_u32()? as usize;
+
+    if record_len != MLU_RECORD_SIZE {
+        context.signal_error(
+            ErrorCode::UnknownExtension,
+            "multiLocalizedUnicodeType of len != 12 is not supported.",
+        );
+        return Err(Error::from(ErrorKind::InvalidData));
+    }
+
+    // Offsets are relative to the start of the tag, including its type base
+    let size_of_header = count
+        .checked_mul(MLU_RECORD_SIZE)
+        .and_then(|size| size.checked_add(MLU_HEADER_SIZE))
+        .filter(|size| *size <= size_of_tag + 8)
+        .ok_or_else(|| Error::from(ErrorKind::InvalidData))?;
+
+    let mut records = Vec::with_capacity(count);
+    let mut largest_position = 0;
+    for _ in 0..count {
+        let language = io.read_u16()?;
+        let country = io.read_u16()?;
+        let len = io.read_u32()? as usize;
+        let offset = io.read_u32()? as usize;
+
+        if offset < size_of_header || offset + len > size_of_tag + 8 {
+            return Err(Error::from(ErrorKind::InvalidData));
+        }
+
+        let begin = offset - size_of_header;
+        largest_position = largest_position.max(begin + len);
+        records.push((language, country, begin, len));
+    }
+
+    let pool = read_bytes(io, largest_position)?;
+
+    let mut mlu = Mlu::new();
+    for (language, country, begin, len) in records {
+        mlu.set(
+            u16_to_code(language),
+            u16_to_code(country),
+            &decode_utf16(&pool[begin..begin + len]),
+        );
+    }
+
+    *num_items = 1;
+    Ok(TagValue::Mlu(mlu))
+}
+
+pub(crate) fn write_mlu(
+    _context: &mut Context,
+    _handler: &TypeHandler,
+    io: &mut dyn IOHandler,
+    value: &TagValue,
+    _num_items: usize,
+) -> Result<()> {
+    let mlu = match value {
+        TagValue::Mlu(mlu) => mlu,
+        _ => return Err(Error::from(ErrorKind::InvalidInput)),
+    };
+
+    let entries = mlu.get_entries();
+    io.write_u32(entries.len() as u32)?;
+    io.write_u32(MLU_RECORD_SIZE as u32)?;
+
+    let strings = entries
+        .iter()
+        .map(|entry| entry.get_value().encode_utf16().collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+
+    let mut offset = MLU_HEADER_SIZE + MLU_RECORD_SIZE * entries.len();
+    for (entry, string) in entries.iter().zip(strings.iter()) {
+        let len = string.len() * 2;
+
+        io.write_u16(code_to_u16(entry.get_language()))?;
+        io.write_u16(code_to_u16(entry.get_country()))?;
+        io.write_u32(len as u32)?;
+        io.write_u32(offset as u32)?;
+
+        offset += len;
+    }
+
+    for string in strings {
+        io.write_u16_array(&string)?;
+    }
+
+    Ok(())
+}
+
+pub(crate) fn read_text(
+    _context: &mut Context,
+    _handler: &TypeHandler,
+    io: &mut dyn IOHandler,
+    num_items: &mut usize,
+    size_of_tag: usize,
+) -> Result<TagValue> {
+    let text = read_bytes(io, size_of_tag)?;
+
+    *num_items = 1;
+    Ok(TagValue::Mlu(Mlu::from_text(&decode_ascii(&text))))
+}
+
+pub(crate) fn write_text(
+    _context: &mut Context,
+    _handler: &TypeHandler,
+    io: &mut dyn IOHandler,
+    value: &TagValue,
+    _num_items: usize,
+) -> Result<()> {
+    match value {
+        TagValue::Mlu(mlu) => io.write(&encode_ascii(
+            mlu.get(Mlu::NO_LANGUAGE, Mlu::NO_COUNTRY)
+                .unwrap_or_default(),
+        )),
+        _ => Err(Error::from(ErrorKind::InvalidInput)),
+    }
+}
+
+pub(crate) fn read_text_description(
+    _context: &mut Context,
+    _handler: &TypeHandler,
+    io: &mut dyn IOHandler,
+    num_items: &mut usize,
+    size_of_tag: usize,
+) -> Result<TagValue> {
+    // One dword should be there
+    if size_of_tag < 4 {
+        return Err(Error::from(ErrorKind::InvalidData));
+    }
+    let mut remaining = size_of_tag - 4;
+
+    // Many profiles in the wild claim more ASCII than the tag holds, so take what is there
+    let ascii_count = (io.read_u32()? as usize).min(remaining);
+    let ascii = decode_ascii(&read_bytes(io, ascii_count)?);
+    remaining -= ascii_count;
+
+    // From here on be tolerant, as the Unicode and ScriptCode parts are often missing or truncated
+    let mut text = ascii;
+    if remaining >= 8 {
+        let _unicode_language = io.read_u32()?;
+        let unicode_count = io.read_u32()? as usize;
+        remaining -= 8;
+
+        if let Some(unicode_len) = unicode_count.checked_mul(2).filter(|len| *len <= remaining) {
+            let unicode = decode_utf16(&read_bytes(io, unicode_len)?);
+            remaining -= unicode_len;
+
+            // Prefer the Unicode description, which can hold what ASCII cannot
+            if !unicode.is_empty() {
+                text = unicode;
+            }
+
+            // The ScriptCode part is not used, but skip it as this type may come embedded in other types
+            if remaining >= SCRIPT_CODE_SIZE {
+                read_bytes(io, SCRIPT_CODE_SIZE)?;
+            }
+        }
+    }
+
+    *num_items = 1;
+    Ok(TagValue::Mlu(Mlu::from_text(&text)))
+}
+
+pub(crate) fn write_text_description(
+    _context: &mut Context,
+    _handler: &TypeHandler,
+    io: &mut dyn IOHandler,
+    value: &TagValue,
+    _num_items: usize,
+) -> Result<()> {
+    let text = match value {
+        TagValue::Mlu(mlu) => mlu
+            .get(Mlu::NO_LANGUAGE, Mlu::NO_COUNTRY)
+            .unwrap_or_default(),
+        _ => return Err(Error::from(ErrorKind::InvalidInput)),
+    };
+
+    let ascii = encode_ascii(text);
+    let unicode = text
+        .encode_utf16()
+        .take_while(|unit| *unit != 0)
+        .chain([0])
+        .collect::<Vec<_>>();
+
+    io.write_u32(ascii.len() as u32)?;
+    io.write(&ascii)?;
+
+    io.write_u32(0)?; // Unicode language code
+    io.write_u32(unicode.len() as u32)?;
+    io.write_u16_array(&unicode)?;
+
+    // ScriptCode code & count (unused)
+    io.write_u16(0)?;
+    io.write_u8(0)?;
+    io.write(&[0u8; 67])
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::{self, ErrorKind};
+
+    use crate::{
+        io::{AccessMode, FileMem, FileMemReadOnly},
+        plugins::{TagTypeReader, TagTypeWriter, TypeHandler},
+        state::Context,
+        testing::get_test_resource_path,
+        types::{signatures, Mlu, Profile, TagValue},
+    };
+
+    use super::*;
+    use test_case::test_case;
+
+    fn read(reader: TagTypeReader, data: &[u8]) -> io::Result<Mlu> {
+        let mut context = Context::new(None);
+        let handler = TypeHandler::new(signatures::tag_type::TEXT, reader, write_text);
+
+        let mut io = FileMemReadOnly::new(data);
+        let mut count = 0;
+        match reader(&mut context, &handler, &mut io, &mut count, data.len())? {
+            TagValue::Mlu(mlu) => Ok(mlu),
+            value => panic!("unexpected tag value {:?}", value),
+        }
+    }
+
+    fn write(writer: TagTypeWriter, mlu: &Mlu) -> io::Result<Vec<u8>> {
+        let mut context = Context::new(None);
+        let handler = TypeHandler::new(signatures::tag_type::TEXT, read_text, writer);
+
+        let mut mem = FileMem::new(Vec::new());
+        writer(
+            &mut context,
+            &handler,
+            &mut mem,
+            &TagValue::Mlu(mlu.clone()),
+            1,
+        )?;
+
+        Ok(mem.cursor.into_inner())
+    }
+
+    /// Prepends the type base, as `mluc` offsets count it.
+    fn with_type_base(data: &[u8]) -> Vec<u8> {
+        [b"mluc\0\0\0\0".as_slice(), data].concat()
+    }
+
+    fn read_mlu_tag(data: &[u8]) -> io::Result<Mlu> {
+        let tag = with_type_base(data);
+        let mut context = Context::new(None);
+        let handler = TypeHandler::new(
+            signatures::tag_type::MULTI_LOCALIZED_UNICODE,
+            read_mlu,
+            write_mlu,
+        );
+
+        let mut io = FileMemReadOnly::new(tag.as_slice());
+        io.read_type_base()?;
+        let mut count = 0;
+        match read_mlu(&mut context, &handler, &mut io, &mut count, tag.len() - 8)? {
+            TagValue::Mlu(mlu) => Ok(mlu),
+            value => panic!("unexpected tag value {:?}", value),
+        }
+    }
+
+    #[test]
+    fn test_read_text_tags_from_profile() -> io::Result<()> {
+        let mut context = Context::new(None);
+        let mut profile = Profile::open_from_file_thr(
+            &mut context,
+            get_test_resource_path("sRGB_v4_ICC_preference.icc"),
+            AccessMode::Read,
+        )?;
+
+        for sig in [
+            signatures::tag::PROFILE_DESCRIPTION,
+            signatures::tag::COPYRIGHT,
+        ] {
+            match profile.read_tag_thr(&mut context, sig)? {
+                TagValue::Mlu(mlu) => {
+                    assert_eq!(mlu.get_translation_codes(0), Some((['e', 'n'], ['U', 'S'])));
+                    assert!(!mlu.get(['e', 'n'], ['U', 'S']).unwrap().is_empty());
+                }
+                value => panic!("unexpected tag value {:?}", value),
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_mlu_round_trips() -> io::Result<()> {
+        let mut mlu = Mlu::new();
+        mlu.set(['e', 'n'], ['U', 'S'], "Color");
+        mlu.set(['d', 'e'], ['D', 'E'], "Farbe \u{1F3A8}");
+        mlu.set(['j', 'a'], ['J', 'P'], "");
+
+        let read = read_mlu_tag(&write(write_mlu, &mlu)?)?;
+
+        assert_eq!(read, mlu);
+        Ok(())
+    }
+
+    #[test]
+    fn test_mlu_drops_odd_trailing_byte() -> io::Result<()> {
+        let data = [
+            &[0, 0, 0, 1, 0, 0, 0, 12][..],
+            &[b'e', b'n', b'U', b'S', 0, 0, 0, 5, 0, 0, 0, 28],
+            &[0, b'H', 0, b'i', 0],
+        ]
+        .concat();
+
+        let mlu = read_mlu_tag(&data)?;
+
+        assert_eq!(mlu.get(['e', 'n'], ['U', 'S']), Some("Hi"));
+        Ok(())
+    }
+
+    #[test_case(&[0, 0, 0, 1, 0, 0, 0, 16]; "unknown record size")]
+    #[test_case(&[0, 0, 0, 1, 0, 0, 0, 12, 0, 0, 0, 0, 0, 0, 0, 2, 0, 0, 0, 4]; "offset inside header")]
+    #[test_case(&[0, 0, 0, 1, 0, 0, 0, 12, 0, 0, 0, 0, 0, 0, 0, 4, 0, 0, 0, 28]; "string past tag")]
+    #[test_case(&[0xff, 0xff, 0xff, 0xff, 0, 0, 0, 12]; "too many records")]
+    fn test_mlu_rejects_malformed_tags(data: &[u8]) {
+        let err = read_mlu_tag(data).unwrap_err();
+
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+
+    #[test_case(b"Hello\0\0\0", "Hello"; "nul padded")]
+    #[test_case(b"Hello", "Hello"; "unterminated")]
+    #[test_case(b"", ""; "empty")]
+    fn test_read_text(data: &[u8], expected: &str) -> io::Result<()> {
+        assert_eq!(read(read_text, data)?, Mlu::from_text(expected));
+        Ok(())
+    }
+
+    #[test]
+    fn test_text_round_trips_ascii() -> io::Result<()> {
+        let data = write(write_text, &Mlu::from_text("Copyright"))?;
+
+        assert_eq!(data, b"Copyright\0");
+        assert_eq!(
+            read(read_text, &write(write_text, &Mlu::from_text("Caf\u{e9}"))?)?,
+            Mlu::from_text("Caf?")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_text_description_round_trips() -> io::Result<()> {
+        let mlu = Mlu::from_text("Caf\u{e9} RGB");
+
+        let data = write(write_text_description, &mlu)?;
+
+        assert_eq!(&data[..4], &9u32.to_be_bytes());
+        assert_eq!(&data[4..13], b"Caf? RGB\0");
+        assert_eq!(read(read_text_description, &data)?, mlu);
+        Ok(())
+    }
+
+    #[test_case(&[0, 0, 0, 4, b'a', b'b', b'c', 0]; "ascii only")]
+    #[test_case(&[0, 0, 0, 9, b'a', b'b', b'c', 0]; "ascii count past tag")]
+    #[test_case(&[0, 0, 0, 4, b'a', b'b', b'c', 0, 0, 0, 0, 0, 0, 0, 0, 9, 0, b'x']; "unicode count past tag")]
+    #[test_case(&[0, 0, 0, 4, b'a', b'b', b'c', 0, 0, 0, 0, 0, 0, 0, 0, 0]; "empty unicode, no scriptcode")]
+    fn test_text_description_tolerates_malformed_tags(data: &[u8]) -> io::Result<()> {
+        assert_eq!(read(read_text_description, data)?, Mlu::from_text("abc"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_text_description_prefers_unicode() -> io::Result<()> {
+        let data = [
+            &[0, 0, 0, 4, b'a', b'b', b'c', 0][..],
+            &[0, 0, 0, 0, 0, 0, 0, 3, 0, b'a', 0x01, 0x01, 0, 0],
+        ]
+        .concat();
+
+        assert_eq!(
+            read(read_text_description, &data)?,
+            Mlu::from_text("a\u{101}")
+        );
+        Ok(())
+    }
+
+    #[test_case(2.1, b"desc"; "v2")]
+    #[test_case(4.3, b"mluc"; "v4")]
+    fn test_profile_description_type_follows_version(
+        version: f64,
+        expected: &[u8; 4],
+    ) -> io::Result<()> {
+        let mut context = Context::new(None);
+        let mut profile = Profile::new();
+        profile.set_version(version);
+        profile.write_tag_thr(
+            &mut context,
+            signatures::tag::PROFILE_DESCRIPTION,
+            TagValue::Mlu(Mlu::from_text("My profile")),
+        )?;
+        let saved = profile.save_to_mem_thr(&mut context)?;
+        let mut reopened = Profile::open_from_mem_thr(&mut context, &saved)?;
+
+        let raw = reopened.read_raw_tag(signatures::tag::PROFILE_DESCRIPTION)?;
+        assert_eq!(&raw[..4], expected);
+        match reopened.read_tag_thr(&mut context, signatures::tag::PROFILE_DESCRIPTION)? {
+            TagValue::Mlu(mlu) => {
+                assert_eq!(
+                    mlu.get(Mlu::NO_LANGUAGE, Mlu::NO_COUNTRY),
+                    Some("My profile")
+                )
+            }
+            value => panic!("unexpected tag value {:?}", value),
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,344 @@
+//! `vcgt` (video card gamma) tag type, Apple's private tag type for the calibration of display video cards.
+
+use std::io::{Error, ErrorKind, Result};
+
+use crate::{
+    io::IOHandler,
+    plugins::TypeHandler,
+    quick_saturate_word,
+    state::{Context, ErrorCode},
+    types::{TagValue, ToneCurve},
+};
+
+use super::lut::from_8_to_16;
+
+/// The curves are given as tables of 8 or 16 bit entries.
+const VCGT_TABLE_TYPE: u32 = 0;
+/// The curves are given as a gamma, a minimum and a maximum per channel.
+const VCGT_FORMULA_TYPE: u32 = 1;
+
+/// The parametric curve type formula curves are stored as.
+const VCGT_PARAMETRIC_TYPE: i32 = 5;
+
+fn read_vcgt_tables(
+    context: &mut Context,
+    io: &mut dyn IOHandler,
+    size_of_tag: usize,
+) -> Result<[ToneCurve; 3]> {
+    let channels = io.read_u16()? as usize;
+    let entries = io.read_u16()? as usize;
+    let mut bytes = io.read_u16()? as usize;
+
+    // Adobe's quirk fixup. Fixing broken profiles...
+    if entries == 256 && bytes == 1 && size_of_tag == 1576 {
+        bytes = 2;
+    }
+
+    if bytes != 1 && bytes != 2 {
+        context.signal_error(
+            ErrorCode::UnknownExtension,
+            format!("Unsupported bit depth for VCGT '{}'", bytes * 8),
+        );
+        return Err(Error::from(ErrorKind::InvalidData));
+    }
+
+    if channels == 0 || 4 + 6 + channels * entries * bytes > size_of_tag {
+        return Err(Error::from(ErrorKind::InvalidData));
+    }
+
+    let mut curves = Vec::with_capacity(channels.min(3));
+    for _ in 0..channels {
+        let mut table = vec![0u16; entries];
+        if bytes == 1 {
+            let mut table8 = vec![0u8; entries];
+            io.read(&mut table8)?;
+            for (v16, v8) in table.iter_mut().zip(table8) {
+                *v16 = from_8_to_16(v8);
+            }
+        } else {
+            io.read_u16_array(&mut table)?;
+        }
+
+        // Only the red, green and blue channels are kept
+        if curves.len() < 3 {
+            let curve = ToneCurve::build_tabulated_16(context, &table)
+                .ok_or_else(|| Error::from(ErrorKind::InvalidData))?;
+            curves.push(curve);
+        }
+    }
+
+    // A single channel applies to all of them
+    while curves.len() < 3 {
+        curves.push(curves[curves.len() - 1].clone());
+    }
+
+    curves
+        .try_into()
+        .map_err(|_| Error::from(ErrorKind::InvalidData))
+}
+
+fn read_vcgt_formulas(context: &mut Context, io: &mut dyn IOHandler) -> Result<[ToneCurve; 3]> {
+    let mut curves = Vec::with_capacity(3);
+    for _ in 0..3 {
+        let gamma = io.read_s15f16()?;
+        let min = io.read_s15f16()?;
+        let max = io.read_s15f16()?;
+
+        // Parametric curve type 5 is:
+        // Y = (aX + b)^Gamma + e | X >= d
+        // Y = cX + f             | X < d
+
+        // vcgt formula is:
+        // Y = (Max - Min) * (X ^ Gamma) + Min
+
+        // So, the translation is
+        // a = (Max - Min) ^ ( 1 / Gamma)
+        // e = Min
+        // b=c=d=f=0
+        let params = [
+            gamma,
+            (max - min).powf(1.0 / gamma),
+            0.0,
+            0.0,
+            0.0,
+            min,
+            0.0,
+        ];
+
+        let curve = ToneCurve::build_parametric(context, VCGT_PARAMETRIC_TYPE, &params)
+            .ok_or_else(|| Error::from(ErrorKind::InvalidData))?;
+        curves.push(curve);
+    }
+
+    curves
+        .try_into()
+        .map_err(|_| Error::from(ErrorKind::InvalidData))
+}
+
+pub(crate) fn read_vcgt(
+    context: &mut Context,
+    _handler: &TypeHandler,
+    io: &mut dyn IOHandler,
+    num_items: &mut usize,
+    size_of_tag: usize,
+) -> Result<TagValue> {
+    let curves = match io.read_u32()? {
+        VCGT_TABLE_TYPE => read_vcgt_tables(context, io, size_of_tag)?,
+        VCGT_FORMULA_TYPE => read_vcgt_formulas(context, io)?,
+        r#type => {
+            context.signal_error(
+                ErrorCode::UnknownExtension,
+                format!("Unsupported tag type for VCGT '{}'", r#type),
+            );
+            return Err(Error::from(ErrorKind::InvalidData));
+        }
+    };
+
+    *num_items = 1;
+    Ok(TagValue::VideoCardGamma(Box::new(curves)))
+}
+
+/// Writes the curves as formulas when they all are parametric curves of type 5, and as tables of 256 16 bit entries
+/// otherwise.
+pub(crate) fn write_vcgt(
+    _context: &mut Context,
+    _handler: &TypeHandler,
+    io: &mut dyn IOHandler,
+    value: &TagValue,
+    _num_items: usize,
+) -> Result<()> {
+    let curves = match value {
+        TagValue::VideoCardGamma(curves) => curves,
+        _ => return Err(Error::from(ErrorKind::InvalidInput)),
+    };
+
+    if curves
+        .iter()
+        .all(|curve| curve.get_parametric_type() == VCGT_PARAMETRIC_TYPE)
+    {
+        io.write_u32(VCGT_FORMULA_TYPE)?;
+
+        for curve in curves.iter() {
+            let params = curve.get_params().unwrap();
+            let gamma = params[0];
+            let min = params[5];
+            let max = params[1].powf(gamma) + min;
+
+            io.write_s15f16(gamma)?;
+            io.write_s15f16(min)?;
+            io.write_s15f16(max)?;
+        }
+    } else {
+        // Always store as a table of 256 words
+        io.write_u32(VCGT_TABLE_TYPE)?;
+        io.write_u16(3)?;
+        io.write_u16(256)?;
+        io.write_u16(2)?;
+
+        for curve in curves.iter() {
+            for j in 0..256 {
+                let v = curve.eval_f32(j as f32 / 255.0);
+                io.write_u16(quick_saturate_word(v as f64 * 65535.0))?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use std::io;
+
+    use crate::{
+        io::{FileMem, FileMemReadOnly},
+        plugins::TypeHandler,
+        state::Context,
+        types::{signatures, Profile, TagValue, ToneCurve},
+    };
+
+    use super::*;
+
+    fn write(value: &TagValue) -> io::Result<Vec<u8>> {
+        let mut context = Context::new(None);
+        let handler = TypeHandler::new(signatures::tag_type::VCGT, read_vcgt, write_vcgt);
+
+        let mut mem = FileMem::new(Vec::new());
+        write_vcgt(&mut context, &handler, &mut mem, value, 1)?;
+        Ok(mem.cursor.into_inner())
+    }
+
+    fn read(data: &[u8], size_of_tag: usize) -> io::Result<[ToneCurve; 3]> {
+        let mut context = Context::new(None);
+        let handler = TypeHandler::new(signatures::tag_type::VCGT, read_vcgt, write_vcgt);
+
+        let mut io = FileMemReadOnly::new(data);
+        let mut count = 0;
+        match read_vcgt(&mut context, &handler, &mut io, &mut count, size_of_tag)? {
+            TagValue::VideoCardGamma(curves) => Ok(*curves),
+            value => panic!("unexpected tag value {:?}", value),
+        }
+    }
+
+    fn table_header(channels: u16, entries: u16, bytes: u16) -> Vec<u8> {
+        let mut data = VCGT_TABLE_TYPE.to_be_bytes().to_vec();
+        data.extend_from_slice(&channels.to_be_bytes());
+        data.extend_from_slice(&entries.to_be_bytes());
+        data.extend_from_slice(&bytes.to_be_bytes());
+        data
+    }
+
+    #[test]
+    fn test_tables_round_trip() -> io::Result<()> {
+        let mut context = Context::new(None);
+        let curves = [
+            ToneCurve::build_gamma(&mut context, 1.8).unwrap(),
+            ToneCurve::build_gamma(&mut context, 2.2).unwrap(),
+            ToneCurve::build_tabulated_16(&mut context, &[0, 0x4000, 0xffff]).unwrap(),
+        ];
+
+        let data = write(&TagValue::VideoCardGamma(Box::new(curves.clone())))?;
+        assert_eq!(data.len(), 4 + 6 + 3 * 256 * 2);
+
+        let read = read(&data, data.len())?;
+        for (read, curve) in read.iter().zip(curves.iter()) {
+            for v in (0..=0xffffu32).step_by(0x101) {
+                let v = v as u16;
+                assert!((read.eval_u16(v) as i32 - curve.eval_u16(v) as i32).abs() <= 1);
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_single_8_bit_table_applies_to_all_channels() -> io::Result<()> {
+        let mut data = table_header(1, 3, 1);
+        data.extend_from_slice(&[0, 0x80, 0xff]);
+
+        let curves = read(&data, data.len())?;
+        for curve in curves.iter() {
+            assert_eq!(curve.get_table16(), [0, 0x8080, 0xffff]);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_unsupported_bit_depth_fails() {
+        let mut data = table_header(3, 2, 4);
+        data.extend_from_slice(&[0; 24]);
+
+        assert!(read(&data, data.len()).is_err());
+    }
+
+    #[test]
+    fn test_adobe_quirk_reads_16_bit_entries() -> io::Result<()> {
+        let mut data = table_header(3, 256, 1);
+        for _ in 0..3 {
+            for i in 0..256u16 {
+                data.extend_from_slice(&(i * 0x101).to_be_bytes());
+            }
+        }
+
+        let curves = read(&data, 1576)?;
+        assert_eq!(curves[2].get_table16()[255], 0xffff);
+        assert_eq!(curves[0].get_table16()[128], 0x8080);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_formulas_round_trip() -> io::Result<()> {
+        let mut context = Context::new(None);
+        let (gamma, min, max): (f64, f64, f64) = (2.2, 0.0625, 0.875);
+        let params = [
+            gamma,
+            (max - min).powf(1.0 / gamma),
+            0.0,
+            0.0,
+            0.0,
+            min,
+            0.0,
+        ];
+        let curve = ToneCurve::build_parametric(&mut context, 5, &params).unwrap();
+        let curves = [curve.clone(), curve.clone(), curve];
+
+        let data = write(&TagValue::VideoCardGamma(Box::new(curves)))?;
+        assert_eq!(data.len(), 4 + 3 * 3 * 4);
+
+        let read = read(&data, data.len())?;
+        for curve in read.iter() {
+            assert_eq!(curve.get_parametric_type(), 5);
+            let read_params = curve.get_params().unwrap();
+            assert!((read_params[0] - gamma).abs() < 1e-4);
+            assert!((read_params[5] - min).abs() < 1e-4);
+            assert!((curve.eval_f32(1.0) as f64 - max).abs() < 1e-4);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_vcgt_round_trips_through_profile() -> io::Result<()> {
+        let mut context = Context::new(None);
+        let curve = ToneCurve::build_gamma(&mut context, 1.0).unwrap();
+        let mut profile = Profile::new();
+        profile.write_tag_thr(
+            &mut context,
+            signatures::tag::VCGT,
+            TagValue::VideoCardGamma(Box::new([curve.clone(), curve.clone(), curve])),
+        )?;
+        let saved = profile.save_to_mem_thr(&mut context)?;
+        let mut reopened = Profile::open_from_mem_thr(&mut context, &saved)?;
+
+        match reopened.read_tag_thr(&mut context, signatures::tag::VCGT)? {
+            TagValue::VideoCardGamma(curves) => {
+                assert_eq!(curves[1].eval_u16(0x1234), 0x1234);
+            }
+            value => panic!("unexpected tag value {:?}", value),
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,34 @@
+use super::{
+    Chromaticity, DateTimeNumber, Dict, Measurement, Mlu, NamedColorList, Pipeline, Sequence,
+    Signature, ToneCurve, ViewingConditions, CIEXYZ,
+};
+
+/// The typed contents of a tag, as read from or written to a [`Profile`](super::Profile).
+///
+/// Which variant a tag holds depends on the tag type it was stored as. For example, a `rXYZ` tag holds an
+/// [`XYZ`](TagValue::XYZ) value, while a `rTRC` tag holds a [`Curve`](TagValue::Curve).
+#[derive(Clone, Debug)]
+pub enum TagValue {
+    XYZ(CIEXYZ),
+    S15Fixed16Array(Vec<f64>),
+    U16Fixed16Array(Vec<f64>),
+    U8Array(Vec<u8>),
+    U16Array(Vec<u16>),
+    U32Array(Vec<u32>),
+    U64Array(Vec<u64>),
+    Curve(ToneCurve),
+    Pipeline(Pipeline),
+    Mlu(Mlu),
+    NamedColorList(NamedColorList),
+    Sequence(Sequence),
+    Chromaticity(Chromaticity),
+    /// The order in which the channels of a device are printed, as channel indices.
+    ColorantOrder(Vec<u8>),
+    Measurement(Measurement),
+    ViewingConditions(ViewingConditions),
+    DateTime(DateTimeNumber),
+    Signature(Signature),
+    /// The red, green and blue curves of a video card gamma tag.
+    VideoCardGamma(Box<[ToneCurve; 3]>),
+    Dict(Dict),
+}
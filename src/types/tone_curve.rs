@@ -1,12 +1,380 @@
-use crate::plugins::{InterpParams, ParametricCurveEvaluator};
+use crate::{
+    plugins::{get_parametric_curve_by_type, ParametricCurveEvaluator, MINUS_INF, PLUS_INF},
+    quick_saturate_word,
+    state::{Context, ErrorCode},
+};
 
 use super::CurveSegment;
 
+/// The maximum number of entries in the 16 bit table of a `ToneCurve`.
+const MAX_TABLE_ENTRIES: usize = 65530;
+
+/// A tone curve, mapping a single channel through either segmented functions or a table of 16 bit values.
+///
+/// Segmented curves (see [`build_segmented`](ToneCurve::build_segmented)) are evaluated exactly in floating point, and
+/// also keep a 16 bit table approximating them. Tabulated curves (see
+/// [`build_tabulated_16`](ToneCurve::build_tabulated_16)) only have the table, which is linearly interpolated.
+#[derive(Clone, Debug)]
 pub struct ToneCurve {
-    interp_params: InterpParams,
-    num_segments: usize,
-    segments: Box<[CurveSegment]>,
-    seg_interp: Box<[Box<[InterpParams]>]>,
-    evals: Option<Box<[ParametricCurveEvaluator]>>,
-    table16: Option<Box<[u16]>>,
+    segments: Vec<CurveSegment>,
+    evals: Vec<Option<ParametricCurveEvaluator>>,
+    table16: Vec<u16>,
+}
+
+impl ToneCurve {
+    fn new(
+        context: &mut Context,
+        segments: &[CurveSegment],
+        table16: Vec<u16>,
+    ) -> Option<ToneCurve> {
+        // We allow huge tables, which are then restricted for smoothing operations
+        if table16.len() > MAX_TABLE_ENTRIES {
+            context.signal_error(
+                ErrorCode::Range,
+                format!(
+                    "Couldn't create tone curve of more than {} entries",
+                    MAX_TABLE_ENTRIES
+                ),
+            );
+            return None;
+        }
+
+        if table16.is_empty() && segments.is_empty() {
+            context.signal_error(
+                ErrorCode::Range,
+                "Couldn't create tone curve with zero segments and no table",
+            );
+            return None;
+        }
+
+        let mut evals = Vec::with_capacity(segments.len());
+        for segment in segments {
+            // Type == 0 means segment is sampled
+            if segment.r#type == 0 {
+                evals.push(None);
+                continue;
+            }
+
+            match get_parametric_curve_by_type(context, segment.r#type) {
+                Some((eval, _)) => evals.push(Some(eval)),
+                None => {
+                    context.signal_error(
+                        ErrorCode::UnknownExtension,
+                        format!("Invalid parametric curve type {}", segment.r#type),
+                    );
+                    return None;
+                }
+            }
+        }
+
+        Some(ToneCurve {
+            segments: segments.to_vec(),
+            evals,
+            table16,
+        })
+    }
+
+    /// Builds a curve from a table of 16 bit values, equally spaced across the domain.
+    pub fn build_tabulated_16(context: &mut Context, values: &[u16]) -> Option<ToneCurve> {
+        Self::new(context, &[], values.to_vec())
+    }
+
+    /// Builds a curve from segments, which must be sorted and must not overlap.
+    ///
+    /// The 16 bit table is computed by evaluating the segments at 4096 points, or at just 2 points for an identity
+    /// gamma curve.
+    pub fn build_segmented(context: &mut Context, segments: &[CurveSegment]) -> Option<ToneCurve> {
+        // Optimization for identity curves.
+        let grid_points = if segments.len() == 1 && segments[0].r#type == 1 {
+            entries_by_gamma(segments[0].params[0])
+        } else {
+            4096
+        };
+
+        let mut curve = Self::new(context, segments, vec![0u16; grid_points])?;
+
+        // Once we have the floating point version, we can approximate a 16 bit table of 4096 entries for performance
+        // reasons. This table would normally not be used except on 8/16 bits transforms.
+        for i in 0..grid_points {
+            let r = i as f64 / (grid_points - 1) as f64;
+            curve.table16[i] = quick_saturate_word(curve.eval_segmented(r) * 65535.0);
+        }
+
+        Some(curve)
+    }
+
+    /// Builds a curve from a single parametric function of type `r#type`, covering the whole domain.
+    ///
+    /// `params` must hold at least as many parameters as the function type requires.
+    pub fn build_parametric(
+        context: &mut Context,
+        r#type: i32,
+        params: &[f64],
+    ) -> Option<ToneCurve> {
+        let count = match get_parametric_curve_by_type(context, r#type) {
+            Some((_, count)) => count as usize,
+            None => {
+                context.signal_error(
+                    ErrorCode::UnknownExtension,
+                    format!("Invalid parametric curve type {}", r#type),
+                );
+                return None;
+            }
+        };
+
+        if params.len() < count {
+            context.signal_error(
+                ErrorCode::Range,
+                format!(
+                    "Parametric curve type {} needs {} parameters, got {}",
+                    r#type,
+                    count,
+                    params.len()
+                ),
+            );
+            return None;
+        }
+
+        let mut segment = CurveSegment {
+            x0: MINUS_INF as f32,
+            x1: PLUS_INF as f32,
+            r#type,
+            params: [0.0; 10],
+            sampled_points: Vec::new(),
+        };
+        segment.params[..count].copy_from_slice(&params[..count]);
+
+        Self::build_segmented(context, &[segment])
+    }
+
+    /// Builds the curve `Y = X ^ gamma`.
+    pub fn build_gamma(context: &mut Context, gamma: f64) -> Option<ToneCurve> {
+        Self::build_parametric(context, 1, &[gamma])
+    }
+
+    pub fn get_segments(&self) -> &[CurveSegment] {
+        &self.segments
+    }
+
+    /// Returns the 16 bit table approximating the curve.
+    pub fn get_table16(&self) -> &[u16] {
+        &self.table16
+    }
+
+    /// Returns the type of the parametric function of a single segment curve, or 0 for any other curve.
+    pub fn get_parametric_type(&self) -> i32 {
+        match self.segments.as_slice() {
+            [segment] => segment.r#type,
+            _ => 0,
+        }
+    }
+
+    /// Returns the parameters of the parametric function of a single segment curve.
+    pub fn get_params(&self) -> Option<&[f64; 10]> {
+        match self.segments.as_slice() {
+            [segment] => Some(&segment.params),
+            _ => None,
+        }
+    }
+
+    /// Evaluates the curve at `v` in floating point.
+    ///
+    /// Tabulated curves are limited to the precision of their 16 bit table.
+    pub fn eval_f32(&self, v: f32) -> f32 {
+        // Check for 16 bits table. If so, this is a limited-precision tone curve
+        if self.segments.is_empty() {
+            let r#in = quick_saturate_word(v as f64 * 65535.0);
+            let out = self.eval_u16(r#in);
+
+            return (out as f64 / 65535.0) as f32;
+        }
+
+        self.eval_segmented(v as f64) as f32
+    }
+
+    /// Evaluates the curve at `v` using its 16 bit table.
+    pub fn eval_u16(&self, v: u16) -> u16 {
+        let table = &self.table16;
+        let domain = table.len() - 1;
+
+        // if last value or just one point
+        if v == 0xffff || domain == 0 {
+            return table[domain];
+        }
+
+        let val3 = to_fixed_domain(domain as i64 * v as i64);
+        let cell0 = (val3 >> 16) as usize; // Cell is 16 MSB bits
+        let rest = val3 & 0xFFFF; // Rest is 16 LSB bits
+
+        let y0 = table[cell0] as i64;
+        let y1 = table[cell0 + 1] as i64;
+
+        linear_interp(rest, y0, y1)
+    }
+
+    fn eval_segmented(&self, r: f64) -> f64 {
+        for (segment, eval) in self.segments.iter().zip(self.evals.iter()).rev() {
+            // Check for domain
+            if r <= segment.x0 as f64 || r > segment.x1 as f64 {
+                continue;
+            }
+
+            let out = match eval {
+                Some(eval) => eval(segment.r#type, &segment.params, r),
+                None => {
+                    let r1 = (r as f32 - segment.x0) / (segment.x1 - segment.x0);
+                    eval_sampled(&segment.sampled_points, r1) as f64
+                }
+            };
+
+            return if out.is_infinite() && out > 0.0 {
+                PLUS_INF
+            } else if out.is_infinite() {
+                MINUS_INF
+            } else {
+                out
+            };
+        }
+
+        MINUS_INF
+    }
+}
+
+/// Returns the number of table entries needed by a gamma curve, identities only need 2.
+fn entries_by_gamma(gamma: f64) -> usize {
+    if (gamma - 1.0).abs() < 0.001 {
+        2
+    } else {
+        4096
+    }
+}
+
+/// Linearly interpolates the equally spaced `points` at `value`.
+fn eval_sampled(points: &[f32], value: f32) -> f32 {
+    if points.is_empty() {
+        return 0.0;
+    }
+
+    let value = if value.is_nan() {
+        0.0
+    } else {
+        value.clamp(0.0, 1.0)
+    };
+    let domain = (points.len() - 1) as f32;
+
+    // if last value or just one point
+    if value == 1.0 || domain == 0.0 {
+        return points[points.len() - 1];
+    }
+
+    let val2 = domain * value;
+    let cell0 = val2.floor() as usize;
+    let cell1 = val2.ceil() as usize;
+    let rest = val2 - cell0 as f32;
+
+    let y0 = points[cell0];
+    let y1 = points[cell1];
+
+    y0 + (y1 - y0) * rest
+}
+
+/// Converts a value in the range of `0..=0xffff * domain` to 15.16 fixed point.
+fn to_fixed_domain(a: i64) -> i64 {
+    a + ((a + 0x7fff) / 0xffff)
+}
+
+/// Interpolates between `l` and `h` by the 16 bit fraction `a`.
+fn linear_interp(a: i64, l: i64, h: i64) -> u16 {
+    let dif = (h - l) * a + 0x8000;
+    ((dif >> 16) + l) as u16
+}
+
+#[cfg(test)]
+mod test {
+    use test_case::test_case;
+
+    use crate::{plugins::PLUS_INF, state::Context, types::CurveSegment};
+
+    use super::ToneCurve;
+
+    #[test_case(1.0, 2; "identity")]
+    #[test_case(2.2, 4096; "gamma")]
+    fn test_build_gamma(gamma: f64, entries: usize) {
+        let mut context = Context::new(None);
+
+        let curve = ToneCurve::build_gamma(&mut context, gamma).unwrap();
+
+        assert_eq!(curve.get_table16().len(), entries);
+        assert_eq!(curve.get_parametric_type(), 1);
+        assert_eq!(curve.get_params().unwrap()[0], gamma);
+        assert!((curve.eval_f32(0.5) - 0.5f32.powf(gamma as f32)).abs() < 1e-5);
+        assert_eq!(curve.eval_u16(0), 0);
+        assert_eq!(curve.eval_u16(0xffff), 0xffff);
+    }
+
+    #[test]
+    fn test_build_parametric_srgb() {
+        let mut context = Context::new(None);
+        let params = [2.4, 1.0 / 1.055, 0.055 / 1.055, 1.0 / 12.92, 0.04045];
+
+        let curve = ToneCurve::build_parametric(&mut context, 4, &params).unwrap();
+
+        assert!((curve.eval_f32(0.02) - 0.02 / 12.92).abs() < 1e-6);
+        assert!((curve.eval_f32(0.5) - 0.214_041).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_build_parametric_rejects_bad_input() {
+        let mut context = Context::new(None);
+
+        assert!(ToneCurve::build_parametric(&mut context, 42, &[1.0]).is_none());
+        assert!(ToneCurve::build_parametric(&mut context, 4, &[2.4, 1.0]).is_none());
+    }
+
+    #[test]
+    fn test_build_tabulated_16_interpolates() {
+        let mut context = Context::new(None);
+
+        let curve = ToneCurve::build_tabulated_16(&mut context, &[0, 0x8000, 0xffff]).unwrap();
+
+        assert_eq!(curve.get_parametric_type(), 0);
+        assert!(curve.get_segments().is_empty());
+        assert_eq!(curve.eval_u16(0x7fff), 0x8000);
+        assert!((curve.eval_u16(0x4000) as i32 - 0x4000).abs() <= 1);
+        assert!((curve.eval_f32(0.75) - 0.75).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_build_tabulated_16_rejects_empty_and_huge_tables() {
+        let mut context = Context::new(None);
+
+        assert!(ToneCurve::build_tabulated_16(&mut context, &[]).is_none());
+        assert!(ToneCurve::build_tabulated_16(&mut context, &[0; 65531]).is_none());
+    }
+
+    #[test]
+    fn test_build_segmented_with_sampled_segment() {
+        let mut context = Context::new(None);
+        let linear = CurveSegment {
+            x0: -PLUS_INF as f32,
+            x1: 0.0,
+            r#type: 1,
+            params: [1.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0],
+            sampled_points: Vec::new(),
+        };
+        let sampled = CurveSegment {
+            x0: 0.0,
+            x1: 1.0,
+            r#type: 0,
+            params: [0.0; 10],
+            sampled_points: vec![0.0, 0.25, 1.0],
+        };
+
+        let curve = ToneCurve::build_segmented(&mut context, &[linear, sampled]).unwrap();
+
+        assert_eq!(curve.get_parametric_type(), 0);
+        assert!((curve.eval_f32(-0.5) + 0.5).abs() < 1e-6);
+        assert!((curve.eval_f32(0.25) - 0.125).abs() < 1e-6);
+        assert!((curve.eval_f32(0.75) - 0.625).abs() < 1e-6);
+    }
 }
@@ -0,0 +1,11 @@
+use super::{StandardIlluminant, CIEXYZ};
+
+/// The viewing conditions a profile was made for, as stored in `view` tags.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ViewingConditions {
+    /// The absolute tristimulus values of the illuminant, in cd/m².
+    pub illuminant: CIEXYZ,
+    /// The absolute tristimulus values of the surround, in cd/m².
+    pub surround: CIEXYZ,
+    pub illuminant_type: StandardIlluminant,
+}